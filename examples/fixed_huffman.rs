@@ -1,17 +1,9 @@
 fn main() {
     let mut bit_lengths = vec![];
-    for _ in 0..144 {
-        bit_lengths.push(8);
-    }
-    for _ in 144..256 {
-        bit_lengths.push(9);
-    }
-    for _ in 256..280 {
-        bit_lengths.push(7);
-    }
-    for _ in 280..288 {
-        bit_lengths.push(8);
-    }
+    bit_lengths.extend([8; 144]);
+    bit_lengths.extend([9; 256 - 144]);
+    bit_lengths.extend([7; 280 - 256]);
+    bit_lengths.extend([8; 288 - 280]);
     let ll_codes = otus_deflate::generate_code(&bit_lengths);
 
     let d_length = [5; 32];
@@ -26,13 +18,11 @@ fn main() {
     let length_ct = otus_deflate::deflate::CONVERT_LENGTH.clone();
     let distance_ct = otus_deflate::deflate::CONVERT_DISTANCE.clone();
     println!("\nLength conversion table:");
-    for len in 3_usize..258 {
-        let (code, extra, bits) = length_ct[&len];
+    for (len, (code, extra, bits)) in length_ct.iter().enumerate().take(258).skip(3) {
         println!("{len} => {code} and {bits} in {extra} bits.")
     }
     println!("\nDistance conversion table:");
-    for distance in 1_usize..=32768 {
-        let (code, extra, bits) = distance_ct[&distance];
+    for (distance, (code, extra, bits)) in distance_ct.iter().enumerate().take(32769).skip(1) {
         println!("{distance} => {code} and {bits} in {extra} bits.")
     }
 }
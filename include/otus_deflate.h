@@ -0,0 +1,228 @@
+#ifndef OTUS_DEFLATE_H
+#define OTUS_DEFLATE_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit by hand. */
+
+#include <stdint.h>
+#define ZLIB_VERSION "1.3.1"
+
+typedef struct z_stream *z_streamp;
+
+#define deflateInit(strm, level) \
+  deflateInit_((strm), (level), ZLIB_VERSION, (int)sizeof(z_stream))
+#define deflateInit2(strm, level, method, windowBits, memLevel, strategy) \
+  deflateInit2_((strm), (level), (method), (windowBits), (memLevel), (strategy), \
+                ZLIB_VERSION, (int)sizeof(z_stream))
+#define inflateInit(strm) inflateInit_((strm), ZLIB_VERSION, (int)sizeof(z_stream))
+#define inflateInit2(strm, windowBits) \
+  inflateInit2_((strm), (windowBits), ZLIB_VERSION, (int)sizeof(z_stream))
+
+
+#define Z_NO_FLUSH 0
+
+#define Z_PARTIAL_FLUSH 1
+
+#define Z_SYNC_FLUSH 2
+
+#define Z_FULL_FLUSH 3
+
+#define Z_FINISH 4
+
+#define Z_BLOCK 5
+
+#define Z_TREES 6
+
+#define Z_OK 0
+
+#define Z_STREAM_END 1
+
+#define Z_NEED_DICT 2
+
+#define Z_ERRNO -1
+
+#define Z_STREAM_ERROR -2
+
+#define Z_DATA_ERROR -3
+
+#define Z_MEM_ERROR -4
+
+#define Z_BUF_ERROR -5
+
+#define Z_VERSION_ERROR -6
+
+#define Z_NO_COMPRESSION 0
+
+#define Z_BEST_SPEED 1
+
+#define Z_BEST_COMPRESSION 9
+
+#define Z_DEFAULT_COMPRESSION -1
+
+#define Z_FILTERED 1
+
+#define Z_HUFFMAN_ONLY 2
+
+#define Z_RLE 3
+
+#define Z_FIXED 4
+
+#define Z_DEFAULT_STRATEGY 0
+
+#define Z_DEFLATED 8
+
+#define MAX_WBITS 15
+
+#define DEF_MEM_LEVEL 8
+
+typedef struct internal_state internal_state;
+
+typedef void *(*alloc_func)(void*, unsigned int, unsigned int);
+
+typedef void (*free_func)(void*, void*);
+
+typedef struct z_stream {
+  const uint8_t *next_in;
+  unsigned int avail_in;
+  unsigned long total_in;
+  uint8_t *next_out;
+  unsigned int avail_out;
+  unsigned long total_out;
+  const char *msg;
+  struct internal_state *state;
+  /**
+   * Allocates the stream state when set together with `zfree`; buffers
+   * inside the state always come from the Rust global allocator.
+   */
+  alloc_func zalloc;
+  free_func zfree;
+  void *opaque;
+  int data_type;
+  unsigned long adler;
+  unsigned long reserved;
+} z_stream;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+const char *zlibVersion(void);
+
+/**
+ * # Safety
+ *
+ * `strm` must be null or point to a writable `z_stream` whose `zalloc`,
+ * `zfree` and `opaque` are initialised, and `version` must be null or a
+ * valid C string.
+ */
+int deflateInit2_(struct z_stream *strm,
+                  int level,
+                  int method,
+                  int windowBits,
+                  int memLevel,
+                  int strategy,
+                  const char *version,
+                  int streamSize);
+
+/**
+ * # Safety
+ *
+ * Same as [`deflateInit2_`].
+ */
+int deflateInit_(struct z_stream *strm, int level, const char *version, int streamSize);
+
+/**
+ * # Safety
+ *
+ * `strm` must be null or point to a stream set up by [`deflateInit2_`], with
+ * `next_in`/`avail_in` and `next_out`/`avail_out` describing valid buffers.
+ */
+int deflate(struct z_stream *strm, int flush);
+
+/**
+ * # Safety
+ *
+ * `strm` must be null or point to a `z_stream` whose `state` is null or was
+ * set up by [`deflateInit2_`] and not yet ended.
+ */
+int deflateEnd(struct z_stream *strm);
+
+/**
+ * # Safety
+ *
+ * Same as [`deflateInit2_`].
+ */
+int inflateInit2_(struct z_stream *strm, int windowBits, const char *version, int streamSize);
+
+/**
+ * # Safety
+ *
+ * Same as [`deflateInit2_`].
+ */
+int inflateInit_(struct z_stream *strm, const char *version, int streamSize);
+
+/**
+ * # Safety
+ *
+ * `strm` must be null or point to a stream set up by [`inflateInit2_`], with
+ * `next_in`/`avail_in` and `next_out`/`avail_out` describing valid buffers.
+ */
+int inflate(struct z_stream *strm, int flush);
+
+/**
+ * # Safety
+ *
+ * `strm` must be null or point to a `z_stream` whose `state` is null or was
+ * set up by [`inflateInit2_`] and not yet ended.
+ */
+int inflateEnd(struct z_stream *strm);
+
+unsigned long compressBound(unsigned long sourceLen);
+
+/**
+ * # Safety
+ *
+ * `dest_len` must be null or point to the writable length of `dest`, and
+ * `source` must be readable for `source_len` bytes.
+ */
+int compress2(uint8_t *dest,
+              unsigned long *destLen,
+              const uint8_t *source,
+              unsigned long sourceLen,
+              int level);
+
+/**
+ * # Safety
+ *
+ * Same as [`compress2`].
+ */
+int compress(uint8_t *dest, unsigned long *destLen, const uint8_t *source, unsigned long sourceLen);
+
+/**
+ * # Safety
+ *
+ * Same as [`compress2`].
+ */
+int uncompress(uint8_t *dest,
+               unsigned long *destLen,
+               const uint8_t *source,
+               unsigned long sourceLen);
+
+/**
+ * # Safety
+ *
+ * `buf` must be null or readable for `len` bytes.
+ */
+unsigned long crc32(unsigned long crc, const uint8_t *buf, unsigned int len);
+
+/**
+ * # Safety
+ *
+ * `buf` must be null or readable for `len` bytes.
+ */
+unsigned long adler32(unsigned long adler, const uint8_t *buf, unsigned int len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* OTUS_DEFLATE_H */
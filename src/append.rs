@@ -0,0 +1,279 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crc32fast::Hasher;
+
+use crate::{
+    gzip::{self, GzipHeader},
+    inflate::Inflater,
+    join::{unset_final, CHUNK_SIZE},
+    writer::DeflateWriter,
+};
+
+#[derive(Debug)]
+struct Member {
+    body: u64,
+    last_header: u64,
+    end: u64,
+    hasher: Hasher,
+    total: u64,
+    window: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GzAppender<F: Read + Write + Seek> {
+    writer: Option<DeflateWriter<F>>,
+    hasher: Hasher,
+    total: u64,
+    finished: bool,
+}
+
+impl GzAppender<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Self::new(file)
+    }
+}
+
+impl<F: Read + Write + Seek> GzAppender<F> {
+    pub fn new(mut inner: F) -> io::Result<Self> {
+        if inner.seek(SeekFrom::End(0))? == 0 {
+            inner.write_all(&GzipHeader::default().to_bytes())?;
+            return Ok(Self {
+                writer: Some(DeflateWriter::new(inner)),
+                hasher: Hasher::new(),
+                total: 0,
+                finished: false,
+            });
+        }
+        inner.rewind()?;
+        let member = last_member(&mut BufReader::new(&mut inner))?;
+        let start = member.body + member.last_header / 8;
+        let len = member.end.div_ceil(8) - member.last_header / 8;
+        let mut tail = vec![0; len as usize];
+        inner.seek(SeekFrom::Start(start))?;
+        inner.read_exact(&mut tail)?;
+        unset_final(
+            &mut tail,
+            member.last_header % 8,
+            member.end - (start - member.body) * 8,
+        );
+        inner.seek(SeekFrom::Start(start))?;
+        inner.write_all(&tail)?;
+        Ok(Self {
+            writer: Some(DeflateWriter::new(inner).with_dictionary(&member.window)),
+            hasher: member.hasher,
+            total: member.total,
+            finished: false,
+        })
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.total
+    }
+
+    pub fn get_ref(&self) -> &F {
+        self.writer.as_ref().unwrap().get_ref()
+    }
+
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        let writer = self.writer.as_mut().unwrap();
+        if !self.finished {
+            writer.try_finish()?;
+            let trailer = gzip::trailer(self.hasher.clone().finalize(), self.total as u32);
+            writer.get_mut().write_all(&trailer)?;
+            self.finished = true;
+        }
+        writer.get_mut().flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<F> {
+        self.try_finish()?;
+        self.writer.take().unwrap().finish()
+    }
+}
+
+impl<F: Read + Write + Seek> Write for GzAppender<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.as_mut().unwrap().write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.total += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().unwrap().flush()
+    }
+}
+
+impl<F: Read + Write + Seek> Drop for GzAppender<F> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+pub fn append<P: AsRef<Path>>(path: P, data: &[u8]) -> io::Result<()> {
+    let mut appender = GzAppender::open(path)?;
+    appender.write_all(data)?;
+    appender.finish()?;
+    Ok(())
+}
+
+fn last_member<R: BufRead + Seek>(reader: &mut R) -> io::Result<Member> {
+    loop {
+        GzipHeader::read_from(reader)?;
+        let body = reader.stream_position()?;
+        let mut inflater = Inflater::new(&mut *reader);
+        let mut hasher = Hasher::new();
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut last_header = 0;
+        while !inflater.is_done() {
+            if inflater.is_at_block_boundary() {
+                last_header = inflater.bits_read();
+            }
+            let n = inflater.inflate_block(&mut buf)?;
+            hasher.update(&buf[..n]);
+        }
+        let end = inflater.bits_read();
+        let total = inflater.total_out();
+        let window = inflater.window();
+        drop(inflater);
+        reader.seek(SeekFrom::Start(body + end.div_ceil(8)))?;
+        let mut trailer = [0; 8];
+        reader.read_exact(&mut trailer)?;
+        if trailer != gzip::trailer(hasher.clone().finalize(), total as u32) {
+            return Err(invalid("gzip trailer mismatch"));
+        }
+        if reader.fill_buf()?.is_empty() {
+            return Ok(Member {
+                body,
+                last_header,
+                end,
+                hasher,
+                total,
+                window,
+            });
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::flate2::{
+        read::{GzDecoder, MultiGzDecoder},
+        write::GzEncoder,
+        Compression, GzBuilder,
+    };
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut decoder = GzDecoder::new(data);
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(data.len() as u64, decoder.total_in());
+        output
+    }
+
+    fn append_to(file: Vec<u8>, data: &[u8]) -> Vec<u8> {
+        let mut appender = GzAppender::new(Cursor::new(file)).unwrap();
+        appender.write_all(data).unwrap();
+        appender.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn trivial_test() {
+        let file = append_to(vec![], b"");
+        assert!(gunzip(&file).is_empty());
+        assert_eq!(b"".to_vec(), gunzip(&append_to(file, b"")));
+    }
+
+    #[test]
+    fn test_append_across_restarts() {
+        let mut encoder = GzBuilder::new()
+            .filename("app.log")
+            .write(vec![], Compression::default());
+        encoder.write_all(b"started\n").unwrap();
+        let mut file = encoder.finish().unwrap();
+        let mut expected = b"started\n".to_vec();
+        for i in 0..20 {
+            let line = format!("restart {i}: Fa-la-la-la, la-la-la-la!\n").repeat(i * 300);
+            file = append_to(file, line.as_bytes());
+            expected.extend(line.as_bytes());
+            assert_eq!(expected, gunzip(&file));
+        }
+        let mut decoder = GzDecoder::new(file.as_slice());
+        decoder.read_to_end(&mut vec![]).unwrap();
+        assert_eq!(Some(&b"app.log"[..]), decoder.header().unwrap().filename());
+    }
+
+    #[test]
+    fn test_append_to_last_member() {
+        let mut file = vec![];
+        let mut second = 0;
+        for data in [&b"first member\n"[..], b"second member\n"] {
+            let mut encoder = GzEncoder::new(vec![], Compression::fast());
+            encoder.write_all(data).unwrap();
+            second = file.len();
+            file.extend(encoder.finish().unwrap());
+        }
+        let file = append_to(file, b"appended\n");
+        let mut decoder = MultiGzDecoder::new(file.as_slice());
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(b"first member\nsecond member\nappended\n".to_vec(), output);
+        assert_eq!(
+            b"second member\nappended\n".to_vec(),
+            gunzip(&file[second..])
+        );
+    }
+
+    #[test]
+    fn test_drop_finishes_the_member() {
+        let mut file = Cursor::new(append_to(vec![], b"started\n"));
+        {
+            let mut appender = GzAppender::new(&mut file).unwrap();
+            appender.write_all(b"dropped without finish\n").unwrap();
+        }
+        assert_eq!(
+            b"started\ndropped without finish\n".to_vec(),
+            gunzip(file.get_ref())
+        );
+        let mut appender = GzAppender::new(&mut file).unwrap();
+        appender.write_all(b"finished\n").unwrap();
+        appender.try_finish().unwrap();
+        appender.try_finish().unwrap();
+        drop(appender);
+        assert_eq!(
+            b"started\ndropped without finish\nfinished\n".to_vec(),
+            gunzip(file.get_ref())
+        );
+    }
+
+    #[test]
+    fn test_append_errors() {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"log line\n").unwrap();
+        let mut file = encoder.finish().unwrap();
+        let last = file.len() - 1;
+        file[last] ^= 1;
+        assert!(GzAppender::new(Cursor::new(file.clone())).is_err());
+        assert!(GzAppender::new(Cursor::new(file[..last].to_vec())).is_err());
+        assert!(GzAppender::new(Cursor::new(b"not gzip".to_vec())).is_err());
+    }
+}
@@ -0,0 +1,465 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    compressor::{Compressor, Flush, State, Status},
+    decompressor::Decompressor,
+    encoder::Encoder,
+    framing::{Framing, Gzip, Raw, Zlib},
+    limits::DecodeLimits,
+};
+
+pub const CHUNK_SIZE: usize = 1 << 15;
+
+pub type AsyncDeflateEncoder<W> = AsyncEncoder<W, Raw>;
+pub type AsyncZlibEncoder<W> = AsyncEncoder<W, Zlib>;
+pub type AsyncGzipEncoder<W> = AsyncEncoder<W, Gzip>;
+pub type AsyncDeflateDecoder<R> = AsyncDecoder<R, Raw>;
+pub type AsyncZlibDecoder<R> = AsyncDecoder<R, Zlib>;
+pub type AsyncGzipDecoder<R> = AsyncDecoder<R, Gzip>;
+
+#[derive(Debug)]
+pub struct AsyncEncoder<W, F> {
+    inner: W,
+    framing: F,
+    compressor: Compressor,
+    pending: Vec<u8>,
+    pos: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl<W: AsyncWrite + Unpin, F: Framing + Default> AsyncEncoder<W, F> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            framing: F::default(),
+            compressor: Compressor::new(),
+            pending: vec![],
+            pos: 0,
+            started: false,
+            finished: false,
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin, F: Framing> AsyncEncoder<W, F> {
+    pub fn with_encoder(mut self, encoder: Encoder) -> Self {
+        let block_size = self.compressor.block_size();
+        self.compressor = Compressor::with_encoder(encoder).with_block_size(block_size);
+        self
+    }
+
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        let compressor = std::mem::take(&mut self.compressor);
+        self.compressor = compressor.with_block_size(block_size);
+        self
+    }
+
+    pub fn with_framing(mut self, framing: F) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.compressor.total_in()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn start(&mut self) {
+        if !self.started {
+            self.pending.extend(self.framing.header());
+            self.started = true;
+        }
+    }
+
+    fn step(&mut self, input: &[u8], flush: Flush) -> Status {
+        let len = self.pending.len();
+        self.pending.resize(len + CHUNK_SIZE, 0);
+        let status = self
+            .compressor
+            .compress(input, &mut self.pending[len..], flush);
+        self.pending.truncate(len + status.produced);
+        self.framing.update(&input[..status.consumed]);
+        status
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pos < self.pending.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pos += n;
+        }
+        self.pending.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin, F: Framing + Unpin> AsyncWrite for AsyncEncoder<W, F> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Err(io::Error::other("write after shutdown")));
+        }
+        this.start();
+        loop {
+            ready!(this.poll_drain(cx))?;
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let len = buf.len().min(this.compressor.block_size());
+            let status = this.step(&buf[..len], Flush::None);
+            if status.consumed > 0 {
+                return Poll::Ready(Ok(status.consumed));
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.start();
+        loop {
+            ready!(this.poll_drain(cx))?;
+            if this.finished {
+                break;
+            }
+            let status = this.step(&[], Flush::Sync);
+            if status.produced == 0 && status.state == State::NeedsInput {
+                break;
+            }
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.start();
+        loop {
+            ready!(this.poll_drain(cx))?;
+            if this.finished {
+                break;
+            }
+            if this.step(&[], Flush::Finish).state == State::Done {
+                let trailer = this.framing.trailer(this.compressor.total_in());
+                this.pending.extend(trailer);
+                this.finished = true;
+            }
+        }
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Header,
+    Body,
+    Trailer,
+    Next,
+    Done,
+}
+
+#[derive(Debug)]
+pub struct AsyncDecoder<R, F> {
+    inner: R,
+    framing: F,
+    decompressor: Decompressor,
+    stage: Stage,
+    buffer: Vec<u8>,
+    multi_member: bool,
+    limits: DecodeLimits,
+    blocks: u64,
+    total_out: u64,
+}
+
+impl<R: AsyncBufRead + Unpin, F: Framing + Default> AsyncDecoder<R, F> {
+    pub fn new(inner: R) -> Self {
+        let framing = F::default();
+        Self {
+            inner,
+            multi_member: framing.multi_member(),
+            framing,
+            decompressor: Decompressor::new(),
+            stage: Stage::Header,
+            buffer: vec![],
+            limits: DecodeLimits::default(),
+            blocks: 0,
+            total_out: 0,
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin, F: Framing> AsyncDecoder<R, F> {
+    pub fn with_multi_member(mut self, multi_member: bool) -> Self {
+        self.multi_member = multi_member;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self.decompressor = Decompressor::new().with_limits(limits);
+        self
+    }
+
+    pub fn framing(&self) -> &F {
+        &self.framing
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn poll_header(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            let available = ready!(Pin::new(&mut self.inner).poll_fill_buf(cx))?;
+            let seen = self.buffer.len();
+            self.buffer.extend_from_slice(available);
+            let eof = available.is_empty();
+            if let Some(len) = self.framing.parse_header(&self.buffer)? {
+                Pin::new(&mut self.inner).consume(len - seen);
+                self.buffer.clear();
+                return Poll::Ready(Ok(()));
+            }
+            if eof {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            let n = self.buffer.len() - seen;
+            Pin::new(&mut self.inner).consume(n);
+        }
+    }
+
+    fn poll_trailer(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.buffer.len() < self.framing.trailer_len() {
+            let available = ready!(Pin::new(&mut self.inner).poll_fill_buf(cx))?;
+            if available.is_empty() {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            let n = available
+                .len()
+                .min(self.framing.trailer_len() - self.buffer.len());
+            self.buffer.extend_from_slice(&available[..n]);
+            Pin::new(&mut self.inner).consume(n);
+        }
+        let result = self
+            .framing
+            .check_trailer(&self.buffer, self.decompressor.total_out());
+        self.buffer.clear();
+        Poll::Ready(result)
+    }
+
+    fn poll_body(&mut self, cx: &mut Context<'_>, output: &mut [u8]) -> Poll<io::Result<usize>> {
+        let available = ready!(Pin::new(&mut self.inner).poll_fill_buf(cx))?;
+        let at_eof = available.is_empty();
+        let status = self.decompressor.decompress(available, output)?;
+        Pin::new(&mut self.inner).consume(status.consumed);
+        if status.state == State::NeedsInput && at_eof {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        if status.state == State::Done {
+            self.stage = Stage::Trailer;
+        }
+        self.framing.update(&output[..status.produced]);
+        self.total_out += status.produced as u64;
+        Poll::Ready(Ok(status.produced))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin, F: Framing + Unpin> AsyncRead for AsyncDecoder<R, F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        loop {
+            match this.stage {
+                Stage::Header => {
+                    ready!(this.poll_header(cx))?;
+                    this.stage = Stage::Body;
+                }
+                Stage::Body => {
+                    let n = ready!(this.poll_body(cx, buf.initialize_unfilled()))?;
+                    if n > 0 {
+                        buf.advance(n);
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+                Stage::Trailer => {
+                    ready!(this.poll_trailer(cx))?;
+                    this.stage = if this.multi_member {
+                        Stage::Next
+                    } else {
+                        Stage::Done
+                    };
+                }
+                Stage::Next => {
+                    let available = ready!(Pin::new(&mut this.inner).poll_fill_buf(cx))?;
+                    if available.is_empty() {
+                        this.stage = Stage::Done;
+                    } else {
+                        this.framing.next_member();
+                        this.blocks += this.decompressor.blocks();
+                        let limits = this.limits.remaining(this.total_out, this.blocks);
+                        this.decompressor = Decompressor::new().with_limits(limits);
+                        this.stage = Stage::Header;
+                    }
+                }
+                Stage::Done => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+
+    use super::*;
+    use crate::{
+        gzip::{GzipDecoder, GzipHeader},
+        inflate::inflate,
+        test_util::sample,
+    };
+
+    async fn encode<F: Framing + Default + Unpin>(input: &[u8], chunk: usize) -> Vec<u8> {
+        let mut encoder = AsyncEncoder::<_, F>::new(vec![]);
+        for piece in input.chunks(chunk) {
+            encoder.write_all(piece).await.unwrap();
+        }
+        encoder.shutdown().await.unwrap();
+        encoder.into_inner()
+    }
+
+    async fn decode<F: Framing + Default + Unpin>(data: &[u8], capacity: usize) -> Vec<u8> {
+        let mut decoder = AsyncDecoder::<_, F>::new(BufReader::with_capacity(capacity, data));
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).await.unwrap();
+        output
+    }
+
+    #[tokio::test]
+    async fn trivial_test() {
+        let data = encode::<Raw>(b"", 1).await;
+        assert_eq!(vec![3, 0], data);
+        assert!(decode::<Raw>(&data, 1).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let input = sample(100_000);
+        let raw = encode::<Raw>(&input, 1000).await;
+        assert_eq!(input, inflate(&raw).unwrap());
+        assert_eq!(input, decode::<Raw>(&raw, 7).await);
+        let zlib = encode::<Zlib>(&input, 1 << 20).await;
+        assert_eq!(input, decode::<Zlib>(&zlib, 3).await);
+        let gzip = encode::<Gzip>(&input, 333).await;
+        let mut decoded = vec![];
+        GzipDecoder::new(gzip.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(input, decoded);
+        assert_eq!(input, decode::<Gzip>(&gzip, 5).await);
+    }
+
+    #[tokio::test]
+    async fn test_block_size() {
+        let input = sample(100_000);
+        let mut encoder = AsyncDeflateEncoder::new(vec![]).with_block_size(4096);
+        encoder = encoder.with_encoder(Encoder::new());
+        assert_eq!(4096, encoder.write(&input).await.unwrap());
+        encoder.write_all(&input[4096..]).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let small = encoder.into_inner();
+        assert_eq!(input, inflate(&small).unwrap());
+        assert!(small.len() > encode::<Raw>(&input, 1 << 20).await.len());
+    }
+
+    #[tokio::test]
+    async fn test_gzip_members_and_header() {
+        let header = GzipHeader {
+            name: Some(b"sample.txt".to_vec()),
+            ..Default::default()
+        };
+        let mut encoder = AsyncGzipEncoder::new(vec![]).with_framing(Gzip::new(header.clone()));
+        encoder.write_all(b"Fa-la-la-la").await.unwrap();
+        encoder.flush().await.unwrap();
+        encoder.write_all(b", la-la-la-la").await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let mut data = encoder.into_inner();
+        data.extend(encode::<Gzip>(b"!", 1).await);
+        let mut decoder = AsyncGzipDecoder::new(BufReader::with_capacity(2, data.as_slice()));
+        let mut output = String::new();
+        decoder.read_to_string(&mut output).await.unwrap();
+        assert_eq!("Fa-la-la-la, la-la-la-la!", output);
+        assert_eq!(&header, decoder.framing().gzip_header());
+        let mut decoder = AsyncGzipDecoder::new(data.as_slice()).with_multi_member(false);
+        output.clear();
+        decoder.read_to_string(&mut output).await.unwrap();
+        assert_eq!("Fa-la-la-la, la-la-la-la", output);
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let mut data = encode::<Gzip>(&[b'a'; 600], 600).await;
+        data.extend(encode::<Gzip>(&[b'a'; 600], 600).await);
+        let data = data.as_slice();
+        let decode = |limits| async move {
+            let mut decoder = AsyncGzipDecoder::new(data).with_limits(limits);
+            let mut output = vec![];
+            decoder.read_to_end(&mut output).await.map(|_| output)
+        };
+        let limits = DecodeLimits::new().with_max_output(1200);
+        assert_eq!(vec![b'a'; 1200], decode(limits).await.unwrap());
+        let limits = DecodeLimits::new().with_max_output(1000);
+        assert!(decode(limits).await.is_err());
+        let limits = DecodeLimits::new().with_max_blocks(1);
+        assert!(decode(limits).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_input() {
+        let mut zlib = encode::<Zlib>(b"Fa-la-la-la", 4).await;
+        let last = zlib.len() - 1;
+        zlib[last] ^= 1;
+        let mut decoder = AsyncZlibDecoder::new(zlib.as_slice());
+        let mut output = vec![];
+        assert!(decoder.read_to_end(&mut output).await.is_err());
+        let mut decoder = AsyncDeflateDecoder::new(&zlib[2..6]);
+        assert!(decoder.read_to_end(&mut output).await.is_err());
+    }
+}
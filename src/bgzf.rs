@@ -0,0 +1,340 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::{
+    deflate::BTYPE_STORED,
+    encoder::Encoder,
+    gzip::{trailer, GzipHeader, CM_DEFLATE, MAGIC},
+    inflate::inflate,
+};
+
+pub const MAX_BLOCK_SIZE: usize = 1 << 16;
+pub const BLOCK_DATA_SIZE: usize = 0xff00;
+pub const EOF_MARKER: [u8; 28] = [
+    0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0, 0x1b, 0, 3, 0, 0, 0, 0, 0, 0, 0,
+    0, 0,
+];
+
+const HEADER_SIZE: usize = 18;
+const TRAILER_SIZE: usize = 8;
+const FEXTRA: u8 = 4;
+const SUBFIELD_ID: [u8; 2] = *b"BC";
+
+pub fn virtual_offset(block_address: u64, within_block: usize) -> u64 {
+    block_address << 16 | within_block as u64
+}
+
+pub fn split_virtual_offset(offset: u64) -> (u64, usize) {
+    (offset >> 16, (offset & 0xffff) as usize)
+}
+
+pub fn compress_block(encoder: &Encoder, data: &[u8]) -> Vec<u8> {
+    assert!(data.len() <= BLOCK_DATA_SIZE);
+    let mut cdata = encoder.deflate(data);
+    if HEADER_SIZE + cdata.len() + TRAILER_SIZE > MAX_BLOCK_SIZE {
+        cdata = vec![1 | (BTYPE_STORED as u8) << 1];
+        cdata.extend((data.len() as u16).to_le_bytes());
+        cdata.extend((!(data.len() as u16)).to_le_bytes());
+        cdata.extend(data);
+    }
+    let bsize = (HEADER_SIZE + cdata.len() + TRAILER_SIZE - 1) as u16;
+    let mut extra = SUBFIELD_ID.to_vec();
+    extra.extend(2u16.to_le_bytes());
+    extra.extend(bsize.to_le_bytes());
+    let mut block = GzipHeader {
+        extra: Some(extra),
+        ..Default::default()
+    }
+    .to_bytes();
+    block.extend(cdata);
+    block.extend(trailer(crc32fast::hash(data), data.len() as u32));
+    block
+}
+
+#[derive(Debug)]
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    encoder: Encoder,
+    buffer: Vec<u8>,
+    block_address: u64,
+    finished: bool,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_encoder(inner, Encoder::new())
+    }
+
+    pub fn with_encoder(inner: W, encoder: Encoder) -> Self {
+        Self {
+            inner,
+            encoder,
+            buffer: Vec::with_capacity(BLOCK_DATA_SIZE),
+            block_address: 0,
+            finished: false,
+        }
+    }
+
+    pub fn virtual_offset(&self) -> u64 {
+        virtual_offset(self.block_address, self.buffer.len())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.write_block()?;
+            self.inner.write_all(&EOF_MARKER)?;
+            self.block_address += EOF_MARKER.len() as u64;
+            self.finished = true;
+        }
+        self.inner.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner)
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let block = compress_block(&self.encoder, &self.buffer);
+            self.inner.write_all(&block)?;
+            self.block_address += block.len() as u64;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::other("BGZF stream is already finished"));
+        }
+        let n = buf.len().min(BLOCK_DATA_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == BLOCK_DATA_SIZE {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.write_block()?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug)]
+pub struct BgzfReader<R: Read> {
+    inner: R,
+    block: Vec<u8>,
+    pos: usize,
+    block_address: u64,
+    next_address: u64,
+}
+
+impl<R: Read> BgzfReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block: vec![],
+            pos: 0,
+            block_address: 0,
+            next_address: 0,
+        }
+    }
+
+    pub fn virtual_offset(&self) -> u64 {
+        if self.pos == self.block.len() {
+            virtual_offset(self.next_address, 0)
+        } else {
+            virtual_offset(self.block_address, self.pos)
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut header = [0; HEADER_SIZE];
+        let mut filled = 0;
+        while filled < HEADER_SIZE {
+            match self.inner.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let xlen = u16::from_le_bytes([header[10], header[11]]) as usize;
+        if header[..2] != MAGIC || header[2] != CM_DEFLATE || header[3] & FEXTRA == 0 || xlen < 6 {
+            return Err(invalid("invalid BGZF block header"));
+        }
+        let mut rest = vec![0; xlen - 6];
+        self.inner.read_exact(&mut rest)?;
+        let mut extra = header[12..].to_vec();
+        extra.extend(&rest);
+        let bsize = block_size(&extra).ok_or_else(|| invalid("missing BGZF block size"))?;
+        let cdata_len = (bsize + 1)
+            .checked_sub(HEADER_SIZE - 6 + xlen + TRAILER_SIZE)
+            .ok_or_else(|| invalid("invalid BGZF block size"))?;
+        let mut cdata = vec![0; cdata_len + TRAILER_SIZE];
+        self.inner.read_exact(&mut cdata)?;
+        let (cdata, trailer) = cdata.split_at(cdata_len);
+        let data = inflate(cdata)?;
+        let crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+        let isize = u32::from_le_bytes(trailer[4..].try_into().unwrap());
+        if crc != crc32fast::hash(&data) || isize != data.len() as u32 {
+            return Err(invalid("BGZF block checksum mismatch"));
+        }
+        self.block_address = self.next_address;
+        self.next_address += bsize as u64 + 1;
+        self.block = data;
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    pub fn seek_virtual(&mut self, offset: u64) -> io::Result<()> {
+        let (block_address, within_block) = split_virtual_offset(offset);
+        self.inner.seek(SeekFrom::Start(block_address))?;
+        self.next_address = block_address;
+        self.block.clear();
+        self.pos = 0;
+        if !self.read_block()? && within_block > 0 {
+            return Err(invalid("virtual offset is past the end of the stream"));
+        }
+        if within_block > self.block.len() {
+            return Err(invalid("virtual offset is past the end of the block"));
+        }
+        self.pos = within_block;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.block.len() {
+            if !self.read_block()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.pos);
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn block_size(mut extra: &[u8]) -> Option<usize> {
+    while extra.len() >= 4 {
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        let data = extra.get(4..4 + len)?;
+        if extra[..2] == SUBFIELD_ID && len == 2 {
+            return Some(u16::from_le_bytes([data[0], data[1]]) as usize);
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_util::lines;
+
+    #[test]
+    fn trivial_test() {
+        let output = BgzfWriter::new(vec![]).finish().unwrap();
+        assert_eq!(EOF_MARKER.to_vec(), output);
+        let mut reader = BgzfReader::new(output.as_slice());
+        assert_eq!(0, reader.read(&mut [0; 16]).unwrap());
+    }
+
+    #[test]
+    fn test_blocks_are_bounded() {
+        let input = lines(100_000);
+        let mut writer = BgzfWriter::new(vec![]);
+        writer.write_all(&input).unwrap();
+        let output = writer.finish().unwrap();
+        assert!(output.ends_with(&EOF_MARKER));
+        let mut pos = 0;
+        while pos < output.len() {
+            assert_eq!(b"BC", &output[pos + 12..pos + 14]);
+            let bsize = u16::from_le_bytes([output[pos + 16], output[pos + 17]]) as usize;
+            let isize =
+                u32::from_le_bytes(output[pos + bsize - 3..pos + bsize + 1].try_into().unwrap());
+            assert!(isize as usize <= BLOCK_DATA_SIZE);
+            pos += bsize + 1;
+        }
+        assert_eq!(output.len(), pos);
+        let mut content = vec![];
+        BgzfReader::new(output.as_slice())
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(input, content);
+    }
+
+    #[test]
+    fn test_incompressible_block_is_stored() {
+        let mut state = 12345_u32;
+        let data = (0..BLOCK_DATA_SIZE)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        let block = compress_block(&Encoder::new(), &data);
+        assert!(block.len() <= MAX_BLOCK_SIZE);
+        let mut content = vec![];
+        BgzfReader::new(block.as_slice())
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(data, content);
+    }
+
+    #[test]
+    fn test_seek_virtual_offset() {
+        let input = lines(100_000);
+        let mut writer = BgzfWriter::new(vec![]);
+        let mut offsets = vec![];
+        for (index, line) in input.split_inclusive(|b| *b == b'\n').enumerate() {
+            if index % 9973 == 0 {
+                offsets.push((writer.virtual_offset(), line.to_vec()));
+            }
+            writer.write_all(line).unwrap();
+        }
+        let mut reader = BgzfReader::new(Cursor::new(writer.finish().unwrap()));
+        for (offset, line) in offsets.iter().rev() {
+            reader.seek_virtual(*offset).unwrap();
+            assert_eq!(*offset, reader.virtual_offset());
+            let mut buf = vec![0; line.len()];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(*line, buf);
+        }
+    }
+}
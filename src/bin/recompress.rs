@@ -0,0 +1,72 @@
+use std::{env, fs, io, path::Path, process::ExitCode};
+
+use otus_deflate::recompress::recompress;
+
+const USAGE: &str = "usage: recompress [--dry-run] FILE...";
+
+fn run(path: &Path, dry_run: bool) -> io::Result<()> {
+    let input = fs::read(path)?;
+    let result = recompress(&input)?;
+    let smaller = result.data.len() < input.len();
+    println!(
+        "{}: {:?}, {} -> {} bytes, {}/{} streams improved{}",
+        path.display(),
+        result.container,
+        input.len(),
+        result.data.len().min(input.len()),
+        result.replaced,
+        result.streams,
+        if smaller && dry_run { " (dry run)" } else { "" }
+    );
+    if smaller && !dry_run {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".recompress");
+        let metadata = fs::metadata(path)?;
+        fs::write(&tmp, &result.data)?;
+        let tmp = Path::new(&tmp);
+        if let Err(e) = copy_metadata(&metadata, tmp).and_then(|_| fs::rename(tmp, path)) {
+            let _ = fs::remove_file(tmp);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+fn copy_metadata(metadata: &fs::Metadata, path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{chown, MetadataExt};
+        let current = fs::metadata(path)?;
+        if (current.uid(), current.gid()) != (metadata.uid(), metadata.gid()) {
+            chown(path, Some(metadata.uid()), Some(metadata.gid()))?;
+        }
+    }
+    fs::set_permissions(path, metadata.permissions())
+}
+
+fn main() -> ExitCode {
+    let mut dry_run = false;
+    let mut paths = vec![];
+    for arg in env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--dry-run" | "-n") => dry_run = true,
+            Some("--help" | "-h") => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let mut status = ExitCode::SUCCESS;
+    for path in paths {
+        if let Err(e) = run(Path::new(&path), dry_run) {
+            eprintln!("{}: {e}", Path::new(&path).display());
+            status = ExitCode::FAILURE;
+        }
+    }
+    status
+}
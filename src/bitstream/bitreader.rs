@@ -0,0 +1,124 @@
+use std::io::{self, Read};
+
+use super::BYTE_SIZE;
+
+const BUFFER_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub struct BitReader<R: Read> {
+    inner: R,
+    buffer: Vec<u8>,
+    pos: usize,
+    len: usize,
+    bits: u32,
+    count: usize,
+    bits_read: u64,
+}
+
+impl<R: Read> BitReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: vec![0; BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+            bits: 0,
+            count: 0,
+            bits_read: 0,
+        }
+    }
+
+    pub fn bits_read(&self) -> u64 {
+        self.bits_read
+    }
+
+    pub fn read_bit(&mut self) -> io::Result<u32> {
+        self.read_bits(1)
+    }
+
+    pub fn read_bits(&mut self, n: usize) -> io::Result<u32> {
+        assert!(n <= 24);
+        while self.count < n {
+            let byte = self.next_byte()?;
+            self.bits |= (byte as u32) << self.count;
+            self.count += BYTE_SIZE;
+        }
+        let value = self.bits & ((1_u64 << n) - 1) as u32;
+        self.bits >>= n;
+        self.count -= n;
+        self.bits_read += n as u64;
+        Ok(value)
+    }
+
+    pub fn align(&mut self) {
+        let skip = self.count % BYTE_SIZE;
+        self.bits >>= skip;
+        self.count -= skip;
+        self.bits_read += skip as u64;
+    }
+
+    pub fn read_byte(&mut self) -> io::Result<u8> {
+        self.read_bits(BYTE_SIZE).map(|byte| byte as u8)
+    }
+
+    pub fn into_inner(mut self) -> (R, Vec<u8>) {
+        self.align();
+        let mut unread = vec![];
+        while self.count > 0 {
+            unread.push(self.bits as u8);
+            self.bits >>= BYTE_SIZE;
+            self.count -= BYTE_SIZE;
+        }
+        unread.extend_from_slice(&self.buffer[self.pos..self.len]);
+        (self.inner, unread)
+    }
+
+    fn next_byte(&mut self) -> io::Result<u8> {
+        if self.pos == self.len {
+            self.len = loop {
+                match self.inner.read(&mut self.buffer) {
+                    Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Ok(len) => break len,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            };
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Ok(self.buffer[self.pos - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_test() {
+        let mut reader = BitReader::new(&[][..]);
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            reader.read_bit().unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_read_bits_in_deflate_order() {
+        let mut reader = BitReader::new(&[0b1011_0101, 0b0000_0011, 0xab][..]);
+        assert_eq!(1, reader.read_bit().unwrap());
+        assert_eq!(0b010, reader.read_bits(3).unwrap());
+        assert_eq!(0b11_1011, reader.read_bits(6).unwrap());
+        assert_eq!(10, reader.bits_read());
+        reader.align();
+        assert_eq!(0xab, reader.read_byte().unwrap());
+    }
+
+    #[test]
+    fn test_into_inner_returns_unread_bytes() {
+        let mut reader = BitReader::new(&[1, 2, 3, 4][..]);
+        reader.read_bits(12).unwrap();
+        let (_, unread) = reader.into_inner();
+        assert_eq!(vec![3, 4], unread);
+    }
+}
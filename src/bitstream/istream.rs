@@ -1,3 +1,4 @@
+#[cfg(feature = "std")]
 use std::io;
 
 use super::BYTE_SIZE;
@@ -34,6 +35,31 @@ impl<'a> InputStream<'a> {
             None
         }
     }
+
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> usize {
+        let mut have_read = 0;
+        for byte in buf {
+            if self.bits_remain() < BYTE_SIZE {
+                return have_read;
+            }
+            let (left, bits_read) = self.read_until_end_of_byte();
+            let right = if bits_read < BYTE_SIZE {
+                let (byte_pos, bit_pos) = self.get_positions();
+                assert_eq!(
+                    0, bit_pos,
+                    "Stream must be at the 0th bit of a brand new byte."
+                );
+                let bits_to_read = BYTE_SIZE - bits_read;
+                self.advance(bits_to_read);
+                strip_last_bits(self.input[byte_pos], bits_read)
+            } else {
+                0
+            };
+            have_read += 1;
+            *byte = left | right;
+        }
+        have_read
+    }
 }
 
 impl<'a> InputStream<'a> {
@@ -62,30 +88,10 @@ impl<'a> InputStream<'a> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a> io::Read for InputStream<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut have_read = 0;
-        for byte in buf {
-            if self.bits_remain() < BYTE_SIZE {
-                return Ok(have_read);
-            }
-            let (left, bits_read) = self.read_until_end_of_byte();
-            let right = if bits_read < BYTE_SIZE {
-                let (byte_pos, bit_pos) = self.get_positions();
-                assert_eq!(
-                    0, bit_pos,
-                    "Stream must be at the 0th bit of a brand new byte."
-                );
-                let bits_to_read = BYTE_SIZE - bits_read;
-                self.advance(bits_to_read);
-                strip_last_bits(self.input[byte_pos], bits_read)
-            } else {
-                0
-            };
-            have_read += 1;
-            *byte = left | right;
-        }
-        Ok(have_read)
+        Ok(self.read_bytes(buf))
     }
 }
 
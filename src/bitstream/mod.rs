@@ -1,4 +1,16 @@
+use crate::deflate::Variant;
+
+#[cfg(feature = "std")]
+pub mod bitreader;
 pub mod istream;
 pub mod ostream;
+pub mod slice;
+
+pub(crate) const BYTE_SIZE: usize = 8;
 
-const BYTE_SIZE: usize = 8;
+pub trait BitWrite {
+    fn write_code(&mut self, len: usize, token: usize);
+    fn write_numerical(&mut self, n: usize, value: usize);
+    fn align(&mut self);
+    fn variant(&self) -> Variant;
+}
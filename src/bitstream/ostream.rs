@@ -1,14 +1,15 @@
-use std::{
-    io::{self, Write},
-    mem,
-};
+use alloc::{vec, vec::Vec};
+use core::mem;
+#[cfg(feature = "std")]
+use std::io;
 
 use crate::{
-    deflate::{DeflateToken, CONVERT_DISTANCE, CONVERT_LENGTH, END_OF_BLOCK},
+    deflate::{DeflateToken, Variant, BFINAL_NO, BTYPE_FIXED, BTYPE_STORED, END_OF_BLOCK},
+    huffman::generate::{generate_fixed_code, generate_stored_code},
     HuffmanToken,
 };
 
-use super::BYTE_SIZE;
+use super::{BitWrite, BYTE_SIZE};
 
 #[derive(Debug, Default)]
 pub struct OutputStream {
@@ -17,6 +18,7 @@ pub struct OutputStream {
     bit_pos: usize,
     ll_code: Vec<HuffmanToken>,
     d_code: Vec<HuffmanToken>,
+    variant: Variant,
 }
 
 impl OutputStream {
@@ -27,9 +29,19 @@ impl OutputStream {
             bit_pos: 0,
             ll_code,
             d_code,
+            variant: Variant::Deflate,
         }
     }
 
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
     pub fn extend(&mut self, tokens: impl Iterator<Item = DeflateToken>) {
         for ref token in tokens {
             self.write_token(token);
@@ -39,7 +51,23 @@ impl OutputStream {
     pub fn write_token(&mut self, token: &DeflateToken) {
         match token {
             DeflateToken::Bhead(head) => self.write_numerical(1, *head as _),
-            DeflateToken::Btype(b_type) => self.write_numerical(2, *b_type as _),
+            DeflateToken::Btype(b_type) => {
+                self.write_numerical(2, *b_type as _);
+                if *b_type == BTYPE_FIXED {
+                    (self.ll_code, self.d_code) = generate_fixed_code();
+                } else if *b_type == BTYPE_STORED {
+                    (self.ll_code, self.d_code) = generate_stored_code();
+                }
+            }
+            DeflateToken::Tables(tables) => {
+                tables.write_header(self);
+                (self.ll_code, self.d_code) = tables.codes();
+            }
+            DeflateToken::StoredLength(len) => {
+                self.align();
+                self.write_numerical(16, *len as _);
+                self.write_numerical(16, !*len as _);
+            }
             DeflateToken::Literal(literal) => {
                 let huffman_token = &self.ll_code[*literal as usize];
                 self.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _)
@@ -49,11 +77,11 @@ impl OutputStream {
                 self.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
             }
             DeflateToken::Backref { length, distance } => {
-                let (token, extra, bits) = CONVERT_LENGTH[&(*length as usize)];
+                let (token, extra, bits) = self.variant.convert_length(*length as _);
                 let huffman_token = &self.ll_code[token];
                 self.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
                 self.write_numerical(extra, bits);
-                let (token, extra, bits) = CONVERT_DISTANCE[&(*distance as usize)];
+                let (token, extra, bits) = self.variant.convert_distance(*distance as _);
                 let huffman_token = &self.d_code[token];
                 self.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
                 self.write_numerical(extra, bits);
@@ -61,8 +89,12 @@ impl OutputStream {
         }
     }
 
+    pub fn bits_written(&self) -> usize {
+        self.output.len() * BYTE_SIZE + self.bit_pos
+    }
+
     pub fn write_code(&mut self, len: usize, token: usize) {
-        assert!(len <= mem::size_of::<usize>());
+        assert!(len <= BYTE_SIZE * mem::size_of::<usize>());
         for bit_index in (0..len).rev() {
             let bit = (token & (1 << bit_index)) > 0;
             self.write_bit(bit);
@@ -78,10 +110,26 @@ impl OutputStream {
         }
     }
 
-    pub fn finalize(mut self) -> Vec<u8> {
-        if self.bit_pos % BYTE_SIZE > 0 {
-            self.flush().unwrap();
+    pub fn sync_flush(&mut self) {
+        self.write_numerical(1, BFINAL_NO as _);
+        self.write_numerical(2, BTYPE_STORED as _);
+        self.align();
+        self.write_numerical(16, 0);
+        self.write_numerical(16, 0xffff);
+    }
+
+    pub fn align(&mut self) {
+        if !self.bit_pos.is_multiple_of(BYTE_SIZE) {
+            self.push_byte();
         }
+    }
+
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        mem::take(&mut self.output)
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        self.align();
         self.output
     }
 }
@@ -95,17 +143,42 @@ impl OutputStream {
         self.advance(1);
     }
 
+    fn push_byte(&mut self) {
+        self.output.push(self.current);
+        self.bit_pos = 0;
+        self.current = 0;
+    }
+
     fn advance(&mut self, n: usize) {
         assert!(n <= BYTE_SIZE);
         for _ in 0..n {
             self.bit_pos += 1;
-            if self.bit_pos % BYTE_SIZE == 0 {
-                self.flush().unwrap();
+            if self.bit_pos.is_multiple_of(BYTE_SIZE) {
+                self.push_byte();
             }
         }
     }
 }
 
+impl BitWrite for OutputStream {
+    fn write_code(&mut self, len: usize, token: usize) {
+        OutputStream::write_code(self, len, token);
+    }
+
+    fn write_numerical(&mut self, n: usize, value: usize) {
+        OutputStream::write_numerical(self, n, value);
+    }
+
+    fn align(&mut self) {
+        OutputStream::align(self);
+    }
+
+    fn variant(&self) -> Variant {
+        self.variant
+    }
+}
+
+#[cfg(feature = "std")]
 impl io::Write for OutputStream {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let mut have_written = 0;
@@ -117,9 +190,7 @@ impl io::Write for OutputStream {
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.output.push(self.current);
-        self.bit_pos = 0;
-        self.current = 0;
+        self.push_byte();
         Ok(())
     }
 }
@@ -151,11 +222,23 @@ mod tests {
         assert_eq!(&[0b0000_0111], os.finalize().as_slice());
     }
 
+    #[test]
+    fn test_sync_flush() {
+        let mut os = OutputStream::default();
+        os.write_bit(true);
+        os.sync_flush();
+        assert_eq!(vec![0b0000_0001, 0, 0, 0xff, 0xff], os.finalize());
+        let mut os = OutputStream::default();
+        os.write_code(6, 0);
+        os.sync_flush();
+        assert_eq!(vec![0, 0, 0, 0, 0xff, 0xff], os.finalize());
+    }
+
     #[test]
     fn test_write_trait() {
         let mut os = OutputStream::default();
         let buf = [1, 2, 3];
-        os.write(&buf).unwrap();
+        assert_eq!(buf.len(), os.write(&buf).unwrap());
         let output = os.finalize();
         assert_eq!(buf, output.as_slice());
     }
@@ -0,0 +1,116 @@
+use super::BYTE_SIZE;
+
+const ACCUMULATOR_BITS: usize = 64;
+
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    output: &'a mut [u8],
+    pos: usize,
+    bits: u64,
+    count: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(output: &'a mut [u8]) -> Self {
+        Self::with_pending(output, 0, 0)
+    }
+
+    pub fn with_pending(output: &'a mut [u8], bits: u64, count: usize) -> Self {
+        let mut writer = Self {
+            output,
+            pos: 0,
+            bits,
+            count,
+        };
+        writer.drain();
+        writer
+    }
+
+    pub fn pending(&self) -> (u64, usize) {
+        (self.bits, self.count)
+    }
+
+    pub fn written(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_drained(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn fits(&self, n: usize) -> bool {
+        self.count + n <= ACCUMULATOR_BITS
+    }
+
+    pub fn write_numerical(&mut self, n: usize, value: usize) {
+        assert!(self.fits(n));
+        if n > 0 {
+            self.bits |= (value as u64 & (u64::MAX >> (ACCUMULATOR_BITS - n))) << self.count;
+            self.count += n;
+        }
+        self.drain();
+    }
+
+    pub fn write_code(&mut self, len: usize, token: usize) {
+        let reversed = (0..len).fold(0, |acc, i| acc << 1 | (token >> i) & 1);
+        self.write_numerical(len, reversed);
+    }
+
+    pub fn align(&mut self) {
+        self.count = self.count.next_multiple_of(BYTE_SIZE);
+        self.drain();
+    }
+
+    fn drain(&mut self) {
+        while self.count >= BYTE_SIZE && self.pos < self.output.len() {
+            self.output[self.pos] = self.bits as u8;
+            self.bits >>= BYTE_SIZE;
+            self.count -= BYTE_SIZE;
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstream::ostream::OutputStream;
+
+    #[test]
+    fn trivial_test() {
+        let mut output = [];
+        let writer = SliceWriter::new(&mut output);
+        assert!(writer.is_drained());
+        assert_eq!(0, writer.written());
+    }
+
+    #[test]
+    fn test_same_bits_as_output_stream() {
+        let mut os = OutputStream::default();
+        let mut output = [0; 8];
+        let mut writer = SliceWriter::new(&mut output);
+        for (len, value) in [(1, 1), (2, 1), (7, 0b0010111), (5, 21), (16, 0xffff)] {
+            os.write_code(len, value);
+            writer.write_code(len, value);
+            os.write_numerical(len, value);
+            writer.write_numerical(len, value);
+        }
+        writer.align();
+        let written = writer.written();
+        assert_eq!(os.finalize(), output[..written]);
+    }
+
+    #[test]
+    fn test_pending_bits_survive_full_output() {
+        let mut output = [0; 1];
+        let mut writer = SliceWriter::new(&mut output);
+        writer.write_numerical(16, 0xabcd);
+        assert_eq!(1, writer.written());
+        assert!(!writer.is_drained());
+        let (bits, count) = writer.pending();
+        let mut output = [0; 2];
+        let writer = SliceWriter::with_pending(&mut output, bits, count);
+        assert!(writer.is_drained());
+        assert_eq!([0xab, 0], output);
+    }
+}
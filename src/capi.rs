@@ -0,0 +1,894 @@
+use std::{
+    ffi::{c_char, c_int, c_uint, c_ulong, c_void, CStr},
+    mem, ptr, slice,
+};
+
+use crc32fast::Hasher;
+
+use crate::{
+    compressor::{compress_bound, Compressor, Flush, State},
+    decompressor::Decompressor,
+    deflate::Strategy,
+    encoder::Encoder,
+    framing::{Framing, Gzip, Raw, Zlib},
+    gzip::MAGIC,
+    zlib::Adler32,
+};
+
+pub const Z_NO_FLUSH: c_int = 0;
+pub const Z_PARTIAL_FLUSH: c_int = 1;
+pub const Z_SYNC_FLUSH: c_int = 2;
+pub const Z_FULL_FLUSH: c_int = 3;
+pub const Z_FINISH: c_int = 4;
+pub const Z_BLOCK: c_int = 5;
+pub const Z_TREES: c_int = 6;
+
+pub const Z_OK: c_int = 0;
+pub const Z_STREAM_END: c_int = 1;
+pub const Z_NEED_DICT: c_int = 2;
+pub const Z_ERRNO: c_int = -1;
+pub const Z_STREAM_ERROR: c_int = -2;
+pub const Z_DATA_ERROR: c_int = -3;
+pub const Z_MEM_ERROR: c_int = -4;
+pub const Z_BUF_ERROR: c_int = -5;
+pub const Z_VERSION_ERROR: c_int = -6;
+
+pub const Z_NO_COMPRESSION: c_int = 0;
+pub const Z_BEST_SPEED: c_int = 1;
+pub const Z_BEST_COMPRESSION: c_int = 9;
+pub const Z_DEFAULT_COMPRESSION: c_int = -1;
+pub const Z_FILTERED: c_int = 1;
+pub const Z_HUFFMAN_ONLY: c_int = 2;
+pub const Z_RLE: c_int = 3;
+pub const Z_FIXED: c_int = 4;
+pub const Z_DEFAULT_STRATEGY: c_int = 0;
+pub const Z_DEFLATED: c_int = 8;
+pub const MAX_WBITS: c_int = 15;
+pub const DEF_MEM_LEVEL: c_int = 8;
+
+const VERSION: &CStr = c"1.3.1";
+const ZLIB_OVERHEAD: usize = 6;
+
+pub type AllocFunc = Option<unsafe extern "C" fn(*mut c_void, c_uint, c_uint) -> *mut c_void>;
+pub type FreeFunc = Option<unsafe extern "C" fn(*mut c_void, *mut c_void)>;
+
+#[repr(C)]
+pub struct ZStream {
+    pub next_in: *const u8,
+    pub avail_in: c_uint,
+    pub total_in: c_ulong,
+    pub next_out: *mut u8,
+    pub avail_out: c_uint,
+    pub total_out: c_ulong,
+    pub msg: *const c_char,
+    pub state: *mut InternalState,
+    /// Allocates the stream state when set together with `zfree`; buffers
+    /// inside the state always come from the Rust global allocator.
+    pub zalloc: AllocFunc,
+    pub zfree: FreeFunc,
+    pub opaque: *mut c_void,
+    pub data_type: c_int,
+    pub adler: c_ulong,
+    pub reserved: c_ulong,
+}
+
+pub enum InternalState {
+    Deflate(DeflateStream),
+    Inflate(InflateStream),
+}
+
+pub struct DeflateStream {
+    compressor: Compressor,
+    framing: Box<dyn Framing>,
+    pending: Vec<u8>,
+    pos: usize,
+    finished: bool,
+}
+
+impl DeflateStream {
+    fn new(framing: Box<dyn Framing>, encoder: Encoder) -> Self {
+        Self {
+            compressor: Compressor::with_encoder(encoder),
+            pending: framing.header(),
+            framing,
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    fn deflate(&mut self, input: &[u8], output: &mut [u8], flush: Flush) -> (usize, usize, bool) {
+        let (mut consumed, mut produced) = (0, 0);
+        loop {
+            let n = (self.pending.len() - self.pos).min(output.len() - produced);
+            output[produced..produced + n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+            self.pos += n;
+            produced += n;
+            if self.pos < self.pending.len() || self.finished {
+                break;
+            }
+            (self.pending, self.pos) = (vec![], 0);
+            let status =
+                self.compressor
+                    .compress(&input[consumed..], &mut output[produced..], flush);
+            self.framing
+                .update(&input[consumed..consumed + status.consumed]);
+            consumed += status.consumed;
+            produced += status.produced;
+            if status.state != State::Done {
+                break;
+            }
+            self.pending = self.framing.trailer(self.compressor.total_in());
+            self.finished = true;
+        }
+        let done = self.finished && self.pos == self.pending.len();
+        (consumed, produced, done)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Detect,
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+pub struct InflateStream {
+    framing: Box<dyn Framing>,
+    decompressor: Decompressor,
+    stage: Stage,
+    buffer: Vec<u8>,
+}
+
+impl InflateStream {
+    fn new(framing: Option<Box<dyn Framing>>) -> Self {
+        Self {
+            stage: match framing {
+                Some(_) => Stage::Header,
+                None => Stage::Detect,
+            },
+            framing: framing.unwrap_or_else(|| Box::new(Raw)),
+            // zlib has no decode limits; C callers bound the output through avail_out.
+            decompressor: Decompressor::new(),
+            buffer: vec![],
+        }
+    }
+
+    fn inflate(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(usize, usize, bool), &'static CStr> {
+        let (mut consumed, mut produced) = (0, 0);
+        loop {
+            match self.stage {
+                Stage::Detect => {
+                    let Some(first) = input.first() else {
+                        break;
+                    };
+                    self.framing = if *first == MAGIC[0] {
+                        Box::new(Gzip::default())
+                    } else {
+                        Box::new(Zlib::default())
+                    };
+                    self.stage = Stage::Header;
+                }
+                Stage::Header => {
+                    let seen = self.buffer.len();
+                    self.buffer.extend_from_slice(&input[consumed..]);
+                    let parsed = self
+                        .framing
+                        .parse_header(&self.buffer)
+                        .map_err(|_| c"incorrect header check")?;
+                    match parsed {
+                        Some(len) => consumed += len - seen,
+                        None => {
+                            consumed = input.len();
+                            break;
+                        }
+                    }
+                    self.buffer.clear();
+                    self.stage = Stage::Body;
+                }
+                Stage::Body => {
+                    let status = self
+                        .decompressor
+                        .decompress(&input[consumed..], &mut output[produced..])
+                        .map_err(|_| c"invalid deflate data")?;
+                    self.framing
+                        .update(&output[produced..produced + status.produced]);
+                    consumed += status.consumed;
+                    produced += status.produced;
+                    if status.state != State::Done {
+                        break;
+                    }
+                    self.stage = Stage::Trailer;
+                }
+                Stage::Trailer => {
+                    let n = (self.framing.trailer_len() - self.buffer.len())
+                        .min(input.len() - consumed);
+                    self.buffer
+                        .extend_from_slice(&input[consumed..consumed + n]);
+                    consumed += n;
+                    if self.buffer.len() < self.framing.trailer_len() {
+                        break;
+                    }
+                    self.framing
+                        .check_trailer(&self.buffer, self.decompressor.total_out())
+                        .map_err(|_| c"incorrect data check")?;
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => break,
+            }
+        }
+        Ok((consumed, produced, self.stage == Stage::Done))
+    }
+}
+
+fn framing_for(window_bits: c_int) -> Option<Box<dyn Framing>> {
+    match window_bits {
+        8..=15 => Some(Box::new(Zlib::default())),
+        -15..=-8 => Some(Box::new(Raw)),
+        24..=31 => Some(Box::new(Gzip::default())),
+        _ => None,
+    }
+}
+
+fn encoder_for(level: c_int, strategy: Strategy) -> Option<Encoder> {
+    let level = match level {
+        Z_DEFAULT_COMPRESSION => 6,
+        0..=9 => level as u32,
+        _ => return None,
+    };
+    Some(Encoder::new().with_strategy(strategy).with_level(level))
+}
+
+fn strategy_for(strategy: c_int) -> Option<Strategy> {
+    match strategy {
+        Z_DEFAULT_STRATEGY => Some(Strategy::Default),
+        Z_FILTERED => Some(Strategy::Filtered),
+        Z_HUFFMAN_ONLY => Some(Strategy::HuffmanOnly),
+        Z_RLE => Some(Strategy::Rle),
+        Z_FIXED => Some(Strategy::Fixed),
+        _ => None,
+    }
+}
+
+unsafe fn check_version(version: *const c_char, stream_size: c_int) -> bool {
+    !version.is_null()
+        && *version as u8 == VERSION.to_bytes()[0]
+        && stream_size as usize == mem::size_of::<ZStream>()
+}
+
+unsafe fn start(strm: &mut ZStream, state: InternalState) -> c_int {
+    strm.state = match (strm.zalloc, strm.zfree) {
+        (None, None) => Box::into_raw(Box::new(state)),
+        (Some(zalloc), Some(zfree)) => {
+            let size = mem::size_of::<InternalState>() as c_uint;
+            let raw = zalloc(strm.opaque, 1, size).cast::<InternalState>();
+            if raw.is_null() {
+                return Z_MEM_ERROR;
+            }
+            if !raw.is_aligned() {
+                zfree(strm.opaque, raw.cast());
+                return Z_MEM_ERROR;
+            }
+            raw.write(state);
+            raw
+        }
+        _ => return Z_STREAM_ERROR,
+    };
+    (strm.total_in, strm.total_out) = (0, 0);
+    strm.msg = ptr::null();
+    strm.data_type = 0;
+    Z_OK
+}
+
+unsafe fn buffers<'a>(strm: &ZStream) -> Option<(&'a [u8], &'a mut [u8])> {
+    Some((
+        raw_slice(strm.next_in, strm.avail_in as c_ulong)?,
+        raw_slice_mut(strm.next_out, strm.avail_out as c_ulong)?,
+    ))
+}
+
+unsafe fn advance(strm: &mut ZStream, consumed: usize, produced: usize) {
+    if consumed > 0 {
+        strm.next_in = strm.next_in.add(consumed);
+        strm.avail_in -= consumed as c_uint;
+        strm.total_in += consumed as c_ulong;
+    }
+    if produced > 0 {
+        strm.next_out = strm.next_out.add(produced);
+        strm.avail_out -= produced as c_uint;
+        strm.total_out += produced as c_ulong;
+    }
+}
+
+unsafe fn end(strm: *mut ZStream, deflate: bool) -> c_int {
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    match strm.state.as_ref() {
+        Some(InternalState::Deflate(_)) if deflate => {}
+        Some(InternalState::Inflate(_)) if !deflate => {}
+        _ => return Z_STREAM_ERROR,
+    }
+    match strm.zfree {
+        Some(zfree) => {
+            ptr::drop_in_place(strm.state);
+            zfree(strm.opaque, strm.state.cast());
+        }
+        None => drop(Box::from_raw(strm.state)),
+    }
+    strm.state = ptr::null_mut();
+    Z_OK
+}
+
+#[no_mangle]
+pub extern "C" fn zlibVersion() -> *const c_char {
+    VERSION.as_ptr()
+}
+
+/// # Safety
+///
+/// `strm` must be null or point to a writable `z_stream` whose `zalloc`,
+/// `zfree` and `opaque` are initialised, and `version` must be null or a
+/// valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn deflateInit2_(
+    strm: *mut ZStream,
+    level: c_int,
+    method: c_int,
+    window_bits: c_int,
+    mem_level: c_int,
+    strategy: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    if !check_version(version, stream_size) {
+        return Z_VERSION_ERROR;
+    }
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    let (Some(framing), Some(encoder)) = (
+        framing_for(window_bits),
+        strategy_for(strategy).and_then(|strategy| encoder_for(level, strategy)),
+    ) else {
+        return Z_STREAM_ERROR;
+    };
+    if method != Z_DEFLATED || !(1..=9).contains(&mem_level) {
+        return Z_STREAM_ERROR;
+    }
+    strm.adler = framing.checksum() as c_ulong;
+    start(
+        strm,
+        InternalState::Deflate(DeflateStream::new(framing, encoder)),
+    )
+}
+
+/// # Safety
+///
+/// Same as [`deflateInit2_`].
+#[no_mangle]
+pub unsafe extern "C" fn deflateInit_(
+    strm: *mut ZStream,
+    level: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    deflateInit2_(
+        strm,
+        level,
+        Z_DEFLATED,
+        MAX_WBITS,
+        DEF_MEM_LEVEL,
+        Z_DEFAULT_STRATEGY,
+        version,
+        stream_size,
+    )
+}
+
+/// # Safety
+///
+/// `strm` must be null or point to a stream set up by [`deflateInit2_`], with
+/// `next_in`/`avail_in` and `next_out`/`avail_out` describing valid buffers.
+#[no_mangle]
+pub unsafe extern "C" fn deflate(strm: *mut ZStream, flush: c_int) -> c_int {
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    let Some(InternalState::Deflate(stream)) = strm.state.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    let flush = match flush {
+        Z_NO_FLUSH => Flush::None,
+        Z_PARTIAL_FLUSH | Z_SYNC_FLUSH | Z_FULL_FLUSH | Z_BLOCK => Flush::Sync,
+        Z_FINISH => Flush::Finish,
+        _ => return Z_STREAM_ERROR,
+    };
+    let Some((input, output)) = buffers(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    let (consumed, produced, done) = stream.deflate(input, output, flush);
+    advance(strm, consumed, produced);
+    strm.adler = stream.framing.checksum() as c_ulong;
+    match (done, consumed + produced) {
+        (true, _) => Z_STREAM_END,
+        (false, 0) => Z_BUF_ERROR,
+        _ => Z_OK,
+    }
+}
+
+/// # Safety
+///
+/// `strm` must be null or point to a `z_stream` whose `state` is null or was
+/// set up by [`deflateInit2_`] and not yet ended.
+#[no_mangle]
+pub unsafe extern "C" fn deflateEnd(strm: *mut ZStream) -> c_int {
+    end(strm, true)
+}
+
+/// # Safety
+///
+/// Same as [`deflateInit2_`].
+#[no_mangle]
+pub unsafe extern "C" fn inflateInit2_(
+    strm: *mut ZStream,
+    window_bits: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    if !check_version(version, stream_size) {
+        return Z_VERSION_ERROR;
+    }
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    let framing = match window_bits {
+        0 => Some(framing_for(MAX_WBITS)),
+        40..=47 => Some(None),
+        _ => framing_for(window_bits).map(Some),
+    };
+    let Some(framing) = framing else {
+        return Z_STREAM_ERROR;
+    };
+    strm.adler = framing.as_ref().map_or(0, |framing| framing.checksum()) as c_ulong;
+    start(strm, InternalState::Inflate(InflateStream::new(framing)))
+}
+
+/// # Safety
+///
+/// Same as [`deflateInit2_`].
+#[no_mangle]
+pub unsafe extern "C" fn inflateInit_(
+    strm: *mut ZStream,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    inflateInit2_(strm, MAX_WBITS, version, stream_size)
+}
+
+/// # Safety
+///
+/// `strm` must be null or point to a stream set up by [`inflateInit2_`], with
+/// `next_in`/`avail_in` and `next_out`/`avail_out` describing valid buffers.
+#[no_mangle]
+pub unsafe extern "C" fn inflate(strm: *mut ZStream, flush: c_int) -> c_int {
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    let Some(InternalState::Inflate(stream)) = strm.state.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    if !(Z_NO_FLUSH..=Z_TREES).contains(&flush) {
+        return Z_STREAM_ERROR;
+    }
+    let Some((input, output)) = buffers(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    match stream.inflate(input, output) {
+        Ok((consumed, produced, done)) => {
+            advance(strm, consumed, produced);
+            strm.adler = stream.framing.checksum() as c_ulong;
+            match (done, consumed + produced) {
+                (true, _) => Z_STREAM_END,
+                (false, 0) => Z_BUF_ERROR,
+                _ => Z_OK,
+            }
+        }
+        Err(msg) => {
+            strm.msg = msg.as_ptr();
+            Z_DATA_ERROR
+        }
+    }
+}
+
+/// # Safety
+///
+/// `strm` must be null or point to a `z_stream` whose `state` is null or was
+/// set up by [`inflateInit2_`] and not yet ended.
+#[no_mangle]
+pub unsafe extern "C" fn inflateEnd(strm: *mut ZStream) -> c_int {
+    end(strm, false)
+}
+
+#[no_mangle]
+pub extern "C" fn compressBound(source_len: c_ulong) -> c_ulong {
+    (compress_bound(source_len as usize) + ZLIB_OVERHEAD) as c_ulong
+}
+
+/// # Safety
+///
+/// `dest_len` must be null or point to the writable length of `dest`, and
+/// `source` must be readable for `source_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn compress2(
+    dest: *mut u8,
+    dest_len: *mut c_ulong,
+    source: *const u8,
+    source_len: c_ulong,
+    level: c_int,
+) -> c_int {
+    let Some(encoder) = encoder_for(level, Strategy::Default).filter(|_| !dest_len.is_null())
+    else {
+        return Z_STREAM_ERROR;
+    };
+    let (Some(input), Some(output)) = (
+        raw_slice(source, source_len),
+        raw_slice_mut(dest, *dest_len),
+    ) else {
+        return Z_STREAM_ERROR;
+    };
+    let mut stream = DeflateStream::new(Box::new(Zlib::default()), encoder);
+    let (_, produced, done) = stream.deflate(input, output, Flush::Finish);
+    *dest_len = produced as c_ulong;
+    if done {
+        Z_OK
+    } else {
+        Z_BUF_ERROR
+    }
+}
+
+/// # Safety
+///
+/// Same as [`compress2`].
+#[no_mangle]
+pub unsafe extern "C" fn compress(
+    dest: *mut u8,
+    dest_len: *mut c_ulong,
+    source: *const u8,
+    source_len: c_ulong,
+) -> c_int {
+    compress2(dest, dest_len, source, source_len, Z_DEFAULT_COMPRESSION)
+}
+
+/// # Safety
+///
+/// Same as [`compress2`].
+#[no_mangle]
+pub unsafe extern "C" fn uncompress(
+    dest: *mut u8,
+    dest_len: *mut c_ulong,
+    source: *const u8,
+    source_len: c_ulong,
+) -> c_int {
+    if dest_len.is_null() {
+        return Z_STREAM_ERROR;
+    }
+    let (Some(input), Some(output)) = (
+        raw_slice(source, source_len),
+        raw_slice_mut(dest, *dest_len),
+    ) else {
+        return Z_STREAM_ERROR;
+    };
+    let mut stream = InflateStream::new(Some(Box::new(Zlib::default())));
+    match stream.inflate(input, output) {
+        Ok((_, produced, done)) => {
+            *dest_len = produced as c_ulong;
+            match done {
+                true => Z_OK,
+                false if produced == output.len() => Z_BUF_ERROR,
+                false => Z_DATA_ERROR,
+            }
+        }
+        Err(_) => Z_DATA_ERROR,
+    }
+}
+
+/// # Safety
+///
+/// `buf` must be null or readable for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn crc32(crc: c_ulong, buf: *const u8, len: c_uint) -> c_ulong {
+    let Some(data) = raw_slice(buf, len as c_ulong).filter(|_| !buf.is_null()) else {
+        return 0;
+    };
+    let mut hasher = Hasher::new_with_initial(crc as u32);
+    hasher.update(data);
+    hasher.finalize() as c_ulong
+}
+
+/// # Safety
+///
+/// `buf` must be null or readable for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn adler32(adler: c_ulong, buf: *const u8, len: c_uint) -> c_ulong {
+    let Some(data) = raw_slice(buf, len as c_ulong).filter(|_| !buf.is_null()) else {
+        return 1;
+    };
+    let mut checksum = Adler32::from_checksum(adler as u32);
+    checksum.update(data);
+    checksum.finalize() as c_ulong
+}
+
+unsafe fn raw_slice<'a>(data: *const u8, len: c_ulong) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, len) => Some(slice::from_raw_parts(data, len as usize)),
+    }
+}
+
+unsafe fn raw_slice_mut<'a>(data: *mut u8, len: c_ulong) -> Option<&'a mut [u8]> {
+    match (data.is_null(), len) {
+        (_, 0) => Some(&mut []),
+        (true, _) => None,
+        (false, len) => Some(slice::from_raw_parts_mut(data, len as usize)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{alloc, dealloc, Layout};
+
+    use super::*;
+    use crate::test_util::sample;
+
+    fn stream() -> ZStream {
+        ZStream {
+            next_in: ptr::null(),
+            avail_in: 0,
+            total_in: 0,
+            next_out: ptr::null_mut(),
+            avail_out: 0,
+            total_out: 0,
+            msg: ptr::null(),
+            state: ptr::null_mut(),
+            zalloc: None,
+            zfree: None,
+            opaque: ptr::null_mut(),
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        }
+    }
+
+    const SIZE: c_int = mem::size_of::<ZStream>() as c_int;
+
+    unsafe fn pump(
+        strm: &mut ZStream,
+        input: &[u8],
+        chunk: usize,
+        step: unsafe extern "C" fn(*mut ZStream, c_int) -> c_int,
+        flush: c_int,
+    ) -> Vec<u8> {
+        let mut output = vec![];
+        let mut buf = vec![0; chunk];
+        let mut pos = 0;
+        loop {
+            let end = input.len().min(pos + chunk);
+            strm.next_in = input[pos..].as_ptr();
+            strm.avail_in = (end - pos) as c_uint;
+            strm.next_out = buf.as_mut_ptr();
+            strm.avail_out = chunk as c_uint;
+            let flush = if end == input.len() {
+                flush
+            } else {
+                Z_NO_FLUSH
+            };
+            let ret = step(strm, flush);
+            assert!(
+                ret == Z_OK || ret == Z_STREAM_END || ret == Z_BUF_ERROR,
+                "{ret}"
+            );
+            pos = end - strm.avail_in as usize;
+            output.extend_from_slice(&buf[..chunk - strm.avail_out as usize]);
+            if ret == Z_STREAM_END {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(c"1.3.1", unsafe { CStr::from_ptr(zlibVersion()) });
+        let data = b"123456789";
+        assert_eq!(0xcbf43926, unsafe { crc32(0, data.as_ptr(), 9) });
+        assert_eq!(0, unsafe { crc32(0, ptr::null(), 0) });
+        assert_eq!(0x091e01de, unsafe { adler32(1, data.as_ptr(), 9) });
+    }
+
+    #[test]
+    fn test_compress2_round_trip() {
+        let input = sample(50_000);
+        let mut dest = vec![0; compressBound(input.len() as c_ulong) as usize];
+        let mut dest_len = dest.len() as c_ulong;
+        let ret = unsafe {
+            compress2(
+                dest.as_mut_ptr(),
+                &mut dest_len,
+                input.as_ptr(),
+                input.len() as c_ulong,
+                9,
+            )
+        };
+        assert_eq!(Z_OK, ret);
+        assert_eq!(crate::zlib::HEADER, dest[..2]);
+        let mut output = vec![0; input.len()];
+        let mut output_len = output.len() as c_ulong;
+        let ret = unsafe {
+            uncompress(
+                output.as_mut_ptr(),
+                &mut output_len,
+                dest.as_ptr(),
+                dest_len,
+            )
+        };
+        assert_eq!(Z_OK, ret);
+        assert_eq!(input, output);
+        let mut short_len = 10;
+        let ret =
+            unsafe { uncompress(output.as_mut_ptr(), &mut short_len, dest.as_ptr(), dest_len) };
+        assert_eq!(Z_BUF_ERROR, ret);
+        let ret = unsafe { uncompress(output.as_mut_ptr(), &mut output_len, dest.as_ptr(), 20) };
+        assert_eq!(Z_DATA_ERROR, ret);
+    }
+
+    #[test]
+    fn test_streaming() {
+        let input = sample(50_000);
+        let version = VERSION.as_ptr();
+        for (window_bits, inflate_bits) in [(15, 15), (-15, -15), (31, 31), (31, 47), (15, 47)] {
+            let mut strm = stream();
+            let ret = unsafe {
+                deflateInit2_(
+                    &mut strm,
+                    6,
+                    Z_DEFLATED,
+                    window_bits,
+                    8,
+                    Z_DEFAULT_STRATEGY,
+                    version,
+                    SIZE,
+                )
+            };
+            assert_eq!(Z_OK, ret);
+            let compressed = unsafe { pump(&mut strm, &input, 777, deflate, Z_FINISH) };
+            assert_eq!(input.len() as c_ulong, strm.total_in);
+            assert_eq!(compressed.len() as c_ulong, strm.total_out);
+            assert_eq!(Z_OK, unsafe { deflateEnd(&mut strm) });
+            let mut strm = stream();
+            assert_eq!(Z_OK, unsafe {
+                inflateInit2_(&mut strm, inflate_bits, version, SIZE)
+            });
+            let output = unsafe { pump(&mut strm, &compressed, 5, inflate, Z_NO_FLUSH) };
+            assert_eq!(input, output);
+            assert_eq!(Z_OK, unsafe { inflateEnd(&mut strm) });
+            assert_eq!(Z_STREAM_ERROR, unsafe { inflateEnd(&mut strm) });
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        let input = sample(50_000);
+        let mut sizes = vec![];
+        for level in [Z_NO_COMPRESSION, Z_BEST_SPEED, Z_BEST_COMPRESSION] {
+            let mut dest = vec![0; compressBound(input.len() as c_ulong) as usize];
+            let mut dest_len = dest.len() as c_ulong;
+            let ret = unsafe {
+                compress2(
+                    dest.as_mut_ptr(),
+                    &mut dest_len,
+                    input.as_ptr(),
+                    input.len() as c_ulong,
+                    level,
+                )
+            };
+            assert_eq!(Z_OK, ret);
+            sizes.push(dest_len as usize);
+        }
+        assert!(sizes[0] > input.len());
+        assert!(sizes[0] > sizes[1] && sizes[1] > sizes[2]);
+        let mut strm = stream();
+        let ret = unsafe {
+            deflateInit2_(
+                &mut strm,
+                Z_NO_COMPRESSION,
+                Z_DEFLATED,
+                -15,
+                8,
+                Z_DEFAULT_STRATEGY,
+                VERSION.as_ptr(),
+                SIZE,
+            )
+        };
+        assert_eq!(Z_OK, ret);
+        let compressed = unsafe { pump(&mut strm, b"stored", 64, deflate, Z_FINISH) };
+        assert_eq!(b"\x01\x06\x00\xf9\xffstored", compressed.as_slice());
+        assert_eq!(Z_OK, unsafe { deflateEnd(&mut strm) });
+    }
+
+    unsafe extern "C" fn counted_alloc(
+        opaque: *mut c_void,
+        items: c_uint,
+        size: c_uint,
+    ) -> *mut c_void {
+        let layout = Layout::from_size_align((items * size) as usize, 16).unwrap();
+        let ptr = alloc(layout);
+        (*opaque.cast::<Vec<(usize, Layout)>>()).push((ptr as usize, layout));
+        ptr.cast()
+    }
+
+    unsafe extern "C" fn counted_free(opaque: *mut c_void, ptr: *mut c_void) {
+        let live = &mut *opaque.cast::<Vec<(usize, Layout)>>();
+        let i = live.iter().position(|&(p, _)| p == ptr as usize).unwrap();
+        dealloc(ptr.cast(), live.remove(i).1);
+    }
+
+    #[test]
+    fn test_custom_allocator() {
+        let mut live: Vec<(usize, Layout)> = vec![];
+        let mut strm = stream();
+        strm.zalloc = Some(counted_alloc);
+        strm.zfree = Some(counted_free);
+        strm.opaque = (&mut live as *mut Vec<_>).cast();
+        let version = VERSION.as_ptr();
+        assert_eq!(Z_OK, unsafe { deflateInit_(&mut strm, 6, version, SIZE) });
+        let compressed = unsafe { pump(&mut strm, &sample(50_000), 777, deflate, Z_FINISH) };
+        assert_eq!(Z_OK, unsafe { deflateEnd(&mut strm) });
+        assert_eq!(Z_OK, unsafe { inflateInit_(&mut strm, version, SIZE) });
+        assert_eq!(
+            1,
+            unsafe { &*strm.opaque.cast::<Vec<(usize, Layout)>>() }.len()
+        );
+        let output = unsafe { pump(&mut strm, &compressed, 5, inflate, Z_NO_FLUSH) };
+        assert_eq!(sample(50_000), output);
+        assert_eq!(Z_OK, unsafe { inflateEnd(&mut strm) });
+        assert!(live.is_empty());
+        let mut strm = stream();
+        strm.zalloc = Some(counted_alloc);
+        assert_eq!(Z_STREAM_ERROR, unsafe {
+            inflateInit_(&mut strm, version, SIZE)
+        });
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut strm = stream();
+        let version = VERSION.as_ptr();
+        assert_eq!(Z_VERSION_ERROR, unsafe {
+            inflateInit_(&mut strm, version, SIZE - 1)
+        });
+        assert_eq!(Z_STREAM_ERROR, unsafe {
+            inflateInit2_(&mut strm, 7, version, SIZE)
+        });
+        assert_eq!(Z_STREAM_ERROR, unsafe {
+            deflateInit_(&mut strm, 10, version, SIZE)
+        });
+        assert_eq!(Z_OK, unsafe { inflateInit_(&mut strm, version, SIZE) });
+        assert_eq!(Z_STREAM_ERROR, unsafe { deflate(&mut strm, Z_FINISH) });
+        let garbage = [0x78, 0x9d, 0, 0];
+        strm.next_in = garbage.as_ptr();
+        strm.avail_in = 4;
+        let mut out = [0; 16];
+        strm.next_out = out.as_mut_ptr();
+        strm.avail_out = 16;
+        assert_eq!(Z_DATA_ERROR, unsafe { inflate(&mut strm, Z_NO_FLUSH) });
+        assert_eq!(c"incorrect header check", unsafe {
+            CStr::from_ptr(strm.msg)
+        });
+        assert_eq!(Z_OK, unsafe { inflateEnd(&mut strm) });
+    }
+}
@@ -0,0 +1,381 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    bitstream::{slice::SliceWriter, BitWrite, BYTE_SIZE},
+    deflate::{DeflateToken, Strategy, Variant, BFINAL_NO, BFINAL_YES, BTYPE_FIXED, BTYPE_STORED},
+    encoder::{stored_tokens, Encoder, BLOCK_SIZE},
+    huffman::generate::{generate_fixed_code, generate_stored_code},
+    HuffmanToken,
+};
+
+pub const MAX_HEADER_SIZE: usize = 289;
+
+pub fn compress_bound(len: usize) -> usize {
+    len + len.div_ceil(BYTE_SIZE) + (len / BLOCK_SIZE + 2) * MAX_HEADER_SIZE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    None,
+    Sync,
+    Finish,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    NeedsInput,
+    NeedsOutput,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub consumed: usize,
+    pub produced: usize,
+    pub state: State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Idle,
+    Block,
+    Sync,
+    Finish,
+    Done,
+}
+
+#[derive(Debug)]
+pub struct Compressor {
+    encoder: Encoder,
+    block_size: usize,
+    buffer: Vec<u8>,
+    dictionary_len: usize,
+    tokens: Vec<DeflateToken>,
+    next_token: usize,
+    next_item: usize,
+    ll_code: Vec<HuffmanToken>,
+    d_code: Vec<HuffmanToken>,
+    bits: u64,
+    count: usize,
+    stage: Stage,
+    then: Stage,
+    flushed: bool,
+    total_in: u64,
+    total_out: u64,
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compressor {
+    pub fn new() -> Self {
+        Self::with_encoder(Encoder::new())
+    }
+
+    pub fn with_encoder(encoder: Encoder) -> Self {
+        Self {
+            encoder,
+            block_size: BLOCK_SIZE,
+            buffer: vec![],
+            dictionary_len: 0,
+            tokens: vec![],
+            next_token: 0,
+            next_item: 0,
+            ll_code: vec![],
+            d_code: vec![],
+            bits: 0,
+            count: 0,
+            stage: Stage::Idle,
+            then: Stage::Idle,
+            flushed: false,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.clamp(1, BLOCK_SIZE);
+        self
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub fn compress(&mut self, input: &[u8], output: &mut [u8], flush: Flush) -> Status {
+        let mut writer = SliceWriter::with_pending(output, self.bits, self.count);
+        let mut consumed = 0;
+        let state = loop {
+            match self.stage {
+                Stage::Block => {
+                    if !self.write_tokens(&mut writer) {
+                        break State::NeedsOutput;
+                    }
+                    self.stage = self.then;
+                }
+                Stage::Sync => {
+                    if !self.write_sync(&mut writer) {
+                        break State::NeedsOutput;
+                    }
+                    self.flushed = true;
+                    self.stage = Stage::Idle;
+                }
+                Stage::Finish => {
+                    writer.align();
+                    if !writer.is_drained() {
+                        break State::NeedsOutput;
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => break State::Done,
+                Stage::Idle => {
+                    let pending = self.buffer.len() - self.dictionary_len;
+                    let n = (self.block_size - pending).min(input.len() - consumed);
+                    self.buffer
+                        .extend_from_slice(&input[consumed..consumed + n]);
+                    consumed += n;
+                    self.flushed &= n == 0;
+                    if pending + n == self.block_size {
+                        self.start_block(false, Stage::Idle);
+                        continue;
+                    }
+                    match flush {
+                        Flush::None => break State::NeedsInput,
+                        Flush::Sync if self.flushed => break State::NeedsInput,
+                        Flush::Sync if pending + n == 0 => self.stage = Stage::Sync,
+                        Flush::Sync => self.start_block(false, Stage::Sync),
+                        Flush::Finish => self.start_block(true, Stage::Finish),
+                    }
+                }
+            }
+        };
+        (self.bits, self.count) = writer.pending();
+        self.total_in += consumed as u64;
+        self.total_out += writer.written() as u64;
+        Status {
+            consumed,
+            produced: writer.written(),
+            state,
+        }
+    }
+
+    fn start_block(&mut self, last: bool, then: Stage) {
+        if self.encoder.strategy() == Strategy::Stored {
+            self.tokens = stored_tokens(&self.buffer[self.dictionary_len..], last);
+        } else {
+            let tokens = self
+                .encoder
+                .tokens(&self.buffer, self.dictionary_len)
+                .collect::<Vec<_>>();
+            let bfinal = if last { BFINAL_YES } else { BFINAL_NO };
+            self.tokens = self.encoder.block_header(&tokens, bfinal);
+            self.tokens.extend(tokens);
+            self.tokens.push(DeflateToken::EndOfBlock);
+        }
+        (self.next_token, self.next_item) = (0, 0);
+        self.stage = Stage::Block;
+        self.then = then;
+        let keep = self
+            .buffer
+            .len()
+            .saturating_sub(self.encoder.variant().max_distance());
+        self.buffer.drain(..keep);
+        self.dictionary_len = self.buffer.len();
+    }
+
+    fn write_tokens(&mut self, writer: &mut SliceWriter) -> bool {
+        while self.next_token < self.tokens.len() {
+            let token = &self.tokens[self.next_token];
+            let mut sink = Resume::new(writer, self.next_item, self.encoder.variant());
+            token.write_to_ostream(&self.ll_code, &self.d_code, &mut sink);
+            if sink.full {
+                self.next_item = sink.written;
+                return false;
+            }
+            match token {
+                DeflateToken::Btype(BTYPE_FIXED) => {
+                    (self.ll_code, self.d_code) = generate_fixed_code();
+                }
+                DeflateToken::Btype(BTYPE_STORED) => {
+                    (self.ll_code, self.d_code) = generate_stored_code();
+                }
+                DeflateToken::Tables(tables) => (self.ll_code, self.d_code) = tables.codes(),
+                _ => {}
+            }
+            (self.next_token, self.next_item) = (self.next_token + 1, 0);
+        }
+        true
+    }
+
+    fn write_sync(&mut self, writer: &mut SliceWriter) -> bool {
+        let mut sink = Resume::new(writer, self.next_item, self.encoder.variant());
+        sink.write_numerical(1, BFINAL_NO as _);
+        sink.write_numerical(2, BTYPE_STORED as _);
+        sink.align();
+        sink.write_numerical(16, 0);
+        sink.write_numerical(16, 0xffff);
+        self.next_item = sink.written;
+        if sink.full || !writer.is_drained() {
+            return false;
+        }
+        self.next_item = 0;
+        true
+    }
+}
+
+struct Resume<'s, 'a> {
+    writer: &'s mut SliceWriter<'a>,
+    skip: usize,
+    written: usize,
+    full: bool,
+    variant: Variant,
+}
+
+impl<'s, 'a> Resume<'s, 'a> {
+    fn new(writer: &'s mut SliceWriter<'a>, skip: usize, variant: Variant) -> Self {
+        Self {
+            writer,
+            skip,
+            written: 0,
+            full: false,
+            variant,
+        }
+    }
+
+    fn item(&mut self, bits: usize, write: impl FnOnce(&mut SliceWriter<'a>)) {
+        if self.written < self.skip {
+            self.written += 1;
+        } else if !self.full && self.writer.fits(bits) {
+            write(self.writer);
+            self.written += 1;
+        } else {
+            self.full = true;
+        }
+    }
+}
+
+impl BitWrite for Resume<'_, '_> {
+    fn write_code(&mut self, len: usize, token: usize) {
+        self.item(len, |writer| writer.write_code(len, token));
+    }
+
+    fn write_numerical(&mut self, n: usize, value: usize) {
+        self.item(n, |writer| writer.write_numerical(n, value));
+    }
+
+    fn align(&mut self) {
+        self.item(BYTE_SIZE - 1, |writer| writer.align());
+    }
+
+    fn variant(&self) -> Variant {
+        self.variant
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deflate::Strategy, inflate::inflate, test_util::sample};
+
+    fn compress_all(compressor: &mut Compressor, input: &[u8], chunk: usize) -> Vec<u8> {
+        let mut output = vec![];
+        let mut buf = vec![0; chunk];
+        let mut pos = 0;
+        loop {
+            let end = input.len().min(pos + chunk);
+            let flush = if end == input.len() {
+                Flush::Finish
+            } else {
+                Flush::None
+            };
+            let status = compressor.compress(&input[pos..end], &mut buf, flush);
+            pos += status.consumed;
+            output.extend_from_slice(&buf[..status.produced]);
+            if status.state == State::Done {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn trivial_test() {
+        let mut output = [0; 8];
+        let status = Compressor::new().compress(b"", &mut output, Flush::Finish);
+        assert_eq!(State::Done, status.state);
+        assert_eq!([3, 0], output[..status.produced]);
+    }
+
+    #[test]
+    fn test_matches_encoder_output() {
+        let input = &sample(100_000)[..BLOCK_SIZE / 2];
+        let output = compress_all(&mut Compressor::new(), input, 1 << 20);
+        assert_eq!(Encoder::new().deflate(input), output);
+    }
+
+    #[test]
+    fn test_small_buffers() {
+        let input = sample(100_000);
+        for encoder in [
+            Encoder::new(),
+            Encoder::new().with_strategy(Strategy::Fixed),
+            Encoder::new().with_variant(Variant::Deflate64),
+            Encoder::new().with_level(0),
+            Encoder::new().with_level(1),
+        ] {
+            let mut compressor = Compressor::with_encoder(encoder);
+            let output = compress_all(&mut compressor, &input, 7);
+            assert_eq!(input.len() as u64, compressor.total_in());
+            assert_eq!(output.len() as u64, compressor.total_out());
+            let decoded = crate::inflate::inflate_with(&output, encoder.variant()).unwrap();
+            assert_eq!(input, decoded);
+        }
+    }
+
+    #[test]
+    fn test_sync_flush() {
+        let mut compressor = Compressor::new();
+        let mut output = [0; 64];
+        let status = compressor.compress(b"Fa-la-la-la", &mut output, Flush::Sync);
+        assert_eq!(State::NeedsInput, status.state);
+        assert_eq!(
+            [0, 0, 0xff, 0xff],
+            output[status.produced - 4..status.produced]
+        );
+        let mut stream = output[..status.produced].to_vec();
+        let status = compressor.compress(b"", &mut output, Flush::Sync);
+        assert_eq!(0, status.produced);
+        let status = compressor.compress(b"", &mut output, Flush::Finish);
+        assert_eq!(State::Done, status.state);
+        stream.extend_from_slice(&output[..status.produced]);
+        assert_eq!(b"Fa-la-la-la".to_vec(), inflate(&stream).unwrap());
+    }
+
+    #[test]
+    fn test_compress_bound() {
+        let mut state = 0x2545f491_u32;
+        let input = (0..3 * BLOCK_SIZE + 123)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        let mut output = vec![0; compress_bound(input.len())];
+        let status = Compressor::new().compress(&input, &mut output, Flush::Finish);
+        assert_eq!(State::Done, status.state);
+        assert_eq!(input.len(), status.consumed);
+        assert_eq!(input, inflate(&output[..status.produced]).unwrap());
+    }
+}
@@ -0,0 +1,528 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::{
+    compressor::{State, Status},
+    deflate::{Variant, BTYPE_DYNAMIC, BTYPE_FIXED, BTYPE_STORED, END_OF_BLOCK},
+    huffman::{
+        decode::{HuffmanDecoder, Lookup},
+        dynamic::CL_ORDER,
+    },
+    inflate::{distance_base, length_base, InflateError, FIXED_DECODERS},
+    limits::DecodeLimits,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Header,
+    StoredLength,
+    Stored(usize),
+    Tables,
+    CodeLengthCodes,
+    CodeLengths,
+    Codes,
+    Copy { length: usize, distance: usize },
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symbol {
+    Literal(u8),
+    EndOfBlock,
+    Match { length: usize, distance: usize },
+}
+
+#[derive(Debug)]
+pub struct Decompressor {
+    variant: Variant,
+    stage: Stage,
+    last: bool,
+    bits: u128,
+    count: usize,
+    hlit: usize,
+    hdist: usize,
+    hclen: usize,
+    cl_lengths: [u8; CL_ORDER.len()],
+    cl_decoder: Option<HuffmanDecoder>,
+    lengths: Vec<u8>,
+    decoders: Option<Box<(HuffmanDecoder, HuffmanDecoder)>>,
+    window: Vec<u8>,
+    window_pos: usize,
+    limits: DecodeLimits,
+    blocks: u64,
+    total_in: u64,
+    total_out: u64,
+}
+
+impl Default for Decompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decompressor {
+    pub fn new() -> Self {
+        Self::with_variant(Variant::Deflate)
+    }
+
+    pub fn with_variant(variant: Variant) -> Self {
+        Self {
+            variant,
+            stage: Stage::Header,
+            last: false,
+            bits: 0,
+            count: 0,
+            hlit: 0,
+            hdist: 0,
+            hclen: 0,
+            cl_lengths: [0; CL_ORDER.len()],
+            cl_decoder: None,
+            lengths: vec![],
+            decoders: None,
+            window: vec![0; variant.max_distance()],
+            window_pos: 0,
+            limits: DecodeLimits::default(),
+            blocks: 0,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stage == Stage::Done
+    }
+
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8]) -> Result<Status, InflateError> {
+        let mut input = Input {
+            data: input,
+            pos: 0,
+        };
+        let room = self
+            .limits
+            .output_room(self.total_out)
+            .min(output.len() as u64);
+        let mut produced = 0;
+        let result = self.run(&mut input, &mut output[..room as usize], &mut produced);
+        self.total_in += input.pos as u64;
+        let state = result?;
+        self.limits.check_output(self.total_out)?;
+        self.limits.check_ratio(self.total_in, self.total_out)?;
+        Ok(Status {
+            consumed: input.pos,
+            produced,
+            state,
+        })
+    }
+
+    fn run(
+        &mut self,
+        input: &mut Input,
+        output: &mut [u8],
+        produced: &mut usize,
+    ) -> Result<State, InflateError> {
+        loop {
+            match self.stage {
+                Stage::Header if self.last => self.stage = Stage::Done,
+                Stage::Header => {
+                    if !self.fill(input, 3) {
+                        return Ok(State::NeedsInput);
+                    }
+                    self.blocks += 1;
+                    self.limits.check_blocks(self.blocks)?;
+                    self.last = self.take(1) == 1;
+                    self.stage = match self.take(2) as u16 {
+                        BTYPE_STORED => Stage::StoredLength,
+                        BTYPE_FIXED => {
+                            self.decoders = None;
+                            Stage::Codes
+                        }
+                        BTYPE_DYNAMIC => Stage::Tables,
+                        _ => return Err(InflateError::InvalidBlockType),
+                    };
+                }
+                Stage::StoredLength => {
+                    self.take(self.count % 8);
+                    if !self.fill(input, 32) {
+                        return Ok(State::NeedsInput);
+                    }
+                    let len = self.take(16);
+                    if len != !self.take(16) & 0xffff {
+                        return Err(InflateError::InvalidStoredLength);
+                    }
+                    self.stage = Stage::Stored(len);
+                }
+                Stage::Stored(0) => self.stage = Stage::Header,
+                Stage::Stored(remain) => {
+                    if *produced == output.len() {
+                        return Ok(State::NeedsOutput);
+                    }
+                    if self.count > 0 {
+                        output[*produced] = self.take(8) as u8;
+                    } else if input.pos < input.data.len() {
+                        output[*produced] = input.data[input.pos];
+                        input.pos += 1;
+                    } else {
+                        return Ok(State::NeedsInput);
+                    }
+                    self.emit(output[*produced]);
+                    *produced += 1;
+                    self.stage = Stage::Stored(remain - 1);
+                }
+                Stage::Tables => {
+                    if !self.fill(input, 14) {
+                        return Ok(State::NeedsInput);
+                    }
+                    self.hlit = self.take(5) + 257;
+                    self.hdist = self.take(5) + 1;
+                    self.hclen = self.take(4) + 4;
+                    self.cl_lengths = [0; CL_ORDER.len()];
+                    self.lengths.clear();
+                    self.stage = Stage::CodeLengthCodes;
+                }
+                Stage::CodeLengthCodes => {
+                    while self.lengths.len() < self.hclen {
+                        if !self.fill(input, 3) {
+                            return Ok(State::NeedsInput);
+                        }
+                        self.cl_lengths[CL_ORDER[self.lengths.len()]] = self.take(3) as u8;
+                        self.lengths.push(0);
+                    }
+                    self.cl_decoder = Some(
+                        HuffmanDecoder::new(&self.cl_lengths)
+                            .ok_or(InflateError::InvalidCodeLengths)?,
+                    );
+                    self.lengths.clear();
+                    self.stage = Stage::CodeLengths;
+                }
+                Stage::CodeLengths => {
+                    while self.lengths.len() < self.hlit + self.hdist {
+                        let Some((len, repeat)) = self.decode_length(input)? else {
+                            return Ok(State::NeedsInput);
+                        };
+                        if self.lengths.len() + repeat > self.hlit + self.hdist {
+                            return Err(InflateError::InvalidCodeLengths);
+                        }
+                        self.lengths.extend((0..repeat).map(|_| len));
+                    }
+                    if self.lengths[END_OF_BLOCK] == 0 {
+                        return Err(InflateError::InvalidCodeLengths);
+                    }
+                    let ll_decoder = HuffmanDecoder::new(&self.lengths[..self.hlit]);
+                    let d_decoder = HuffmanDecoder::new(&self.lengths[self.hlit..]);
+                    let decoders = ll_decoder
+                        .zip(d_decoder)
+                        .ok_or(InflateError::InvalidCodeLengths)?;
+                    self.decoders = Some(Box::new(decoders));
+                    self.stage = Stage::Codes;
+                }
+                Stage::Codes => {
+                    let Some((symbol, bits)) = self.decode_symbol(input)? else {
+                        return Ok(State::NeedsInput);
+                    };
+                    if *produced == output.len() && symbol != Symbol::EndOfBlock {
+                        return Ok(State::NeedsOutput);
+                    }
+                    self.take(bits);
+                    match symbol {
+                        Symbol::Literal(byte) => {
+                            output[*produced] = self.emit(byte);
+                            *produced += 1;
+                        }
+                        Symbol::EndOfBlock => self.stage = Stage::Header,
+                        Symbol::Match { length, distance } => {
+                            self.stage = Stage::Copy { length, distance }
+                        }
+                    }
+                }
+                Stage::Copy { length: 0, .. } => self.stage = Stage::Codes,
+                Stage::Copy { length, distance } => {
+                    let n = length.min(output.len() - *produced);
+                    if n == 0 {
+                        return Ok(State::NeedsOutput);
+                    }
+                    let window_size = self.window.len();
+                    for _ in 0..n {
+                        let byte =
+                            self.window[(self.window_pos + window_size - distance) % window_size];
+                        output[*produced] = self.emit(byte);
+                        *produced += 1;
+                    }
+                    self.stage = Stage::Copy {
+                        length: length - n,
+                        distance,
+                    };
+                }
+                Stage::Done => return Ok(State::Done),
+            }
+        }
+    }
+
+    fn fill(&mut self, input: &mut Input, n: usize) -> bool {
+        while self.count < n {
+            if !self.pull(input) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn pull(&mut self, input: &mut Input) -> bool {
+        match input.data.get(input.pos) {
+            Some(byte) => {
+                self.bits |= (*byte as u128) << self.count;
+                self.count += 8;
+                input.pos += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn peek(&self, offset: usize, n: usize) -> usize {
+        (self.bits >> offset) as usize & ((1 << n) - 1)
+    }
+
+    fn take(&mut self, n: usize) -> usize {
+        let value = self.peek(0, n);
+        self.bits >>= n;
+        self.count -= n;
+        value
+    }
+
+    fn lookup(
+        &self,
+        decoder: &HuffmanDecoder,
+        offset: usize,
+    ) -> Result<Option<(usize, usize)>, InflateError> {
+        match decoder.lookup(self.bits >> offset, self.count.saturating_sub(offset)) {
+            Lookup::Symbol(symbol, len) => Ok(Some((symbol as usize, len))),
+            Lookup::Incomplete => Ok(None),
+            Lookup::Invalid => Err(InflateError::InvalidSymbol),
+        }
+    }
+
+    fn decode_length(&mut self, input: &mut Input) -> Result<Option<(u8, usize)>, InflateError> {
+        loop {
+            if let Some((len, repeat, bits)) = self.try_decode_length()? {
+                self.take(bits);
+                return Ok(Some((len, repeat)));
+            }
+            if !self.pull(input) {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn try_decode_length(&self) -> Result<Option<(u8, usize, usize)>, InflateError> {
+        let decoder = self.cl_decoder.as_ref().unwrap();
+        let Some((symbol, len)) = self
+            .lookup(decoder, 0)
+            .map_err(|_| InflateError::InvalidCodeLengths)?
+        else {
+            return Ok(None);
+        };
+        let (extra, base) = match symbol {
+            0..=15 => (0, 1),
+            16 => (2, 3),
+            17 => (3, 3),
+            _ => (7, 11),
+        };
+        if self.count < len + extra {
+            return Ok(None);
+        }
+        let repeat = base + self.peek(len, extra);
+        let value = match symbol {
+            0..=15 => symbol as u8,
+            16 => *self
+                .lengths
+                .last()
+                .ok_or(InflateError::InvalidCodeLengths)?,
+            _ => 0,
+        };
+        Ok(Some((value, repeat, len + extra)))
+    }
+
+    fn decode_symbol(
+        &mut self,
+        input: &mut Input,
+    ) -> Result<Option<(Symbol, usize)>, InflateError> {
+        loop {
+            if let Some(decoded) = self.try_decode_symbol()? {
+                return Ok(Some(decoded));
+            }
+            if !self.pull(input) {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn try_decode_symbol(&self) -> Result<Option<(Symbol, usize)>, InflateError> {
+        let (ll_decoder, d_decoder) = match self.decoders {
+            Some(ref decoders) => (&decoders.0, &decoders.1),
+            None => (&FIXED_DECODERS.0, &FIXED_DECODERS.1),
+        };
+        let Some((symbol, mut bits)) = self.lookup(ll_decoder, 0)? else {
+            return Ok(None);
+        };
+        if symbol < END_OF_BLOCK {
+            return Ok(Some((Symbol::Literal(symbol as u8), bits)));
+        }
+        if symbol == END_OF_BLOCK {
+            return Ok(Some((Symbol::EndOfBlock, bits)));
+        }
+        let (base, extra) = length_base(self.variant, symbol)?;
+        if self.count < bits + extra {
+            return Ok(None);
+        }
+        let length = base + self.peek(bits, extra);
+        bits += extra;
+        let Some((symbol, len)) = self.lookup(d_decoder, bits)? else {
+            return Ok(None);
+        };
+        bits += len;
+        let (base, extra) = distance_base(self.variant, symbol)?;
+        if self.count < bits + extra {
+            return Ok(None);
+        }
+        let distance = base + self.peek(bits, extra);
+        bits += extra;
+        if distance as u64 > self.total_out {
+            return Err(InflateError::DistanceTooFarBack);
+        }
+        Ok(Some((Symbol::Match { length, distance }, bits)))
+    }
+
+    fn emit(&mut self, byte: u8) -> u8 {
+        self.window[self.window_pos] = byte;
+        self.window_pos = (self.window_pos + 1) % self.window.len();
+        self.total_out += 1;
+        byte
+    }
+}
+
+struct Input<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encoder::Encoder, inflate::inflate, limits::Limit, test_util::sample};
+
+    fn decompress_all(data: &[u8], in_chunk: usize, out_chunk: usize, variant: Variant) -> Vec<u8> {
+        let mut decompressor = Decompressor::with_variant(variant);
+        let mut output = vec![];
+        let mut buf = vec![0; out_chunk];
+        let mut pos = 0;
+        loop {
+            let end = data.len().min(pos + in_chunk);
+            let status = decompressor.decompress(&data[pos..end], &mut buf).unwrap();
+            pos += status.consumed;
+            output.extend_from_slice(&buf[..status.produced]);
+            match status.state {
+                State::Done => return output,
+                State::NeedsInput => assert!(pos < data.len()),
+                State::NeedsOutput => {}
+            }
+        }
+    }
+
+    #[test]
+    fn trivial_test() {
+        let mut decompressor = Decompressor::new();
+        let status = decompressor.decompress(&[3, 0, 1, 2], &mut []).unwrap();
+        assert_eq!(State::Done, status.state);
+        assert_eq!(2, status.consumed);
+        assert!(decompressor.is_done());
+    }
+
+    #[test]
+    fn test_fragmented_input() {
+        let input = sample(60_000);
+        let data = Encoder::new().deflate(&input);
+        for (in_chunk, out_chunk) in [(1, 1 << 16), (3, 5), (1 << 20, 1), (1000, 777)] {
+            assert_eq!(
+                input,
+                decompress_all(&data, in_chunk, out_chunk, Variant::Deflate)
+            );
+        }
+    }
+
+    #[test]
+    fn test_stored_and_deflate64() {
+        let stored = [1, 6, 0, 249, 255, 115, 116, 111, 114, 101, 100];
+        assert_eq!(
+            b"stored".to_vec(),
+            decompress_all(&stored, 1, 2, Variant::Deflate)
+        );
+        let input = sample(60_000).repeat(2);
+        let data = Encoder::new()
+            .with_variant(Variant::Deflate64)
+            .deflate(&input);
+        assert_eq!(input, decompress_all(&data, 5, 1000, Variant::Deflate64));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut decompressor = Decompressor::new();
+        assert!(matches!(
+            decompressor.decompress(&[7], &mut [0; 8]),
+            Err(InflateError::InvalidBlockType)
+        ));
+        let data = Encoder::new().deflate(b"Fa-la-la-la");
+        let mut truncated = Decompressor::new();
+        let status = truncated
+            .decompress(&data[..data.len() - 1], &mut [0; 64])
+            .unwrap();
+        assert_eq!(State::NeedsInput, status.state);
+        assert!(inflate(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let input = vec![b'a'; 3 * (1 << 16)];
+        let data = Encoder::new().deflate(&input);
+        let decompress = |limits| {
+            let mut decompressor = Decompressor::new().with_limits(limits);
+            let mut output = vec![0; 2 * input.len()];
+            let result = decompressor.decompress(&data, &mut output);
+            (result, decompressor.total_out())
+        };
+        let limits = DecodeLimits::new().with_max_output(input.len() as u64);
+        assert!(decompress(limits).0.is_ok());
+        let limits = DecodeLimits::new().with_max_output(1000);
+        let (result, total_out) = decompress(limits);
+        assert!(matches!(
+            result,
+            Err(InflateError::LimitExceeded(Limit::Output))
+        ));
+        assert_eq!(1001, total_out);
+        let limits = DecodeLimits::new().with_max_blocks(1);
+        assert!(matches!(
+            decompress(limits).0,
+            Err(InflateError::LimitExceeded(Limit::Blocks))
+        ));
+        let limits = DecodeLimits::new().with_max_ratio(10);
+        assert!(matches!(
+            decompress(limits).0,
+            Err(InflateError::LimitExceeded(Limit::Ratio))
+        ));
+    }
+}
@@ -1,28 +1,46 @@
-use crate::{bitstream::ostream::OutputStream, HuffmanToken};
+use crate::{
+    bitstream::BitWrite,
+    huffman::dynamic::DynamicTables,
+    matcher::{match_length, BruteForce, MatchFinder},
+    HuffmanToken,
+};
+use alloc::{boxed::Box, vec, vec::Vec};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
 
-pub fn deflate<'a>(input: &'a [u8]) -> impl Iterator<Item = DeflateToken> + '_ {
+pub fn deflate(input: &[u8]) -> impl Iterator<Item = DeflateToken> + '_ {
+    deflate_with(input, BruteForce)
+}
+
+pub fn deflate_with<'a, M: MatchFinder + 'a>(
+    input: &'a [u8],
+    finder: M,
+) -> impl Iterator<Item = DeflateToken> + 'a {
     use DeflateToken::*;
-    let encoder = Deflator::new(input);
+    let encoder = Deflator::with_finder(input, finder);
     [Bhead(BFINAL_YES), Btype(BTYPE_FIXED)]
         .into_iter()
         .chain(encoder)
-        .chain([EndOfBlock].into_iter())
+        .chain([EndOfBlock])
 }
 
-const BFINAL_YES: u16 = 1;
-const BTYPE_FIXED: u16 = 1;
+pub const BFINAL_NO: u16 = 0;
+pub const BFINAL_YES: u16 = 1;
+pub const BTYPE_STORED: u16 = 0;
+pub const BTYPE_FIXED: u16 = 1;
+pub const BTYPE_DYNAMIC: u16 = 2;
 pub const END_OF_BLOCK: usize = 256;
-const MIN_SEQUENCE: usize = 3;
-const MAX_SEQUENCE: usize = 258;
-const MAX_DISTANCE: usize = 32768;
+pub const MIN_SEQUENCE: usize = 3;
+pub const MAX_SEQUENCE: usize = 258;
+pub const MAX_DISTANCE: usize = 32768;
+pub const MAX_SEQUENCE64: usize = 65538;
+pub const MAX_DISTANCE64: usize = 65536;
+const FILTERED_SEQUENCE: usize = 6;
 
 lazy_static! {
-    pub static ref CONVERT_LENGTH: HashMap<usize, (usize, usize, usize)> = {
-        let mut map = HashMap::new();
-        for len in 3..259 {
-            let entry = match len {
+    pub static ref CONVERT_LENGTH: Vec<(usize, usize, usize)> = {
+        let mut table = vec![(0, 0, 0); MAX_SEQUENCE + 1];
+        for (len, entry) in table.iter_mut().enumerate().skip(MIN_SEQUENCE) {
+            *entry = match len {
                 len if len < 11 => (len + 254, 0, 0),
                 len if len < 19 => ((len - 11) / 2 + 265, 1, (len + 1) % 2),
                 len if len < 35 => ((len - 19) / 4 + 269, 2, (len + 1) % 4),
@@ -32,19 +50,18 @@ lazy_static! {
                 258 => (285, 0, 0),
                 _ => unreachable!(),
             };
-            map.insert(len, entry);
         }
-        map
+        table
     };
-    pub static ref CONVERT_DISTANCE: HashMap<usize, (usize, usize, usize)> = {
-        let mut map = HashMap::new();
-        for distance in 1..=MAX_DISTANCE {
-            let entry = match distance {
+    pub static ref CONVERT_DISTANCE: Vec<(usize, usize, usize)> = {
+        let mut table = vec![(0, 0, 0); MAX_DISTANCE64 + 1];
+        for (distance, entry) in table.iter_mut().enumerate().skip(1) {
+            *entry = match distance {
                 d if d < 5 => (d - 1, 0, 0),
                 d if d < 9 => ((d - 5) / 2 + 4, 1, (d - 1) % 2),
                 d if d < 17 => ((d - 9) / 4 + 6, 2, (d - 1) % 4),
-                d if d < 25 => ((d - 17) / 8 + 8, 3, (d - 1) % 8),
-                d if d < 65 => ((d - 25) / 16 + 10, 4, (d - 1) % 16),
+                d if d < 33 => ((d - 17) / 8 + 8, 3, (d - 1) % 8),
+                d if d < 65 => ((d - 33) / 16 + 10, 4, (d - 1) % 16),
                 d if d < 129 => ((d - 65) / 32 + 12, 5, (d - 1) % 32),
                 d if d < 257 => ((d - 129) / 64 + 14, 6, (d - 1) % 64),
                 d if d < 513 => ((d - 257) / 128 + 16, 7, (d - 1) % 128),
@@ -53,78 +70,160 @@ lazy_static! {
                 d if d < 4097 => ((d - 2049) / 1024 + 22, 10, (d - 1) % 1024),
                 d if d < 8193 => ((d - 4097) / 2048 + 24, 11, (d - 1) % 2048),
                 d if d < 16385 => ((d - 8193) / 4096 + 26, 12, (d - 1) % 4096),
-                d => ((d - 16385) / 8192 + 28, 13, (d - 1) % 8192),
+                d if d < 32769 => ((d - 16385) / 8192 + 28, 13, (d - 1) % 8192),
+                d => ((d - 32769) / 16384 + 30, 14, (d - 1) % 16384),
             };
-            map.insert(distance, entry);
         }
-        map
+        table
     };
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    Deflate,
+    Deflate64,
+}
+
+impl Variant {
+    pub fn max_sequence(self) -> usize {
+        match self {
+            Variant::Deflate => MAX_SEQUENCE,
+            Variant::Deflate64 => MAX_SEQUENCE64,
+        }
+    }
+
+    pub fn max_distance(self) -> usize {
+        match self {
+            Variant::Deflate => MAX_DISTANCE,
+            Variant::Deflate64 => MAX_DISTANCE64,
+        }
+    }
+
+    pub fn distance_codes(self) -> usize {
+        match self {
+            Variant::Deflate => 30,
+            Variant::Deflate64 => 32,
+        }
+    }
+
+    pub fn convert_length(self, len: usize) -> (usize, usize, usize) {
+        match self {
+            Variant::Deflate64 if len == MAX_SEQUENCE => (284, 5, 31),
+            Variant::Deflate64 if len > MAX_SEQUENCE => (285, 16, len - MIN_SEQUENCE),
+            _ => CONVERT_LENGTH[len],
+        }
+    }
+
+    pub fn convert_distance(self, distance: usize) -> (usize, usize, usize) {
+        CONVERT_DISTANCE[distance]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    #[default]
+    Default,
+    HuffmanOnly,
+    Rle,
+    Filtered,
+    Fixed,
+    Stored,
+}
+
 #[derive(Debug)]
-pub struct Deflator<'a> {
+pub struct Deflator<'a, M: MatchFinder = BruteForce> {
     input: &'a [u8],
     pos: usize,
+    finder: M,
+    strategy: Strategy,
+    variant: Variant,
 }
 
 impl<'a> Deflator<'a> {
-    fn new(input: &'a [u8]) -> Self {
-        Self { input, pos: 0 }
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_finder(input, BruteForce)
+    }
+}
+
+impl<'a, M: MatchFinder> Deflator<'a, M> {
+    pub fn with_finder(input: &'a [u8], finder: M) -> Self {
+        Self::with_dictionary(input, 0, finder)
+    }
+
+    pub fn with_dictionary(input: &'a [u8], dictionary_len: usize, finder: M) -> Self {
+        assert!(dictionary_len <= input.len());
+        Self {
+            input,
+            pos: dictionary_len,
+            finder,
+            strategy: Strategy::Default,
+            variant: Variant::Deflate,
+        }
     }
 
-    fn find_sequence(&self) -> Option<(u16, u16)> {
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    fn find_sequence(&mut self) -> Option<(u32, u32)> {
         if self.input.len() - self.pos < MIN_SEQUENCE {
             return None;
         }
-        let (mut index, mut len) = (0, 0);
-        let start = if self.pos < MAX_DISTANCE {
-            0
-        } else {
-            self.pos - MAX_DISTANCE
-        };
-        for current in (start..self.pos).rev() {
-            let longest = self.lookup(current);
-            if longest > len {
-                len = longest;
-                index = current;
+        let (max_length, max_distance) = (self.variant.max_sequence(), self.variant.max_distance());
+        let (found, min_len) = match self.strategy {
+            Strategy::HuffmanOnly | Strategy::Stored => return None,
+            Strategy::Rle if self.pos == 0 => return None,
+            Strategy::Rle => {
+                let len = match_length(self.input, self.pos - 1, self.pos, max_length);
+                (Some((len, 1)), MIN_SEQUENCE)
             }
-        }
-        if len > 2 {
-            Some((len as _, (self.pos - index) as _))
-        } else {
-            None
+            Strategy::Filtered => (
+                self.finder
+                    .find(self.input, self.pos, max_length, max_distance),
+                FILTERED_SEQUENCE,
+            ),
+            Strategy::Default | Strategy::Fixed => (
+                self.finder
+                    .find(self.input, self.pos, max_length, max_distance),
+                MIN_SEQUENCE,
+            ),
+        };
+        match found {
+            Some((len, distance)) if len >= min_len => Some((len as _, distance as _)),
+            _ => None,
         }
     }
 
-    fn lookup(&self, x: usize) -> usize {
-        let y = self.pos;
-        assert!(x < y);
-        let mut len = 0;
-        let mut offset = 0;
-        loop {
-            let left = x + offset;
-            let right = y + offset;
-            if offset > MAX_SEQUENCE || right >= self.input.len() {
-                break;
-            }
-            if self.input[left] == self.input[right] {
-                len += 1;
-            } else {
-                break;
+    fn advance(&mut self, n: usize) {
+        let uses_finder = !matches!(
+            self.strategy,
+            Strategy::HuffmanOnly | Strategy::Rle | Strategy::Stored
+        );
+        for _ in 0..n {
+            if uses_finder {
+                self.finder.insert(self.input, self.pos);
             }
-            offset += 1;
+            self.pos += 1;
         }
-        len
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DeflateToken {
     Bhead(u16),
     Btype(u16),
+    Tables(Box<DynamicTables>),
+    StoredLength(u16),
     Literal(u16),
     EndOfBlock,
-    Backref { length: u16, distance: u16 },
+    Backref { length: u32, distance: u32 },
 }
 
 impl DeflateToken {
@@ -132,11 +231,17 @@ impl DeflateToken {
         &self,
         ll_code: &[HuffmanToken],
         d_code: &[HuffmanToken],
-        os: &mut OutputStream,
+        os: &mut impl BitWrite,
     ) {
         match self {
             DeflateToken::Bhead(head) => os.write_numerical(1, *head as _),
             DeflateToken::Btype(b_type) => os.write_numerical(2, *b_type as _),
+            DeflateToken::Tables(tables) => tables.write_header(os),
+            DeflateToken::StoredLength(len) => {
+                os.align();
+                os.write_numerical(16, *len as _);
+                os.write_numerical(16, !*len as _);
+            }
             DeflateToken::Literal(literal) => {
                 let huffman_token = &ll_code[*literal as usize];
                 os.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _)
@@ -146,11 +251,11 @@ impl DeflateToken {
                 os.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
             }
             DeflateToken::Backref { length, distance } => {
-                let (token, extra, bits) = CONVERT_LENGTH[&(*length as usize)];
+                let (token, extra, bits) = os.variant().convert_length(*length as _);
                 let huffman_token = &ll_code[token];
                 os.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
                 os.write_numerical(extra, bits);
-                let (token, extra, bits) = CONVERT_DISTANCE[&(*distance as usize)];
+                let (token, extra, bits) = os.variant().convert_distance(*distance as _);
                 let huffman_token = &d_code[token];
                 os.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
                 os.write_numerical(extra, bits);
@@ -159,17 +264,17 @@ impl DeflateToken {
     }
 }
 
-impl<'a> Iterator for Deflator<'a> {
+impl<'a, M: MatchFinder> Iterator for Deflator<'a, M> {
     type Item = DeflateToken;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.pos < self.input.len() {
             let current = self.pos;
             if let Some((length, distance)) = self.find_sequence() {
-                self.pos += length as usize;
+                self.advance(length as _);
                 Some(DeflateToken::Backref { length, distance })
             } else {
-                self.pos += 1;
+                self.advance(1);
                 Some(DeflateToken::Literal(self.input[current] as _))
             }
         } else {
@@ -177,3 +282,141 @@ impl<'a> Iterator for Deflator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversion_tables_as_in_rfc_1951() {
+        assert_eq!((257, 0, 0), CONVERT_LENGTH[3]);
+        assert_eq!((266, 1, 0), CONVERT_LENGTH[13]);
+        assert_eq!((284, 5, 30), CONVERT_LENGTH[257]);
+        assert_eq!((285, 0, 0), CONVERT_LENGTH[258]);
+        assert_eq!((0, 0, 0), CONVERT_DISTANCE[1]);
+        assert_eq!((8, 3, 7), CONVERT_DISTANCE[24]);
+        assert_eq!((9, 3, 0), CONVERT_DISTANCE[25]);
+        assert_eq!((9, 3, 7), CONVERT_DISTANCE[32]);
+        assert_eq!((10, 4, 0), CONVERT_DISTANCE[33]);
+        assert_eq!((11, 4, 15), CONVERT_DISTANCE[64]);
+        assert_eq!((12, 5, 0), CONVERT_DISTANCE[65]);
+        assert_eq!((29, 13, 8191), CONVERT_DISTANCE[32768]);
+    }
+
+    #[test]
+    fn test_deflate64_conversion() {
+        let variant = Variant::Deflate64;
+        assert_eq!((284, 5, 30), variant.convert_length(257));
+        assert_eq!((284, 5, 31), variant.convert_length(258));
+        assert_eq!((285, 16, 256), variant.convert_length(259));
+        assert_eq!((285, 16, 65535), variant.convert_length(MAX_SEQUENCE64));
+        assert_eq!((285, 0, 0), Variant::Deflate.convert_length(258));
+        assert_eq!((30, 14, 0), variant.convert_distance(32769));
+        assert_eq!((31, 14, 16383), variant.convert_distance(MAX_DISTANCE64));
+    }
+
+    #[test]
+    fn test_deflate_tokens() {
+        let tokens = Deflator::new(b"Fa-la-la-la").collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                DeflateToken::Literal(b'F' as _),
+                DeflateToken::Literal(b'a' as _),
+                DeflateToken::Literal(b'-' as _),
+                DeflateToken::Literal(b'l' as _),
+                DeflateToken::Backref {
+                    length: 7,
+                    distance: 3
+                },
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_dictionary_is_not_emitted() {
+        let tokens = Deflator::with_dictionary(b"Fa-la-la-la", 4, BruteForce).collect::<Vec<_>>();
+        assert_eq!(
+            vec![DeflateToken::Backref {
+                length: 7,
+                distance: 3
+            }],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_strategies() {
+        let input = b"abcabcabcabc-xxxxxx-abcab";
+        let tokens = Deflator::new(input)
+            .with_strategy(Strategy::HuffmanOnly)
+            .collect::<Vec<_>>();
+        assert_eq!(input.len(), tokens.len());
+        assert!(tokens
+            .iter()
+            .all(|token| matches!(token, DeflateToken::Literal(_))));
+        let tokens = Deflator::new(input)
+            .with_strategy(Strategy::Rle)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![DeflateToken::Backref {
+                length: 5,
+                distance: 1
+            }],
+            tokens
+                .into_iter()
+                .filter(|token| matches!(token, DeflateToken::Backref { .. }))
+                .collect::<Vec<_>>()
+        );
+        let tokens = Deflator::new(input)
+            .with_strategy(Strategy::Filtered)
+            .collect::<Vec<_>>();
+        assert!(tokens.contains(&DeflateToken::Backref {
+            length: 9,
+            distance: 3
+        }));
+        assert!(tokens.iter().all(|token| match token {
+            DeflateToken::Backref { length, .. } => *length as usize >= FILTERED_SEQUENCE,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn test_longest_sequence_is_limited() {
+        let input = [0; 300];
+        let tokens = Deflator::new(&input).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                DeflateToken::Literal(0),
+                DeflateToken::Backref {
+                    length: 258,
+                    distance: 1
+                },
+                DeflateToken::Backref {
+                    length: 41,
+                    distance: 1
+                },
+            ],
+            tokens
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_distances_25_to_32_round_trip() {
+        for distance in 25..=32 {
+            let mut input = (0..distance as u8).map(|i| b'a' + i).collect::<Vec<_>>();
+            input.extend_from_within(..);
+            let tokens = deflate(&input).collect::<Vec<_>>();
+            assert!(tokens.contains(&DeflateToken::Backref {
+                length: distance as u32,
+                distance: distance as u32
+            }));
+            let mut os = crate::bitstream::ostream::OutputStream::default();
+            for token in &tokens {
+                os.write_token(token);
+            }
+            assert_eq!(input, crate::inflate(&os.finalize()).unwrap());
+        }
+    }
+}
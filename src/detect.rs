@@ -0,0 +1,219 @@
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+use crate::{
+    flate2::read,
+    framing::{Framing, Gzip, Raw, Zlib},
+    gzip, zlib,
+};
+
+pub type AutoDecoder<R> = read::Decoder<R, Detect>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Raw,
+    Zlib,
+    Gzip,
+}
+
+impl Format {
+    fn framing(self) -> Box<dyn Framing + Send + Sync> {
+        match self {
+            Format::Raw => Box::new(Raw),
+            Format::Zlib => Box::new(Zlib::default()),
+            Format::Gzip => Box::new(Gzip::default()),
+        }
+    }
+}
+
+pub fn detect(bytes: &[u8]) -> Option<Format> {
+    match *bytes {
+        [a, b, ..] if [a, b] == gzip::MAGIC => Some(Format::Gzip),
+        [a, b, ..] if zlib::is_header([a, b]) => Some(Format::Zlib),
+        [_, _, ..] => Some(Format::Raw),
+        _ => None,
+    }
+}
+
+pub fn decompress(input: &[u8]) -> io::Result<(Format, Vec<u8>)> {
+    let mut decoder = AutoDecoder::new(input);
+    let mut output = vec![];
+    decoder.read_to_end(&mut output)?;
+    let format = decoder.framing().format().unwrap_or(Format::Raw);
+    Ok((format, output))
+}
+
+pub struct Detect {
+    format: Option<Format>,
+    framing: Box<dyn Framing + Send + Sync>,
+}
+
+impl Default for Detect {
+    fn default() -> Self {
+        Self {
+            format: None,
+            framing: Box::new(Raw),
+        }
+    }
+}
+
+impl fmt::Debug for Detect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Detect")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl Detect {
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+}
+
+impl Framing for Detect {
+    fn header(&self) -> Vec<u8> {
+        self.framing.header()
+    }
+
+    fn parse_header(&mut self, bytes: &[u8]) -> io::Result<Option<usize>> {
+        if self.format.is_none() {
+            let Some(format) = detect(bytes) else {
+                return Ok(None);
+            };
+            self.format = Some(format);
+            self.framing = format.framing();
+        }
+        self.framing.parse_header(bytes)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.framing.update(data);
+    }
+
+    fn trailer(&self, total: u64) -> Vec<u8> {
+        self.framing.trailer(total)
+    }
+
+    fn trailer_len(&self) -> usize {
+        self.framing.trailer_len()
+    }
+
+    fn check_trailer(&self, trailer: &[u8], total: u64) -> io::Result<()> {
+        self.framing.check_trailer(trailer, total)
+    }
+
+    fn checksum(&self) -> u32 {
+        self.framing.checksum()
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn next_member(&mut self) {
+        self.framing.next_member();
+    }
+
+    fn multi_member(&self) -> bool {
+        self.framing.multi_member()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Write};
+
+    use super::*;
+    use crate::flate2::{bufread, write, Compression};
+
+    const TEXT: &[u8] = b"Hickory, dickory, dock.";
+    const RAW: &str = "f3c84ccece2faad451488133f293b3f500";
+    const ZLIB: &str = "7801f3c84ccece2faad451488133f293b3f50064f50836";
+    const GZIP: &str = "1f8b0800000000000203f3c84ccece2faad451488133f293b3f5004cc8dd2a17000000";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(None, detect(&[0x1f]));
+        assert_eq!(Some(Format::Raw), detect(&[3, 0]));
+        assert_eq!((Format::Raw, vec![]), decompress(&[3, 0]).unwrap());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Some(Format::Gzip), detect(&gzip::MAGIC));
+        for header in [[0x78, 0x01], [0x78, 0x9c], [0x78, 0xda], [0x48, 0x89]] {
+            assert_eq!(Some(Format::Zlib), detect(&header));
+        }
+        assert_eq!(Some(Format::Raw), detect(&[0x78, 0x9d]));
+        assert_eq!(Some(Format::Raw), detect(&[0x1f, 0x8c]));
+    }
+
+    #[test]
+    fn test_decompress() {
+        for (format, data) in [
+            (Format::Raw, hex(RAW)),
+            (Format::Zlib, hex(ZLIB)),
+            (Format::Gzip, hex(GZIP)),
+        ] {
+            assert_eq!((format, TEXT.to_vec()), decompress(&data).unwrap());
+            let mut decoder = AutoDecoder::new(data.as_slice());
+            assert_eq!(None, decoder.framing().format());
+            let mut output = vec![];
+            decoder.read_to_end(&mut output).unwrap();
+            assert_eq!(Some(format), decoder.framing().format());
+        }
+        let mut data = hex(ZLIB);
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(decompress(&data).is_err());
+        assert!(decompress(&[0x1f]).is_err());
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut encoder = write::GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(TEXT).unwrap();
+        let data = encoder.finish().unwrap();
+        let reader = BufReader::with_capacity(1, data.as_slice());
+        let mut decoder = bufread::Decoder::<_, Detect>::new(reader);
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(TEXT, output);
+        assert_eq!(Some(Format::Gzip), decoder.framing().format());
+    }
+
+    #[test]
+    fn test_reset_detects_again() {
+        let gzip = hex(GZIP);
+        let members = gzip.repeat(2);
+        let zlib = hex(ZLIB);
+        let mut decoder =
+            bufread::Decoder::<_, Detect>::new(members.as_slice()).with_multi_member(true);
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(TEXT.repeat(2), output);
+        assert_eq!(Some(Format::Gzip), decoder.framing().format());
+        decoder.reset(zlib.as_slice());
+        assert_eq!(None, decoder.framing().format());
+        output.clear();
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(TEXT, output);
+        assert_eq!(Some(Format::Zlib), decoder.framing().format());
+        let mut decoder = AutoDecoder::new(gzip.as_slice());
+        decoder.read_to_end(&mut vec![]).unwrap();
+        decoder.reset(zlib.as_slice());
+        output.clear();
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(TEXT, output);
+    }
+}
@@ -0,0 +1,233 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::{
+    bitstream::ostream::OutputStream,
+    deflate::{
+        DeflateToken, Deflator, Strategy, Variant, BFINAL_NO, BFINAL_YES, BTYPE_DYNAMIC,
+        BTYPE_FIXED, BTYPE_STORED,
+    },
+    huffman::dynamic::DynamicTables,
+    matcher::BinaryTree,
+};
+
+pub const BLOCK_SIZE: usize = 1 << 16;
+
+const LEVEL_DEPTHS: [usize; 10] = [0, 4, 8, 16, 32, 64, 256, 512, 1024, 4096];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Encoder {
+    strategy: Strategy,
+    variant: Variant,
+    depth: Option<usize>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_level(mut self, level: u32) -> Self {
+        match level {
+            0 => self.with_strategy(Strategy::Stored),
+            _ => {
+                if self.strategy == Strategy::Stored {
+                    self.strategy = Strategy::Default;
+                }
+                self.with_depth(LEVEL_DEPTHS[level.min(9) as usize])
+            }
+        }
+    }
+
+    pub fn deflate(&self, input: &[u8]) -> Vec<u8> {
+        let mut os = OutputStream::default().with_variant(self.variant);
+        self.deflate_into(&mut os, input, 0, true);
+        os.finalize()
+    }
+
+    pub fn deflate_into(
+        &self,
+        os: &mut OutputStream,
+        input: &[u8],
+        dictionary_len: usize,
+        last: bool,
+    ) {
+        if self.strategy == Strategy::Stored {
+            for token in &stored_tokens(&input[dictionary_len..], last) {
+                os.write_token(token);
+            }
+            return;
+        }
+        let mut block = vec![];
+        let mut consumed = 0;
+        let mut remain = input.len() - dictionary_len;
+        for token in self.tokens(input, dictionary_len) {
+            let len = match token {
+                DeflateToken::Backref { length, .. } => length as usize,
+                _ => 1,
+            };
+            block.push(token);
+            consumed += len;
+            remain -= len;
+            if consumed >= BLOCK_SIZE && remain > 0 {
+                self.write_block(os, &block, BFINAL_NO);
+                block.clear();
+                consumed = 0;
+            }
+        }
+        let bfinal = if last { BFINAL_YES } else { BFINAL_NO };
+        self.write_block(os, &block, bfinal);
+    }
+
+    pub fn tokens<'a>(&self, input: &'a [u8], dictionary_len: usize) -> Deflator<'a, BinaryTree> {
+        let mut finder = BinaryTree::with_window(self.variant.max_distance());
+        if let Some(depth) = self.depth {
+            finder = finder.with_depth(depth);
+        }
+        Deflator::with_dictionary(input, dictionary_len, finder)
+            .with_strategy(self.strategy)
+            .with_variant(self.variant)
+    }
+
+    pub fn block_header(&self, tokens: &[DeflateToken], bfinal: u16) -> Vec<DeflateToken> {
+        if self.strategy == Strategy::Fixed || tokens.is_empty() {
+            return vec![
+                DeflateToken::Bhead(bfinal),
+                DeflateToken::Btype(BTYPE_FIXED),
+            ];
+        }
+        let tables = DynamicTables::from_tokens(
+            tokens.iter().chain([&DeflateToken::EndOfBlock]),
+            self.variant,
+        );
+        vec![
+            DeflateToken::Bhead(bfinal),
+            DeflateToken::Btype(BTYPE_DYNAMIC),
+            DeflateToken::Tables(Box::new(tables)),
+        ]
+    }
+
+    pub fn write_block(&self, os: &mut OutputStream, tokens: &[DeflateToken], bfinal: u16) {
+        for token in &self.block_header(tokens, bfinal) {
+            os.write_token(token);
+        }
+        for token in tokens {
+            os.write_token(token);
+        }
+        os.write_token(&DeflateToken::EndOfBlock);
+    }
+}
+
+pub fn stored_tokens(data: &[u8], last: bool) -> Vec<DeflateToken> {
+    let mut tokens = vec![];
+    let mut chunks = data.chunks(u16::MAX as usize).peekable();
+    if chunks.peek().is_none() && last {
+        return vec![
+            DeflateToken::Bhead(BFINAL_YES),
+            DeflateToken::Btype(BTYPE_STORED),
+            DeflateToken::StoredLength(0),
+        ];
+    }
+    while let Some(chunk) = chunks.next() {
+        let bfinal = if last && chunks.peek().is_none() {
+            BFINAL_YES
+        } else {
+            BFINAL_NO
+        };
+        tokens.push(DeflateToken::Bhead(bfinal));
+        tokens.push(DeflateToken::Btype(BTYPE_STORED));
+        tokens.push(DeflateToken::StoredLength(chunk.len() as u16));
+        tokens.extend(chunk.iter().map(|&byte| DeflateToken::Literal(byte as u16)));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sample;
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(vec![0b0000_0011, 0], Encoder::new().deflate(b""));
+    }
+
+    #[test]
+    fn test_fixed_strategy() {
+        let input = b"Deflate, Hello!\n";
+        let output = Encoder::new().with_strategy(Strategy::Fixed).deflate(input);
+        assert_eq!(0b011, output[0] & 0b111);
+        let output = Encoder::new().deflate(input);
+        assert_eq!(0b101, output[0] & 0b111);
+    }
+
+    #[test]
+    fn test_long_input_is_split_into_blocks() {
+        let input = (0..3 * BLOCK_SIZE as u32)
+            .map(|i| (i % 251) as u8 ^ (i / 1000) as u8)
+            .collect::<Vec<_>>();
+        let encoder = Encoder::new().with_strategy(Strategy::HuffmanOnly);
+        let mut os = OutputStream::default();
+        let tokens = encoder.tokens(&input, 0).collect::<Vec<_>>();
+        encoder.write_block(&mut os, &tokens, BFINAL_YES);
+        let single_block = os.finalize();
+        let output = encoder.deflate(&input);
+        assert_ne!(single_block, output);
+        assert_eq!(0b100, output[0] & 0b111);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_levels() {
+        let input = sample(50_000);
+        let stored = Encoder::new().with_level(0).deflate(&input);
+        assert_eq!(0b000, stored[0] & 0b111);
+        assert!(stored.len() > input.len());
+        let fast = Encoder::new().with_level(1).deflate(&input);
+        let best = Encoder::new().with_level(9).deflate(&input);
+        assert!(fast.len() < stored.len() && best.len() <= fast.len());
+        assert_eq!(
+            Encoder::new().deflate(&input),
+            Encoder::new().with_level(6).deflate(&input)
+        );
+        for output in [stored, fast, best] {
+            assert_eq!(input, crate::inflate::inflate(&output).unwrap());
+        }
+        assert_eq!(
+            vec![1, 0, 0, 0xff, 0xff],
+            Encoder::new().with_level(0).deflate(b"")
+        );
+    }
+
+    #[test]
+    fn test_level_after_stored_level() {
+        let encoder = Encoder::new().with_level(0).with_level(6);
+        assert_eq!(Strategy::Default, encoder.strategy());
+        let input = b"Fa-la-la-la, la-la-la-la! ".repeat(10);
+        assert!(encoder.deflate(&input).len() < input.len());
+        let encoder = Encoder::new().with_strategy(Strategy::Fixed).with_level(6);
+        assert_eq!(Strategy::Fixed, encoder.strategy());
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    io::{self, BufRead, Read},
+    mem,
+};
+
+use super::{
+    mem::{Deflate, Inflate},
+    Compression, GzHeader,
+};
+use crate::{
+    compressor::{Flush, State},
+    framing::{Framing, Gzip, Raw, Zlib},
+};
+
+pub type DeflateEncoder<R> = Encoder<R, Raw>;
+pub type ZlibEncoder<R> = Encoder<R, Zlib>;
+pub type GzEncoder<R> = Encoder<R, Gzip>;
+pub type DeflateDecoder<R> = Decoder<R, Raw>;
+pub type ZlibDecoder<R> = Decoder<R, Zlib>;
+pub type GzDecoder<R> = Decoder<R, Gzip>;
+
+#[derive(Debug)]
+pub struct Encoder<R, F> {
+    inner: R,
+    deflate: Deflate<F>,
+}
+
+impl<R: BufRead, F: Framing + Default> Encoder<R, F> {
+    pub fn new(inner: R, level: Compression) -> Self {
+        Self {
+            inner,
+            deflate: Deflate::new(F::default(), level),
+        }
+    }
+}
+
+impl<R: BufRead, F: Framing> Encoder<R, F> {
+    pub fn with_framing(mut self, framing: F) -> Self {
+        self.deflate = Deflate::new(framing, self.deflate.level());
+        self
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.deflate.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.deflate.total_out()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn reset(&mut self, inner: R) -> R {
+        self.deflate.reset();
+        mem::replace(&mut self.inner, inner)
+    }
+}
+
+impl<R: BufRead, F: Framing> Read for Encoder<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let input = self.inner.fill_buf()?;
+            let flush = if input.is_empty() {
+                Flush::Finish
+            } else {
+                Flush::None
+            };
+            let status = self.deflate.deflate(input, buf, flush);
+            self.inner.consume(status.consumed);
+            if status.produced > 0 || status.state == State::Done || buf.is_empty() {
+                return Ok(status.produced);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Decoder<R, F> {
+    inner: R,
+    inflate: Inflate<F>,
+    multi_member: bool,
+}
+
+impl<R: BufRead, F: Framing + Default> Decoder<R, F> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            inflate: Inflate::new(F::default()),
+            multi_member: false,
+        }
+    }
+}
+
+impl<R: BufRead, F: Framing> Decoder<R, F> {
+    pub(crate) fn with_multi_member(mut self, multi_member: bool) -> Self {
+        self.multi_member = multi_member;
+        self
+    }
+
+    pub fn framing(&self) -> &F {
+        self.inflate.framing()
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.inflate.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.inflate.total_out()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn reset(&mut self, inner: R) -> R {
+        self.inflate.reset();
+        mem::replace(&mut self.inner, inner)
+    }
+}
+
+impl<R: BufRead> Decoder<R, Gzip> {
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.inflate
+            .has_header()
+            .then(|| self.inflate.framing().gzip_header())
+    }
+}
+
+impl<R: BufRead, F: Framing> Read for Decoder<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let input = self.inner.fill_buf()?;
+            let eof = input.is_empty();
+            let status = self.inflate.inflate(input, buf)?;
+            self.inner.consume(status.consumed);
+            if status.produced > 0 || buf.is_empty() {
+                return Ok(status.produced);
+            }
+            match status.state {
+                State::Done if self.multi_member && !self.inner.fill_buf()?.is_empty() => {
+                    self.inflate.next_member()
+                }
+                State::Done => return Ok(0),
+                State::NeedsInput if eof => return Err(io::ErrorKind::UnexpectedEof.into()),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MultiGzDecoder<R>(GzDecoder<R>);
+
+impl<R: BufRead> MultiGzDecoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self(GzDecoder::new(inner).with_multi_member(true))
+    }
+
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.0.header()
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.0.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.0.total_out()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.0.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.0.get_mut()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.0.into_inner()
+    }
+}
+
+impl<R: BufRead> Read for MultiGzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::{inflate::inflate, test_util::sample};
+
+    fn read_all<R: Read>(mut reader: R) -> Vec<u8> {
+        let mut output = vec![];
+        reader.read_to_end(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn trivial_test() {
+        let raw = read_all(DeflateEncoder::new(&b""[..], Compression::default()));
+        assert_eq!(vec![3, 0], raw);
+        assert!(read_all(DeflateDecoder::new(raw.as_slice())).is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = sample(50_000);
+        let mut encoder = ZlibEncoder::new(
+            BufReader::with_capacity(5, input.as_slice()),
+            Compression::best(),
+        );
+        let zlib = read_all(&mut encoder);
+        assert_eq!(input.len() as u64, encoder.total_in());
+        assert_eq!(zlib.len() as u64, encoder.total_out());
+        assert_eq!(input, inflate(&zlib[2..]).unwrap());
+        let mut decoder = ZlibDecoder::new(BufReader::with_capacity(3, zlib.as_slice()));
+        assert_eq!(input, read_all(&mut decoder));
+        assert_eq!(zlib.len() as u64, decoder.total_in());
+        assert_eq!(input.len() as u64, decoder.total_out());
+    }
+
+    #[test]
+    fn test_gzip_members() {
+        let mut data = read_all(GzEncoder::new(&b"Fa-la-la-la"[..], Compression::fast()));
+        data.extend(read_all(GzEncoder::new(
+            &b", la-la-la-la"[..],
+            Compression::fast(),
+        )));
+        let mut decoder = GzDecoder::new(data.as_slice());
+        assert!(decoder.header().is_none());
+        assert_eq!(b"Fa-la-la-la".to_vec(), read_all(&mut decoder));
+        assert!(decoder.header().is_some());
+        assert_eq!(
+            b"Fa-la-la-la, la-la-la-la".to_vec(),
+            read_all(MultiGzDecoder::new(BufReader::with_capacity(
+                2,
+                data.as_slice()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_corrupt_input() {
+        let mut zlib = read_all(ZlibEncoder::new(
+            &b"Fa-la-la-la"[..],
+            Compression::default(),
+        ));
+        let last = zlib.len() - 1;
+        zlib[last] ^= 1;
+        let mut output = vec![];
+        let err = ZlibDecoder::new(zlib.as_slice())
+            .read_to_end(&mut output)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        let err = DeflateDecoder::new(&zlib[2..6])
+            .read_to_end(&mut output)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    }
+}
@@ -0,0 +1,542 @@
+use std::{error, fmt, io};
+
+use super::Compression;
+use crate::{
+    compressor::{Compressor, Flush, State, Status as Progress},
+    decompressor::Decompressor,
+    framing::{Framing, Raw, Zlib},
+    limits::DecodeLimits,
+};
+
+type BoxedFraming = Box<dyn Framing + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushCompress {
+    None,
+    Partial,
+    Sync,
+    Full,
+    Finish,
+}
+
+impl From<FlushCompress> for Flush {
+    fn from(flush: FlushCompress) -> Self {
+        match flush {
+            FlushCompress::None => Flush::None,
+            FlushCompress::Partial | FlushCompress::Sync | FlushCompress::Full => Flush::Sync,
+            FlushCompress::Finish => Flush::Finish,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushDecompress {
+    None,
+    Sync,
+    Finish,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    BufError,
+    StreamEnd,
+}
+
+impl From<Progress> for Status {
+    fn from(progress: Progress) -> Self {
+        match progress.state {
+            State::Done => Status::StreamEnd,
+            _ if progress.consumed == 0 && progress.produced == 0 => Status::BufError,
+            _ => Status::Ok,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressError(());
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deflate compression error")
+    }
+}
+
+impl error::Error for CompressError {}
+
+impl From<CompressError> for io::Error {
+    fn from(e: CompressError) -> Self {
+        io::Error::other(e)
+    }
+}
+
+#[derive(Debug)]
+pub struct DecompressError(io::Error);
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deflate decompression error: {}", self.0)
+    }
+}
+
+impl error::Error for DecompressError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl From<DecompressError> for io::Error {
+    fn from(e: DecompressError) -> Self {
+        e.0
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Deflate<F> {
+    compressor: Compressor,
+    level: Compression,
+    framing: F,
+    pending: Vec<u8>,
+    pos: usize,
+    started: bool,
+    finished: bool,
+    total_out: u64,
+}
+
+impl<F: Framing> Deflate<F> {
+    pub(crate) fn new(framing: F, level: Compression) -> Self {
+        Self {
+            compressor: Compressor::with_encoder(level.encoder()),
+            level,
+            framing,
+            pending: vec![],
+            pos: 0,
+            started: false,
+            finished: false,
+            total_out: 0,
+        }
+    }
+
+    pub(crate) fn level(&self) -> Compression {
+        self.level
+    }
+
+    pub(crate) fn total_in(&self) -> u64 {
+        self.compressor.total_in()
+    }
+
+    pub(crate) fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.finished && self.pos == self.pending.len()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.compressor = Compressor::with_encoder(self.level.encoder());
+        self.framing.reset();
+        self.pending.clear();
+        self.pos = 0;
+        self.started = false;
+        self.finished = false;
+        self.total_out = 0;
+    }
+
+    pub(crate) fn deflate(&mut self, input: &[u8], output: &mut [u8], flush: Flush) -> Progress {
+        if !self.started {
+            self.pending = self.framing.header();
+            self.started = true;
+        }
+        let (mut consumed, mut produced) = (0, 0);
+        let state = loop {
+            let n = (self.pending.len() - self.pos).min(output.len() - produced);
+            output[produced..produced + n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+            self.pos += n;
+            produced += n;
+            if self.pos < self.pending.len() {
+                break State::NeedsOutput;
+            }
+            if self.finished {
+                break State::Done;
+            }
+            self.pending.clear();
+            self.pos = 0;
+            let status =
+                self.compressor
+                    .compress(&input[consumed..], &mut output[produced..], flush);
+            self.framing
+                .update(&input[consumed..consumed + status.consumed]);
+            consumed += status.consumed;
+            produced += status.produced;
+            if status.state != State::Done {
+                break status.state;
+            }
+            self.pending = self.framing.trailer(self.compressor.total_in());
+            self.finished = true;
+        };
+        self.total_out += produced as u64;
+        Progress {
+            consumed,
+            produced,
+            state,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+#[derive(Debug)]
+pub(crate) struct Inflate<F> {
+    decompressor: Decompressor,
+    framing: F,
+    stage: Stage,
+    buffer: Vec<u8>,
+    limits: DecodeLimits,
+    blocks: u64,
+    total_in: u64,
+    total_out: u64,
+}
+
+impl<F: Framing> Inflate<F> {
+    pub(crate) fn new(framing: F) -> Self {
+        Self {
+            decompressor: Decompressor::new(),
+            framing,
+            stage: Stage::Header,
+            buffer: vec![],
+            limits: DecodeLimits::default(),
+            blocks: 0,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    pub(crate) fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self.decompressor = Decompressor::new().with_limits(limits);
+        self
+    }
+
+    pub(crate) fn framing(&self) -> &F {
+        &self.framing
+    }
+
+    pub(crate) fn has_header(&self) -> bool {
+        self.stage != Stage::Header
+    }
+
+    pub(crate) fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    pub(crate) fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub(crate) fn next_member(&mut self) {
+        self.blocks += self.decompressor.blocks();
+        let limits = self.limits.remaining(self.total_out, self.blocks);
+        self.decompressor = Decompressor::new().with_limits(limits);
+        self.framing.next_member();
+        self.stage = Stage::Header;
+        self.buffer.clear();
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.next_member();
+        self.framing.reset();
+        self.decompressor = Decompressor::new().with_limits(self.limits);
+        self.blocks = 0;
+        self.total_in = 0;
+        self.total_out = 0;
+    }
+
+    pub(crate) fn inflate(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<Progress> {
+        let (mut consumed, mut produced) = (0, 0);
+        let result = self.run(input, output, &mut consumed, &mut produced);
+        self.total_in += consumed as u64;
+        self.total_out += produced as u64;
+        result.map(|state| Progress {
+            consumed,
+            produced,
+            state,
+        })
+    }
+
+    fn run(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        consumed: &mut usize,
+        produced: &mut usize,
+    ) -> io::Result<State> {
+        loop {
+            match self.stage {
+                Stage::Header => {
+                    let seen = self.buffer.len();
+                    self.buffer.extend_from_slice(input);
+                    match self.framing.parse_header(&self.buffer)? {
+                        Some(len) => *consumed = len - seen,
+                        None => {
+                            *consumed = input.len();
+                            return Ok(State::NeedsInput);
+                        }
+                    }
+                    self.buffer.clear();
+                    self.stage = Stage::Body;
+                }
+                Stage::Body => {
+                    let status = self
+                        .decompressor
+                        .decompress(&input[*consumed..], &mut output[*produced..])?;
+                    self.framing
+                        .update(&output[*produced..*produced + status.produced]);
+                    *consumed += status.consumed;
+                    *produced += status.produced;
+                    if status.state != State::Done {
+                        return Ok(status.state);
+                    }
+                    self.stage = Stage::Trailer;
+                }
+                Stage::Trailer => {
+                    let n = (self.framing.trailer_len() - self.buffer.len())
+                        .min(input.len() - *consumed);
+                    self.buffer
+                        .extend_from_slice(&input[*consumed..*consumed + n]);
+                    *consumed += n;
+                    if self.buffer.len() < self.framing.trailer_len() {
+                        return Ok(State::NeedsInput);
+                    }
+                    self.framing
+                        .check_trailer(&self.buffer, self.decompressor.total_out())?;
+                    self.buffer.clear();
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return Ok(State::Done),
+            }
+        }
+    }
+}
+
+pub struct Compress {
+    inner: Deflate<BoxedFraming>,
+}
+
+impl Compress {
+    pub fn new(level: Compression, zlib_header: bool) -> Self {
+        Self {
+            inner: Deflate::new(framing(zlib_header), level),
+        }
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.inner.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.inner.total_out()
+    }
+
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    pub fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        flush: FlushCompress,
+    ) -> Result<Status, CompressError> {
+        Ok(self.inner.deflate(input, output, flush.into()).into())
+    }
+
+    pub fn compress_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        flush: FlushCompress,
+    ) -> Result<Status, CompressError> {
+        let (len, before) = (output.len(), self.total_out());
+        output.resize(output.capacity(), 0);
+        let result = self.compress(input, &mut output[len..], flush);
+        output.truncate(len + (self.total_out() - before) as usize);
+        result
+    }
+}
+
+impl fmt::Debug for Compress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compress")
+            .field("total_in", &self.total_in())
+            .field("total_out", &self.total_out())
+            .finish()
+    }
+}
+
+pub struct Decompress {
+    inner: Inflate<BoxedFraming>,
+}
+
+impl Decompress {
+    pub fn new(zlib_header: bool) -> Self {
+        Self {
+            inner: Inflate::new(framing(zlib_header)),
+        }
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.inner.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.inner.total_out()
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.inner = self.inner.with_limits(limits);
+        self
+    }
+
+    pub fn reset(&mut self, zlib_header: bool) {
+        self.inner = Inflate::new(framing(zlib_header)).with_limits(self.inner.limits);
+    }
+
+    pub fn decompress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        _flush: FlushDecompress,
+    ) -> Result<Status, DecompressError> {
+        self.inner
+            .inflate(input, output)
+            .map(Status::from)
+            .map_err(DecompressError)
+    }
+
+    pub fn decompress_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        flush: FlushDecompress,
+    ) -> Result<Status, DecompressError> {
+        let (len, before) = (output.len(), self.total_out());
+        output.resize(output.capacity(), 0);
+        let result = self.decompress(input, &mut output[len..], flush);
+        output.truncate(len + (self.total_out() - before) as usize);
+        result
+    }
+}
+
+impl fmt::Debug for Decompress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decompress")
+            .field("total_in", &self.total_in())
+            .field("total_out", &self.total_out())
+            .finish()
+    }
+}
+
+fn framing(zlib_header: bool) -> BoxedFraming {
+    if zlib_header {
+        Box::new(Zlib::default())
+    } else {
+        Box::new(Raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_test() {
+        let mut compress = Compress::new(Compression::default(), false);
+        let mut output = [0; 8];
+        let status = compress.compress(&[], &mut output, FlushCompress::Finish);
+        assert_eq!(Ok(Status::StreamEnd), status);
+        assert_eq!([3, 0], output[..2]);
+        assert_eq!(2, compress.total_out());
+    }
+
+    #[test]
+    fn test_streaming() {
+        let input = b"Fa-la-la-la, la-la-la-la! ".repeat(100);
+        let mut compress = Compress::new(Compression::best(), true);
+        let mut compressed = Vec::with_capacity(16);
+        for chunk in input.chunks(100) {
+            compress
+                .compress_vec(chunk, &mut compressed, FlushCompress::None)
+                .unwrap();
+            compressed.reserve(16);
+        }
+        assert_eq!(input.len() as u64, compress.total_in());
+        while compress
+            .compress_vec(&[], &mut compressed, FlushCompress::Finish)
+            .unwrap()
+            != Status::StreamEnd
+        {
+            compressed.reserve(16);
+        }
+        assert_eq!(compressed.len() as u64, compress.total_out());
+        let mut decompress = Decompress::new(true);
+        let mut output = Vec::with_capacity(input.len());
+        for chunk in compressed.chunks(3) {
+            let before = decompress.total_in();
+            decompress
+                .decompress_vec(chunk, &mut output, FlushDecompress::None)
+                .unwrap();
+            assert_eq!(chunk.len() as u64, decompress.total_in() - before);
+        }
+        assert_eq!(input, output);
+        let status = decompress.decompress(&[], &mut [0; 4], FlushDecompress::Finish);
+        assert_eq!(Status::StreamEnd, status.unwrap());
+    }
+
+    #[test]
+    fn test_status_and_errors() {
+        let mut decompress = Decompress::new(false);
+        let status = decompress.decompress(&[], &mut [0; 4], FlushDecompress::Finish);
+        assert_eq!(Status::BufError, status.unwrap());
+        assert!(decompress
+            .decompress(&[0xff], &mut [0; 4], FlushDecompress::None)
+            .is_err());
+        decompress.reset(true);
+        let err = decompress
+            .decompress(&[0x78, 0x9d], &mut [0; 4], FlushDecompress::None)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, io::Error::from(err).kind());
+        let mut compress = Compress::new(Compression::fast(), true);
+        compress
+            .compress(b"x", &mut [0; 64], FlushCompress::Finish)
+            .unwrap();
+        compress.reset();
+        assert_eq!((0, 0), (compress.total_in(), compress.total_out()));
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut compress = Compress::new(Compression::default(), true);
+        let mut data = vec![0; 1024];
+        compress
+            .compress(&[b'a'; 10_000], &mut data, FlushCompress::Finish)
+            .unwrap();
+        data.truncate(compress.total_out() as usize);
+        let limits = DecodeLimits::new().with_max_output(1000);
+        let mut decompress = Decompress::new(true).with_limits(limits);
+        for _ in 0..2 {
+            let mut output = vec![0; 20_000];
+            let err = decompress
+                .decompress(&data, &mut output, FlushDecompress::Finish)
+                .unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, io::Error::from(err).kind());
+            decompress.reset(true);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::{BufRead, Read, Write};
+
+use crate::{encoder::Encoder, framing::Gzip};
+
+pub mod bufread;
+mod mem;
+pub mod read;
+pub mod write;
+
+pub use crate::gzip::GzipHeader as GzHeader;
+pub use mem::{
+    Compress, CompressError, Decompress, DecompressError, FlushCompress, FlushDecompress, Status,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Compression(u32);
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self(6)
+    }
+}
+
+impl Compression {
+    pub const fn new(level: u32) -> Self {
+        Self(level)
+    }
+
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    pub const fn fast() -> Self {
+        Self(1)
+    }
+
+    pub const fn best() -> Self {
+        Self(9)
+    }
+
+    pub fn level(&self) -> u32 {
+        self.0
+    }
+
+    pub(crate) fn encoder(self) -> Encoder {
+        Encoder::new().with_level(self.0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GzBuilder {
+    header: GzHeader,
+}
+
+impl GzBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filename<S: Into<Vec<u8>>>(mut self, filename: S) -> Self {
+        self.header.name = Some(filename.into());
+        self
+    }
+
+    pub fn comment<S: Into<Vec<u8>>>(mut self, comment: S) -> Self {
+        self.header.comment = Some(comment.into());
+        self
+    }
+
+    pub fn extra<T: Into<Vec<u8>>>(mut self, extra: T) -> Self {
+        self.header.extra = Some(extra.into());
+        self
+    }
+
+    pub fn mtime(mut self, mtime: u32) -> Self {
+        self.header.mtime = mtime;
+        self
+    }
+
+    pub fn operating_system(mut self, os: u8) -> Self {
+        self.header.os = os;
+        self
+    }
+
+    pub fn write<W: Write>(self, inner: W, level: Compression) -> write::GzEncoder<W> {
+        write::GzEncoder::new(inner, level).with_framing(self.framing(level))
+    }
+
+    pub fn read<R: Read>(self, inner: R, level: Compression) -> read::GzEncoder<R> {
+        read::GzEncoder::new(inner, level).with_framing(self.framing(level))
+    }
+
+    pub fn buf_read<R: BufRead>(self, inner: R, level: Compression) -> bufread::GzEncoder<R> {
+        bufread::GzEncoder::new(inner, level).with_framing(self.framing(level))
+    }
+
+    fn framing(mut self, level: Compression) -> Gzip {
+        self.header.xfl = match level.level() {
+            9.. => 2,
+            0..=1 => 4,
+            _ => 0,
+        };
+        Gzip::new(self.header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+    use crate::test_util::sample;
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(6, Compression::default().level());
+        assert!(Compression::fast() < Compression::best());
+        assert_eq!(Compression::new(0), Compression::none());
+    }
+
+    #[test]
+    fn test_gz_builder() {
+        let mut encoder = GzBuilder::new()
+            .filename("carol.txt")
+            .comment("first verse")
+            .mtime(1_700_000_000)
+            .write(vec![], Compression::best());
+        encoder.write_all(b"Deck the halls").unwrap();
+        let data = encoder.finish().unwrap();
+        let mut decoder = read::GzDecoder::new(data.as_slice());
+        let mut output = String::new();
+        decoder.read_to_string(&mut output).unwrap();
+        assert_eq!("Deck the halls", output);
+        let header = decoder.header().unwrap();
+        assert_eq!(Some(&b"carol.txt"[..]), header.filename());
+        assert_eq!(Some(&b"first verse"[..]), header.comment());
+        assert_eq!(1_700_000_000, header.mtime);
+        assert_eq!(2, header.xfl);
+    }
+
+    #[test]
+    fn test_levels() {
+        let input = sample(20_000);
+        let compress = |level| {
+            let mut encoder = write::GzEncoder::new(vec![], level);
+            encoder.write_all(&input).unwrap();
+            encoder.finish().unwrap()
+        };
+        let none = compress(Compression::none());
+        let fast = compress(Compression::fast());
+        let best = compress(Compression::best());
+        assert!(none.len() > input.len());
+        assert!(none.len() > fast.len() && fast.len() > best.len());
+        for data in [none, fast, best] {
+            let mut output = vec![];
+            read::GzDecoder::new(data.as_slice())
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(input, output);
+        }
+        let mut compress = Compress::new(Compression::none(), false);
+        let mut output = vec![0; 64];
+        compress
+            .compress(b"stored", &mut output, FlushCompress::Finish)
+            .unwrap();
+        assert_eq!(b"\x01\x06\x00\xf9\xffstored", &output[..11]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::io::{self, BufReader, Read};
+
+use super::{bufread, Compression, GzHeader};
+use crate::framing::{Framing, Gzip, Raw, Zlib};
+
+pub type DeflateEncoder<R> = Encoder<R, Raw>;
+pub type ZlibEncoder<R> = Encoder<R, Zlib>;
+pub type GzEncoder<R> = Encoder<R, Gzip>;
+pub type DeflateDecoder<R> = Decoder<R, Raw>;
+pub type ZlibDecoder<R> = Decoder<R, Zlib>;
+pub type GzDecoder<R> = Decoder<R, Gzip>;
+
+#[derive(Debug)]
+pub struct Encoder<R, F>(bufread::Encoder<BufReader<R>, F>);
+
+impl<R: Read, F: Framing + Default> Encoder<R, F> {
+    pub fn new(inner: R, level: Compression) -> Self {
+        Self(bufread::Encoder::new(BufReader::new(inner), level))
+    }
+}
+
+impl<R: Read, F: Framing> Encoder<R, F> {
+    pub fn with_framing(self, framing: F) -> Self {
+        Self(self.0.with_framing(framing))
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.0.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.0.total_out()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.0.get_ref().get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.0.get_mut().get_mut()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.0.into_inner().into_inner()
+    }
+
+    pub fn reset(&mut self, inner: R) -> R {
+        self.0.reset(BufReader::new(inner)).into_inner()
+    }
+}
+
+impl<R: Read, F: Framing> Read for Encoder<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[derive(Debug)]
+pub struct Decoder<R, F>(bufread::Decoder<BufReader<R>, F>);
+
+impl<R: Read, F: Framing + Default> Decoder<R, F> {
+    pub fn new(inner: R) -> Self {
+        Self(bufread::Decoder::new(BufReader::new(inner)))
+    }
+}
+
+impl<R: Read, F: Framing> Decoder<R, F> {
+    pub fn framing(&self) -> &F {
+        self.0.framing()
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.0.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.0.total_out()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.0.get_ref().get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.0.get_mut().get_mut()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.0.into_inner().into_inner()
+    }
+
+    pub fn reset(&mut self, inner: R) -> R {
+        self.0.reset(BufReader::new(inner)).into_inner()
+    }
+}
+
+impl<R: Read> Decoder<R, Gzip> {
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.0.header()
+    }
+}
+
+impl<R: Read, F: Framing> Read for Decoder<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[derive(Debug)]
+pub struct MultiGzDecoder<R>(bufread::MultiGzDecoder<BufReader<R>>);
+
+impl<R: Read> MultiGzDecoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self(bufread::MultiGzDecoder::new(BufReader::new(inner)))
+    }
+
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.0.header()
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.0.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.0.total_out()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.0.get_ref().get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.0.get_mut().get_mut()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.0.into_inner().into_inner()
+    }
+}
+
+impl<R: Read> Read for MultiGzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZLIB_STREAM: [u8; 23] = [
+        0x78, 0xda, 0x73, 0x4b, 0xd4, 0xcd, 0x81, 0x22, 0x1d, 0x85, 0x1c, 0x04, 0x47, 0x51, 0xc1,
+        0x8d, 0xaa, 0x32, 0x00, 0x80, 0x42, 0x1f, 0x75,
+    ];
+
+    #[test]
+    fn trivial_test() {
+        let mut gzip = vec![];
+        GzEncoder::new(&b""[..], Compression::default())
+            .read_to_end(&mut gzip)
+            .unwrap();
+        let mut output = vec![];
+        GzDecoder::new(gzip.as_slice())
+            .read_to_end(&mut output)
+            .unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_foreign_stream() {
+        let mut decoder = ZlibDecoder::new(&ZLIB_STREAM[..]);
+        let mut output = String::new();
+        decoder.read_to_string(&mut output).unwrap();
+        assert_eq!("Fa-la-la-la, la-la-la-la! ".repeat(4), output);
+        assert_eq!(ZLIB_STREAM.len() as u64, decoder.total_in());
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut encoder = DeflateEncoder::new(&b"Fa-la-la-la"[..], Compression::default());
+        let mut first = vec![];
+        encoder.read_to_end(&mut first).unwrap();
+        let previous = encoder.reset(&b", la-la-la-la!"[..]);
+        assert!(previous.is_empty());
+        let mut second = vec![];
+        encoder.read_to_end(&mut second).unwrap();
+        assert_eq!(14, encoder.total_in());
+        let mut decoder = DeflateDecoder::new(first.as_slice());
+        let mut output = String::new();
+        decoder.read_to_string(&mut output).unwrap();
+        decoder.reset(second.as_slice());
+        decoder.read_to_string(&mut output).unwrap();
+        assert_eq!("Fa-la-la-la, la-la-la-la!", output);
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+use super::{
+    mem::{Deflate, Inflate},
+    Compression, GzHeader,
+};
+use crate::{
+    compressor::{Flush, State, Status},
+    framing::{Framing, Gzip, Raw, Zlib},
+};
+
+pub const CHUNK_SIZE: usize = 1 << 15;
+
+pub type DeflateEncoder<W> = Encoder<W, Raw>;
+pub type ZlibEncoder<W> = Encoder<W, Zlib>;
+pub type GzEncoder<W> = Encoder<W, Gzip>;
+pub type DeflateDecoder<W> = Decoder<W, Raw>;
+pub type ZlibDecoder<W> = Decoder<W, Zlib>;
+pub type GzDecoder<W> = Decoder<W, Gzip>;
+
+#[derive(Debug)]
+pub struct Encoder<W: Write, F: Framing> {
+    inner: Option<W>,
+    deflate: Deflate<F>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write, F: Framing + Default> Encoder<W, F> {
+    pub fn new(inner: W, level: Compression) -> Self {
+        Self {
+            inner: Some(inner),
+            deflate: Deflate::new(F::default(), level),
+            buffer: vec![],
+        }
+    }
+}
+
+impl<W: Write, F: Framing> Encoder<W, F> {
+    pub fn with_framing(mut self, framing: F) -> Self {
+        self.deflate = Deflate::new(framing, self.deflate.level());
+        self
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.deflate.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.deflate.total_out()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        loop {
+            self.dump()?;
+            if self.deflate.is_done() {
+                return Ok(());
+            }
+            self.step(&[], Flush::Finish);
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    pub fn reset(&mut self, inner: W) -> io::Result<W> {
+        self.try_finish()?;
+        self.deflate.reset();
+        Ok(mem::replace(self.get_mut(), inner))
+    }
+
+    fn step(&mut self, input: &[u8], flush: Flush) -> Status {
+        self.buffer.resize(CHUNK_SIZE, 0);
+        let status = self.deflate.deflate(input, &mut self.buffer, flush);
+        self.buffer.truncate(status.produced);
+        status
+    }
+
+    fn dump(&mut self) -> io::Result<()> {
+        let inner = self.inner.as_mut().unwrap();
+        inner.write_all(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write, F: Framing> Write for Encoder<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.deflate.is_done() {
+            return Err(io::Error::other("write after finish"));
+        }
+        loop {
+            self.dump()?;
+            let status = self.step(buf, Flush::None);
+            if status.consumed > 0 || buf.is_empty() {
+                return Ok(status.consumed);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        loop {
+            self.dump()?;
+            let status = self.step(&[], Flush::Sync);
+            if status.produced == 0 && status.state != State::NeedsOutput {
+                break;
+            }
+        }
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write, F: Framing> Drop for Encoder<W, F> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Decoder<W: Write, F: Framing> {
+    inner: Option<W>,
+    inflate: Inflate<F>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write, F: Framing + Default> Decoder<W, F> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            inflate: Inflate::new(F::default()),
+            buffer: vec![],
+        }
+    }
+}
+
+impl<W: Write, F: Framing> Decoder<W, F> {
+    pub fn total_in(&self) -> u64 {
+        self.inflate.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.inflate.total_out()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        while self.step(&[])?.produced > 0 {}
+        self.get_mut().flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    pub fn reset(&mut self, inner: W) -> io::Result<W> {
+        self.try_finish()?;
+        self.inflate.reset();
+        Ok(mem::replace(self.get_mut(), inner))
+    }
+
+    fn step(&mut self, input: &[u8]) -> io::Result<Status> {
+        self.buffer.resize(CHUNK_SIZE, 0);
+        let status = self.inflate.inflate(input, &mut self.buffer)?;
+        let inner = self.inner.as_mut().unwrap();
+        inner.write_all(&self.buffer[..status.produced])?;
+        Ok(status)
+    }
+}
+
+impl<W: Write> Decoder<W, Gzip> {
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.inflate
+            .has_header()
+            .then(|| self.inflate.framing().gzip_header())
+    }
+}
+
+impl<W: Write, F: Framing> Write for Decoder<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            let status = self.step(buf)?;
+            if status.consumed > 0 || status.produced == 0 {
+                return Ok(status.consumed);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while self.step(&[])?.produced > 0 {}
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write, F: Framing> Drop for Decoder<W, F> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inflate::inflate, test_util::sample};
+
+    #[test]
+    fn trivial_test() {
+        let output = DeflateEncoder::new(vec![], Compression::default())
+            .finish()
+            .unwrap();
+        assert_eq!(vec![3, 0], output);
+        let output = DeflateDecoder::new(vec![]).finish().unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = sample(50_000);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        for chunk in input.chunks(1000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let gzip = encoder.finish().unwrap();
+        let mut decoder = GzDecoder::new(vec![]);
+        for chunk in gzip.chunks(7) {
+            decoder.write_all(chunk).unwrap();
+        }
+        assert!(decoder.header().is_some());
+        assert_eq!(gzip.len() as u64, decoder.total_in());
+        assert_eq!(input, decoder.finish().unwrap());
+    }
+
+    #[test]
+    fn test_flush_and_drop() {
+        let mut output = vec![];
+        {
+            let mut encoder = ZlibEncoder::new(&mut output, Compression::default());
+            encoder.write_all(b"Fa-la-la-la").unwrap();
+            encoder.flush().unwrap();
+            let flushed = encoder.get_ref().to_vec();
+            assert_eq!([0, 0, 0xff, 0xff], flushed[flushed.len() - 4..]);
+            encoder.write_all(b", la-la-la-la!").unwrap();
+        }
+        assert_eq!(
+            b"Fa-la-la-la, la-la-la-la!".to_vec(),
+            inflate(&output[2..]).unwrap()
+        );
+        let mut decoder = ZlibDecoder::new(vec![]);
+        decoder.write_all(&output).unwrap();
+        assert!(decoder.write(b"trailing").is_ok_and(|n| n == 0));
+        assert_eq!(
+            b"Fa-la-la-la, la-la-la-la!".to_vec(),
+            decoder.finish().unwrap()
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::io;
+
+use crc32fast::Hasher;
+
+use crate::{
+    gzip::{self, GzipHeader},
+    zlib::{self, Adler32},
+};
+
+pub trait Framing {
+    fn header(&self) -> Vec<u8>;
+    fn parse_header(&mut self, bytes: &[u8]) -> io::Result<Option<usize>>;
+    fn update(&mut self, data: &[u8]);
+    fn trailer(&self, total: u64) -> Vec<u8>;
+    fn trailer_len(&self) -> usize;
+    fn check_trailer(&self, trailer: &[u8], total: u64) -> io::Result<()>;
+    fn checksum(&self) -> u32;
+    fn reset(&mut self);
+
+    fn next_member(&mut self) {
+        self.reset();
+    }
+
+    fn multi_member(&self) -> bool {
+        false
+    }
+}
+
+impl<F: Framing + ?Sized> Framing for Box<F> {
+    fn header(&self) -> Vec<u8> {
+        (**self).header()
+    }
+
+    fn parse_header(&mut self, bytes: &[u8]) -> io::Result<Option<usize>> {
+        (**self).parse_header(bytes)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        (**self).update(data)
+    }
+
+    fn trailer(&self, total: u64) -> Vec<u8> {
+        (**self).trailer(total)
+    }
+
+    fn trailer_len(&self) -> usize {
+        (**self).trailer_len()
+    }
+
+    fn check_trailer(&self, trailer: &[u8], total: u64) -> io::Result<()> {
+        (**self).check_trailer(trailer, total)
+    }
+
+    fn checksum(&self) -> u32 {
+        (**self).checksum()
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+
+    fn next_member(&mut self) {
+        (**self).next_member()
+    }
+
+    fn multi_member(&self) -> bool {
+        (**self).multi_member()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Raw;
+
+impl Framing for Raw {
+    fn header(&self) -> Vec<u8> {
+        vec![]
+    }
+
+    fn parse_header(&mut self, _bytes: &[u8]) -> io::Result<Option<usize>> {
+        Ok(Some(0))
+    }
+
+    fn update(&mut self, _data: &[u8]) {}
+
+    fn trailer(&self, _total: u64) -> Vec<u8> {
+        vec![]
+    }
+
+    fn trailer_len(&self) -> usize {
+        0
+    }
+
+    fn check_trailer(&self, _trailer: &[u8], _total: u64) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn checksum(&self) -> u32 {
+        0
+    }
+
+    fn reset(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Zlib {
+    adler: Adler32,
+}
+
+impl Framing for Zlib {
+    fn header(&self) -> Vec<u8> {
+        zlib::HEADER.to_vec()
+    }
+
+    fn parse_header(&mut self, bytes: &[u8]) -> io::Result<Option<usize>> {
+        match bytes {
+            [cmf, flg, ..] => zlib::check_header([*cmf, *flg]).map(|_| Some(2)),
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.adler.update(data);
+    }
+
+    fn trailer(&self, _total: u64) -> Vec<u8> {
+        zlib::trailer(self.checksum()).to_vec()
+    }
+
+    fn trailer_len(&self) -> usize {
+        4
+    }
+
+    fn check_trailer(&self, trailer: &[u8], total: u64) -> io::Result<()> {
+        if trailer != self.trailer(total) {
+            return Err(invalid("zlib checksum mismatch"));
+        }
+        Ok(())
+    }
+
+    fn checksum(&self) -> u32 {
+        self.adler.finalize()
+    }
+
+    fn reset(&mut self) {
+        self.adler = Adler32::new();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Gzip {
+    header: GzipHeader,
+    hasher: Hasher,
+    parsed: bool,
+}
+
+impl Gzip {
+    pub fn new(header: GzipHeader) -> Self {
+        Self {
+            header,
+            hasher: Hasher::new(),
+            parsed: false,
+        }
+    }
+
+    pub fn gzip_header(&self) -> &GzipHeader {
+        &self.header
+    }
+}
+
+impl Framing for Gzip {
+    fn header(&self) -> Vec<u8> {
+        self.header.to_bytes()
+    }
+
+    fn parse_header(&mut self, bytes: &[u8]) -> io::Result<Option<usize>> {
+        let mut reader = bytes;
+        match GzipHeader::read_from(&mut reader) {
+            Ok(header) => {
+                if !self.parsed {
+                    self.header = header;
+                    self.parsed = true;
+                }
+                Ok(Some(bytes.len() - reader.len()))
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn trailer(&self, total: u64) -> Vec<u8> {
+        gzip::trailer(self.checksum(), total as u32).to_vec()
+    }
+
+    fn trailer_len(&self) -> usize {
+        8
+    }
+
+    fn check_trailer(&self, trailer: &[u8], total: u64) -> io::Result<()> {
+        if trailer != self.trailer(total) {
+            return Err(invalid("gzip trailer mismatch"));
+        }
+        Ok(())
+    }
+
+    fn checksum(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+
+    fn reset(&mut self) {
+        self.hasher = Hasher::new();
+        self.parsed = false;
+    }
+
+    fn next_member(&mut self) {
+        self.hasher = Hasher::new();
+    }
+
+    fn multi_member(&self) -> bool {
+        true
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_test() {
+        let mut raw = Raw;
+        assert!(raw.header().is_empty());
+        assert_eq!(Some(0), raw.parse_header(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let mut zlib = Zlib::default();
+        assert_eq!(None, zlib.parse_header(&[0x78]).unwrap());
+        assert_eq!(Some(2), zlib.parse_header(&[0x78, 0x9c, 0]).unwrap());
+        let header = GzipHeader {
+            name: Some(b"a.txt".to_vec()),
+            ..Default::default()
+        };
+        let bytes = header.to_bytes();
+        let mut gzip = Gzip::default();
+        assert_eq!(None, gzip.parse_header(&bytes[..12]).unwrap());
+        assert_eq!(Some(bytes.len()), gzip.parse_header(&bytes).unwrap());
+        assert_eq!(&header, gzip.gzip_header());
+        assert!(Gzip::default()
+            .parse_header(&[0x1f, 0x8c, 8, 0, 0, 0, 0, 0, 0, 255])
+            .is_err());
+    }
+
+    #[test]
+    fn test_trailers() {
+        let mut gzip = Gzip::default();
+        gzip.update(b"Fa-la-la-la");
+        let trailer = gzip.trailer(11);
+        assert_eq!(trailer, gzip::trailer(crc32fast::hash(b"Fa-la-la-la"), 11));
+        assert!(gzip.check_trailer(&trailer, 11).is_ok());
+        assert!(gzip.check_trailer(&trailer, 12).is_err());
+        let mut zlib = Zlib::default();
+        zlib.update(b"Wikipedia");
+        assert_eq!(vec![0x11, 0xe6, 0x03, 0x98], zlib.trailer(9));
+        zlib.reset();
+        assert!(zlib.check_trailer(&[0, 0, 0, 1], 0).is_ok());
+    }
+}
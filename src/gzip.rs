@@ -0,0 +1,484 @@
+use std::io::{self, Read, Write};
+
+use crc32fast::Hasher;
+
+use crate::{
+    inflate::{InflateError, Inflater},
+    limits::DecodeLimits,
+};
+
+pub const MAGIC: [u8; 2] = [0x1f, 0x8b];
+pub const CM_DEFLATE: u8 = 8;
+pub const OS_UNKNOWN: u8 = 255;
+
+const FTEXT: u8 = 1;
+const FHCRC: u8 = 2;
+const FEXTRA: u8 = 4;
+const FNAME: u8 = 8;
+const FCOMMENT: u8 = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GzipHeader {
+    pub text: bool,
+    pub mtime: u32,
+    pub xfl: u8,
+    pub os: u8,
+    pub extra: Option<Vec<u8>>,
+    pub name: Option<Vec<u8>>,
+    pub comment: Option<Vec<u8>>,
+    pub hcrc: bool,
+}
+
+impl Default for GzipHeader {
+    fn default() -> Self {
+        Self {
+            text: false,
+            mtime: 0,
+            xfl: 0,
+            os: OS_UNKNOWN,
+            extra: None,
+            name: None,
+            comment: None,
+            hcrc: false,
+        }
+    }
+}
+
+impl GzipHeader {
+    pub fn filename(&self) -> Option<&[u8]> {
+        self.name.as_deref()
+    }
+
+    pub fn comment(&self) -> Option<&[u8]> {
+        self.comment.as_deref()
+    }
+
+    pub fn extra(&self) -> Option<&[u8]> {
+        self.extra.as_deref()
+    }
+
+    pub fn mtime(&self) -> u32 {
+        self.mtime
+    }
+
+    pub fn operating_system(&self) -> u8 {
+        self.os
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        for (set, flag) in [
+            (self.text, FTEXT),
+            (self.hcrc, FHCRC),
+            (self.extra.is_some(), FEXTRA),
+            (self.name.is_some(), FNAME),
+            (self.comment.is_some(), FCOMMENT),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        let mut header = MAGIC.to_vec();
+        header.extend([CM_DEFLATE, flags]);
+        header.extend(self.mtime.to_le_bytes());
+        header.extend([self.xfl, self.os]);
+        if let Some(ref extra) = self.extra {
+            header.extend((extra.len() as u16).to_le_bytes());
+            header.extend(extra);
+        }
+        for field in [&self.name, &self.comment].into_iter().flatten() {
+            header.extend(field);
+            header.push(0);
+        }
+        if self.hcrc {
+            let crc = crc32fast::hash(&header) as u16;
+            header.extend(crc.to_le_bytes());
+        }
+        header
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::read_with_limits(reader, &DecodeLimits::default())
+    }
+
+    pub fn read_with_limits<R: Read>(reader: &mut R, limits: &DecodeLimits) -> io::Result<Self> {
+        let check_size = |size| {
+            limits
+                .check_header_size(size)
+                .map_err(|limit| io::Error::from(InflateError::from(limit)))
+        };
+        let mut header = vec![0; 10];
+        reader.read_exact(&mut header)?;
+        if header[..2] != MAGIC || header[2] != CM_DEFLATE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid gzip header",
+            ));
+        }
+        let flags = header[3];
+        let extra = if flags & FEXTRA != 0 {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            check_size(header.len() + len.len() + u16::from_le_bytes(len) as usize)?;
+            let mut extra = vec![0; u16::from_le_bytes(len) as usize];
+            reader.read_exact(&mut extra)?;
+            header.extend(len);
+            header.extend(&extra);
+            Some(extra)
+        } else {
+            None
+        };
+        let mut fields = [None, None];
+        for (field, flag) in fields.iter_mut().zip([FNAME, FCOMMENT]) {
+            if flags & flag != 0 {
+                let mut value = vec![];
+                let mut byte = [0];
+                loop {
+                    reader.read_exact(&mut byte)?;
+                    header.push(byte[0]);
+                    check_size(header.len())?;
+                    if byte[0] == 0 {
+                        break;
+                    }
+                    value.push(byte[0]);
+                }
+                *field = Some(value);
+            }
+        }
+        let hcrc = flags & FHCRC != 0;
+        if hcrc {
+            let mut crc = [0; 2];
+            reader.read_exact(&mut crc)?;
+            if u16::from_le_bytes(crc) != crc32fast::hash(&header) as u16 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "gzip header checksum mismatch",
+                ));
+            }
+        }
+        let [name, comment] = fields;
+        Ok(Self {
+            text: flags & FTEXT != 0,
+            mtime: u32::from_le_bytes(header[4..8].try_into().unwrap()),
+            xfl: header[8],
+            os: header[9],
+            extra,
+            name,
+            comment,
+            hcrc,
+        })
+    }
+}
+
+pub fn trailer(crc: u32, isize: u32) -> [u8; 8] {
+    let mut trailer = [0; 8];
+    trailer[..4].copy_from_slice(&crc.to_le_bytes());
+    trailer[4..].copy_from_slice(&isize.to_le_bytes());
+    trailer
+}
+
+#[derive(Debug)]
+pub struct GzipDecoder<R: Read> {
+    reader: Option<Prefixed<R>>,
+    inflater: Option<Inflater<Prefixed<R>>>,
+    hasher: Hasher,
+    header: Option<GzipHeader>,
+    limits: DecodeLimits,
+    members: u64,
+    blocks: u64,
+    total_out: u64,
+    multi_member: bool,
+    done: bool,
+}
+
+impl<R: Read> GzipDecoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            reader: Some(Prefixed {
+                prefix: vec![],
+                pos: 0,
+                inner,
+            }),
+            inflater: None,
+            hasher: Hasher::new(),
+            header: None,
+            limits: DecodeLimits::default(),
+            members: 0,
+            blocks: 0,
+            total_out: 0,
+            multi_member: true,
+            done: false,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_multi_member(mut self, multi_member: bool) -> Self {
+        self.multi_member = multi_member;
+        self
+    }
+
+    pub fn header(&self) -> Option<&GzipHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn members(&self) -> u64 {
+        self.members
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    fn start_member(&mut self) -> io::Result<bool> {
+        let reader = self.reader.as_mut().unwrap();
+        if self.done || (self.members > 0 && (!self.multi_member || reader.at_eof()?)) {
+            self.done = true;
+            return Ok(false);
+        }
+        self.members += 1;
+        self.limits
+            .check_members(self.members)
+            .map_err(InflateError::from)?;
+        let header = GzipHeader::read_with_limits(reader, &self.limits)?;
+        self.header.get_or_insert(header);
+        let limits = self.limits.remaining(self.total_out, self.blocks);
+        self.inflater = Some(Inflater::new(self.reader.take().unwrap()).with_limits(limits));
+        self.hasher = Hasher::new();
+        Ok(true)
+    }
+
+    fn finish_member(&mut self, inflater: Inflater<Prefixed<R>>) -> io::Result<()> {
+        let member_out = inflater.total_out();
+        self.blocks += inflater.blocks();
+        let (mut reader, unread) = inflater.into_inner();
+        reader.unread(unread);
+        let mut trailer = [0; 8];
+        let result = reader.read_exact(&mut trailer);
+        self.reader = Some(reader);
+        result.inspect_err(|_| self.done = true)?;
+        let crc = self.hasher.clone().finalize();
+        if trailer[..4] != crc.to_le_bytes() || trailer[4..] != (member_out as u32).to_le_bytes() {
+            self.done = true;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "gzip trailer mismatch",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for GzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if let Some(inflater) = self.inflater.as_mut() {
+                let n = inflater.read(buf)?;
+                if n > 0 {
+                    self.hasher.update(&buf[..n]);
+                    self.total_out += n as u64;
+                    return Ok(n);
+                }
+                let inflater = self.inflater.take().unwrap();
+                self.finish_member(inflater)?;
+            }
+            if !self.start_member()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Prefixed<R: Read> {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: R,
+}
+
+impl<R: Read> Prefixed<R> {
+    fn unread(&mut self, mut bytes: Vec<u8>) {
+        bytes.extend_from_slice(&self.prefix[self.pos..]);
+        self.prefix = bytes;
+        self.pos = 0;
+    }
+
+    fn at_eof(&mut self) -> io::Result<bool> {
+        if self.pos < self.prefix.len() {
+            return Ok(false);
+        }
+        let mut byte = [0];
+        let n = loop {
+            match self.inner.read(&mut byte) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        self.unread(byte[..n].to_vec());
+        Ok(n == 0)
+    }
+}
+
+impl<R: Read> Read for Prefixed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.prefix.len() {
+            let n = buf.len().min(self.prefix.len() - self.pos);
+            buf[..n].copy_from_slice(&self.prefix[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limit;
+
+    #[test]
+    fn trivial_test() {
+        let header = GzipHeader::default().to_bytes();
+        assert_eq!(vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255], header);
+    }
+
+    #[test]
+    fn test_optional_fields() {
+        let header = GzipHeader {
+            mtime: 0x01020304,
+            extra: Some(b"BC\x02\x00\x10\x00".to_vec()),
+            name: Some(b"a.txt".to_vec()),
+            hcrc: true,
+            ..Default::default()
+        }
+        .to_bytes();
+        assert_eq!(
+            &[0x1f, 0x8b, 8, 14, 4, 3, 2, 1, 0, 255, 6, 0],
+            &header[..12]
+        );
+        assert_eq!(b"BC\x02\x00\x10\x00a.txt\0", &header[12..24]);
+        let crc = crc32fast::hash(&header[..24]) as u16;
+        assert_eq!(crc.to_le_bytes(), header[24..]);
+    }
+
+    #[test]
+    fn test_read_header() {
+        let header = GzipHeader {
+            text: true,
+            mtime: 1234,
+            extra: Some(b"BC\x02\x00\x10\x00".to_vec()),
+            name: Some(b"a.txt".to_vec()),
+            comment: Some(b"carol".to_vec()),
+            hcrc: true,
+            ..Default::default()
+        };
+        let bytes = header.to_bytes();
+        assert_eq!(
+            header,
+            GzipHeader::read_from(&mut bytes.as_slice()).unwrap()
+        );
+        let mut corrupted = bytes.clone();
+        corrupted[4] ^= 1;
+        assert!(GzipHeader::read_from(&mut corrupted.as_slice()).is_err());
+        assert!(GzipHeader::read_from(&mut &bytes[..15]).is_err());
+    }
+
+    fn member(data: &[u8]) -> Vec<u8> {
+        let mut member = GzipHeader::default().to_bytes();
+        member.extend(crate::encoder::Encoder::new().deflate(data));
+        member.extend(trailer(crc32fast::hash(data), data.len() as u32));
+        member
+    }
+
+    #[test]
+    fn test_decode_members() {
+        let mut data = member(b"Fa-la-la-la");
+        data.extend(member(b", la-la-la-la!"));
+        let mut decoder = GzipDecoder::new(data.as_slice());
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(b"Fa-la-la-la, la-la-la-la!".to_vec(), output);
+        assert_eq!(2, decoder.members());
+        let mut decoder = GzipDecoder::new(data.as_slice()).with_multi_member(false);
+        output.clear();
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!(b"Fa-la-la-la".to_vec(), output);
+        data[15] ^= 1;
+        assert!(GzipDecoder::new(data.as_slice())
+            .read_to_end(&mut output)
+            .is_err());
+    }
+
+    #[test]
+    fn test_read_after_truncated_trailer() {
+        let data = member(b"Fa-la-la-la");
+        let mut decoder = GzipDecoder::new(&data[..data.len() - 3]);
+        let mut output = vec![];
+        let err = decoder.read_to_end(&mut output).unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+        assert_eq!(0, decoder.read(&mut [0; 16]).unwrap());
+        let mut data = member(b"Fa-la-la-la");
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        let mut decoder = GzipDecoder::new(data.as_slice());
+        assert!(decoder.read_to_end(&mut output).is_err());
+        assert_eq!(0, decoder.read(&mut [0; 16]).unwrap());
+    }
+
+    fn limit_of(result: io::Result<usize>) -> Option<Limit> {
+        match result.unwrap_err().into_inner()?.downcast::<InflateError>() {
+            Ok(e) => match *e {
+                InflateError::LimitExceeded(limit) => Some(limit),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let bomb = member(&vec![0; 1 << 20]);
+        let decode = |limits| {
+            GzipDecoder::new(bomb.as_slice())
+                .with_limits(limits)
+                .read_to_end(&mut vec![])
+        };
+        assert!(decode(DecodeLimits::new()).is_ok());
+        let limits = DecodeLimits::new().with_max_output(1000);
+        assert_eq!(Some(Limit::Output), limit_of(decode(limits)));
+        let limits = DecodeLimits::new().with_max_ratio(100);
+        assert_eq!(Some(Limit::Ratio), limit_of(decode(limits)));
+        let members = member(b"a").repeat(10);
+        let limits = DecodeLimits::new().with_max_members(9);
+        let result = GzipDecoder::new(members.as_slice())
+            .with_limits(limits)
+            .read_to_end(&mut vec![]);
+        assert_eq!(Some(Limit::Members), limit_of(result));
+        let mut named = GzipHeader {
+            name: Some(vec![b'a'; 1000]),
+            ..Default::default()
+        }
+        .to_bytes();
+        named.extend(&member(b"")[10..]);
+        let limits = DecodeLimits::new().with_max_header_size(100);
+        let result = GzipDecoder::new(named.as_slice())
+            .with_limits(limits)
+            .read_to_end(&mut vec![]);
+        assert_eq!(Some(Limit::HeaderSize), limit_of(result));
+    }
+
+    #[test]
+    fn test_trailer() {
+        assert_eq!([4, 3, 2, 1, 8, 7, 6, 5], trailer(0x01020304, 0x05060708));
+    }
+}
@@ -0,0 +1,102 @@
+use alloc::{vec, vec::Vec};
+
+pub fn build_lengths(freqs: &[usize], max_bits: usize) -> Vec<u8> {
+    let mut lengths = vec![0; freqs.len()];
+    let mut leaves = freqs
+        .iter()
+        .enumerate()
+        .filter(|(_, freq)| **freq > 0)
+        .map(|(symbol, freq)| (*freq, vec![symbol]))
+        .collect::<Vec<_>>();
+    leaves.sort();
+    match leaves.len() {
+        0 => return lengths,
+        1 => {
+            lengths[leaves[0].1[0]] = 1;
+            return lengths;
+        }
+        n => assert!(
+            n <= 1 << max_bits,
+            "{n} symbols do not fit in {max_bits} bits"
+        ),
+    }
+    let mut current = leaves.clone();
+    for _ in 1..max_bits {
+        let packages = current
+            .chunks_exact(2)
+            .map(|pair| {
+                let mut symbols = pair[0].1.clone();
+                symbols.extend_from_slice(&pair[1].1);
+                (pair[0].0 + pair[1].0, symbols)
+            })
+            .collect::<Vec<_>>();
+        current = merge(&leaves, packages);
+    }
+    for (_, symbols) in current.iter().take(2 * leaves.len() - 2) {
+        for symbol in symbols {
+            lengths[*symbol] += 1;
+        }
+    }
+    lengths
+}
+
+fn merge(
+    leaves: &[(usize, Vec<usize>)],
+    packages: Vec<(usize, Vec<usize>)>,
+) -> Vec<(usize, Vec<usize>)> {
+    let mut merged = Vec::with_capacity(leaves.len() + packages.len());
+    let mut leaves = leaves.iter().cloned().peekable();
+    let mut packages = packages.into_iter().peekable();
+    loop {
+        let take_leaf = match (leaves.peek(), packages.peek()) {
+            (Some(leaf), Some(package)) => leaf.0 <= package.0,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_leaf {
+            merged.extend(leaves.next());
+        } else {
+            merged.extend(packages.next());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kraft_sum(lengths: &[u8], max_bits: usize) -> usize {
+        lengths
+            .iter()
+            .filter(|len| **len > 0)
+            .map(|len| 1 << (max_bits - *len as usize))
+            .sum()
+    }
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(vec![0, 0, 0], build_lengths(&[0, 0, 0], 15));
+        assert_eq!(vec![0, 1, 0], build_lengths(&[0, 7, 0], 15));
+        assert_eq!(vec![1, 1], build_lengths(&[1, 100], 15));
+    }
+
+    #[test]
+    fn test_unlimited_lengths() {
+        let lengths = build_lengths(&[10, 1, 1, 2, 4], 15);
+        assert_eq!(vec![1, 4, 4, 3, 2], lengths);
+    }
+
+    #[test]
+    fn test_limited_lengths() {
+        let freqs = (0..20).map(|i| 1 << i).collect::<Vec<usize>>();
+        let lengths = build_lengths(&freqs, 7);
+        assert!(lengths.iter().all(|len| (1..=7).contains(len)));
+        assert_eq!(1 << 7, kraft_sum(&lengths, 7));
+        assert_eq!(1, lengths[19]);
+        let lengths = build_lengths(&freqs, 20);
+        assert_eq!(19, lengths[0]);
+        assert_eq!(1 << 20, kraft_sum(&lengths, 20));
+    }
+}
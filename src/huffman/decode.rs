@@ -0,0 +1,127 @@
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use super::generate::MAX_BITS;
+#[cfg(feature = "std")]
+use crate::bitstream::bitreader::BitReader;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Symbol(u16, usize),
+    Incomplete,
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+pub struct HuffmanDecoder {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl HuffmanDecoder {
+    pub fn new(bit_lengths: &[u8]) -> Option<Self> {
+        let mut counts = [0; MAX_BITS + 1];
+        for len in bit_lengths {
+            counts[*len as usize] += 1;
+        }
+        let mut left: isize = 1;
+        for count in &counts[1..] {
+            left = 2 * left - *count as isize;
+            if left < 0 {
+                return None;
+            }
+        }
+        let mut offsets = [0; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; bit_lengths.len()];
+        for (symbol, len) in bit_lengths.iter().enumerate() {
+            if *len > 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Some(Self { counts, symbols })
+    }
+
+    #[cfg(feature = "std")]
+    pub fn decode<R: Read>(&self, reader: &mut BitReader<R>) -> io::Result<Option<u16>> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for count in &self.counts[1..] {
+            code |= reader.read_bit()? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(Some(self.symbols[(index + code - first) as usize]));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Ok(None)
+    }
+
+    pub fn lookup(&self, bits: u128, count: usize) -> Lookup {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for (len, count_of_len) in self.counts.iter().enumerate().skip(1) {
+            if len > count {
+                return Lookup::Incomplete;
+            }
+            code |= (bits >> (len - 1)) as i32 & 1;
+            let count_of_len = *count_of_len as i32;
+            if code - first < count_of_len {
+                return Lookup::Symbol(self.symbols[(index + code - first) as usize], len);
+            }
+            index += count_of_len;
+            first = (first + count_of_len) << 1;
+            code <<= 1;
+        }
+        Lookup::Invalid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bitstream::ostream::OutputStream, huffman::generate::generate_code};
+
+    #[test]
+    fn trivial_test() {
+        assert!(HuffmanDecoder::new(&[1, 1, 1]).is_none());
+        let decoder = HuffmanDecoder::new(&[0, 0]).unwrap();
+        let mut reader = BitReader::new(&[0xff, 0xff][..]);
+        assert_eq!(None, decoder.decode(&mut reader).unwrap());
+    }
+
+    #[test]
+    fn test_decode_canonical_code() {
+        let bit_lengths = [3, 3, 3, 3, 3, 2, 4, 4];
+        let code = generate_code(&bit_lengths);
+        let message = [7, 5, 0, 6, 3, 5];
+        let mut os = OutputStream::default();
+        for symbol in message {
+            let token = &code[symbol];
+            os.write_code(token.len as _, token.token.unwrap() as _);
+        }
+        let data = os.finalize();
+        let decoder = HuffmanDecoder::new(&bit_lengths).unwrap();
+        let mut reader = BitReader::new(data.as_slice());
+        for symbol in message {
+            assert_eq!(Some(symbol as u16), decoder.decode(&mut reader).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let decoder = HuffmanDecoder::new(&[3, 3, 3, 3, 3, 2, 4, 4]).unwrap();
+        let code = generate_code(&[3, 3, 3, 3, 3, 2, 4, 4]);
+        let mut os = OutputStream::default();
+        os.write_code(code[7].len as _, code[7].token.unwrap() as _);
+        let bits = os.finalize()[0] as u128;
+        assert_eq!(Lookup::Incomplete, decoder.lookup(bits, 3));
+        assert_eq!(Lookup::Symbol(7, 4), decoder.lookup(bits, 4));
+        let empty = HuffmanDecoder::new(&[0, 0]).unwrap();
+        assert_eq!(Lookup::Invalid, empty.lookup(0, 64));
+    }
+}
@@ -0,0 +1,197 @@
+use alloc::{vec, vec::Vec};
+
+use super::{build::build_lengths, generate::generate_code, generate::MAX_BITS, HuffmanToken};
+use crate::{
+    bitstream::BitWrite,
+    deflate::{DeflateToken, Variant, END_OF_BLOCK},
+};
+
+pub const LL_CODES: usize = 286;
+pub const D_CODES: usize = 30;
+const CL_CODES: usize = 19;
+const CL_MAX_BITS: usize = 7;
+pub const CL_ORDER: [usize; CL_CODES] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+const REPEAT_PREVIOUS: u8 = 16;
+const REPEAT_ZERO_SHORT: u8 = 17;
+const REPEAT_ZERO_LONG: u8 = 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicTables {
+    pub ll_lengths: Vec<u8>,
+    pub d_lengths: Vec<u8>,
+}
+
+impl DynamicTables {
+    pub fn from_frequencies(ll_freqs: &[usize], d_freqs: &[usize]) -> Self {
+        Self {
+            ll_lengths: build_lengths(&at_least_two(ll_freqs), MAX_BITS),
+            d_lengths: build_lengths(&at_least_two(d_freqs), MAX_BITS),
+        }
+    }
+
+    pub fn from_tokens<'a>(
+        tokens: impl IntoIterator<Item = &'a DeflateToken>,
+        variant: Variant,
+    ) -> Self {
+        let (ll_freqs, d_freqs) = frequencies(tokens, variant);
+        Self::from_frequencies(&ll_freqs, &d_freqs)
+    }
+
+    pub fn codes(&self) -> (Vec<HuffmanToken>, Vec<HuffmanToken>) {
+        (
+            generate_code(&self.ll_lengths),
+            generate_code(&self.d_lengths),
+        )
+    }
+
+    pub fn write_header(&self, os: &mut impl BitWrite) {
+        let hlit = used_len(&self.ll_lengths).max(257);
+        let hdist = used_len(&self.d_lengths).max(1);
+        let mut lengths = self.ll_lengths[..hlit].to_vec();
+        lengths.extend_from_slice(&self.d_lengths[..hdist]);
+        let encoded = encode_lengths(&lengths);
+        let mut cl_freqs = [0; CL_CODES];
+        for (symbol, _, _) in &encoded {
+            cl_freqs[*symbol as usize] += 1;
+        }
+        let cl_lengths = build_lengths(&cl_freqs, CL_MAX_BITS);
+        let cl_code = generate_code(&cl_lengths);
+        let hclen = CL_ORDER
+            .iter()
+            .rposition(|symbol| cl_lengths[*symbol] > 0)
+            .map_or(0, |index| index + 1)
+            .max(4);
+        os.write_numerical(5, hlit - 257);
+        os.write_numerical(5, hdist - 1);
+        os.write_numerical(4, hclen - 4);
+        for symbol in &CL_ORDER[..hclen] {
+            os.write_numerical(3, cl_lengths[*symbol] as _);
+        }
+        for (symbol, extra, bits) in encoded {
+            let huffman_token = &cl_code[symbol as usize];
+            os.write_code(huffman_token.len as _, huffman_token.token.unwrap() as _);
+            os.write_numerical(extra as _, bits as _);
+        }
+    }
+}
+
+pub fn frequencies<'a>(
+    tokens: impl IntoIterator<Item = &'a DeflateToken>,
+    variant: Variant,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut ll_freqs = vec![0; LL_CODES];
+    let mut d_freqs = vec![0; variant.distance_codes()];
+    for token in tokens {
+        match token {
+            DeflateToken::Literal(literal) => ll_freqs[*literal as usize] += 1,
+            DeflateToken::EndOfBlock => ll_freqs[END_OF_BLOCK] += 1,
+            DeflateToken::Backref { length, distance } => {
+                ll_freqs[variant.convert_length(*length as _).0] += 1;
+                d_freqs[variant.convert_distance(*distance as _).0] += 1;
+            }
+            _ => {}
+        }
+    }
+    (ll_freqs, d_freqs)
+}
+
+fn at_least_two(freqs: &[usize]) -> Vec<usize> {
+    let mut freqs = freqs.to_vec();
+    for symbol in 0..freqs.len() {
+        if freqs.iter().filter(|freq| **freq > 0).count() >= 2 {
+            break;
+        }
+        if freqs[symbol] == 0 {
+            freqs[symbol] = 1;
+        }
+    }
+    freqs
+}
+
+fn used_len(lengths: &[u8]) -> usize {
+    lengths
+        .iter()
+        .rposition(|len| *len > 0)
+        .map_or(0, |index| index + 1)
+}
+
+fn encode_lengths(lengths: &[u8]) -> Vec<(u8, u8, u8)> {
+    let mut encoded = vec![];
+    let mut i = 0;
+    while i < lengths.len() {
+        let len = lengths[i];
+        let mut run = lengths[i..].iter().take_while(|x| **x == len).count();
+        i += run;
+        if len == 0 {
+            while run >= 11 {
+                let repeat = run.min(138);
+                encoded.push((REPEAT_ZERO_LONG, 7, (repeat - 11) as u8));
+                run -= repeat;
+            }
+            if run >= 3 {
+                encoded.push((REPEAT_ZERO_SHORT, 3, (run - 3) as u8));
+                run = 0;
+            }
+        } else {
+            encoded.push((len, 0, 0));
+            run -= 1;
+            while run >= 3 {
+                let repeat = run.min(6);
+                encoded.push((REPEAT_PREVIOUS, 2, (repeat - 3) as u8));
+                run -= repeat;
+            }
+        }
+        encoded.extend((0..run).map(|_| (len, 0, 0)));
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_lengths() {
+        assert_eq!(vec![(1, 0, 0), (1, 0, 0)], encode_lengths(&[1, 1]));
+        assert_eq!(
+            vec![(8, 0, 0), (16, 2, 3), (8, 0, 0), (8, 0, 0)],
+            encode_lengths(&[8; 9])
+        );
+        assert_eq!(
+            vec![(18, 7, 127), (0, 0, 0), (0, 0, 0)],
+            encode_lengths(&[0; 140])
+        );
+        assert_eq!(vec![(17, 3, 2)], encode_lengths(&[0; 5]));
+        assert_eq!(
+            vec![(5, 0, 0), (0, 0, 0), (0, 0, 0), (7, 0, 0)],
+            encode_lengths(&[5, 0, 0, 7])
+        );
+    }
+
+    #[test]
+    fn test_frequencies() {
+        let tokens = [
+            DeflateToken::Literal(b'a' as _),
+            DeflateToken::Backref {
+                length: 10,
+                distance: 1,
+            },
+            DeflateToken::EndOfBlock,
+        ];
+        let (ll_freqs, d_freqs) = frequencies(&tokens, Variant::Deflate);
+        assert_eq!(1, ll_freqs[b'a' as usize]);
+        assert_eq!(1, ll_freqs[264]);
+        assert_eq!(1, ll_freqs[END_OF_BLOCK]);
+        assert_eq!(3, ll_freqs.iter().sum::<usize>());
+        assert_eq!(1, d_freqs[0]);
+    }
+
+    #[test]
+    fn test_tables_have_two_codes_at_least() {
+        let tables = DynamicTables::from_tokens(&[DeflateToken::EndOfBlock], Variant::Deflate);
+        assert_eq!(2, tables.ll_lengths.iter().filter(|len| **len > 0).count());
+        assert_eq!(2, tables.d_lengths.iter().filter(|len| **len > 0).count());
+    }
+}
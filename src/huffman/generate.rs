@@ -1,3 +1,5 @@
+use alloc::{vec, vec::Vec};
+
 use super::HuffmanToken;
 
 pub const MAX_BITS: usize = 15;
@@ -13,6 +15,8 @@ pub fn generate_code(bit_lengths: &[u8]) -> Vec<HuffmanToken> {
             let bl_index = (*len - 1) as usize;
             code.push(HuffmanToken::new(*len, next_code[bl_index]));
             next_code[bl_index] += 1;
+        } else {
+            code.push(HuffmanToken::new(0, 0));
         }
     }
     code
@@ -38,6 +42,13 @@ pub fn generate_fixed_code() -> (Vec<HuffmanToken>, Vec<HuffmanToken>) {
     )
 }
 
+pub fn generate_stored_code() -> (Vec<HuffmanToken>, Vec<HuffmanToken>) {
+    let code = (0..=u8::MAX)
+        .map(|byte| HuffmanToken::new(8, byte.reverse_bits() as u16))
+        .collect();
+    (code, vec![])
+}
+
 fn calc_first_codes(bl_count: &[usize; MAX_BITS], next_code: &mut [u16]) {
     assert!(next_code.len() > bl_count.len());
     next_code.fill(0);
@@ -79,6 +90,16 @@ mod tests {
         assert_eq!(15, code[7].token.unwrap());
     }
 
+    #[test]
+    fn unused_symbols_keep_their_place() {
+        let code = generate_code(&[2, 0, 1, 2]);
+        assert_eq!(4, code.len());
+        assert_eq!(Some(2), code[0].token);
+        assert_eq!(None, code[1].token);
+        assert_eq!(Some(0), code[2].token);
+        assert_eq!(Some(3), code[3].token);
+    }
+
     #[test]
     fn test_calc_first_codes() {
         let mut code = [0; MAX_BITS + 1];
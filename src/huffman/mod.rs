@@ -1,3 +1,6 @@
+pub mod build;
+pub mod decode;
+pub mod dynamic;
 pub mod generate;
 
 #[derive(Debug, Clone, Copy)]
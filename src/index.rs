@@ -0,0 +1,331 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crc32fast::Hasher;
+
+use crate::{gzip::GzipHeader, inflate::Inflater};
+
+pub const DEFAULT_SPAN: u64 = 1 << 20;
+pub const MAGIC: [u8; 8] = *b"DFLTIDX1";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Raw,
+    Gzip,
+}
+
+impl Format {
+    fn to_u8(self) -> u8 {
+        match self {
+            Format::Raw => 0,
+            Format::Gzip => 1,
+        }
+    }
+
+    fn from_u8(format: u8) -> Option<Self> {
+        match format {
+            0 => Some(Format::Raw),
+            1 => Some(Format::Gzip),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub output: u64,
+    pub input_bits: u64,
+    pub window: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    pub format: Format,
+    pub total_out: u64,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+impl Index {
+    pub fn build<R: Read>(reader: R, format: Format, span: u64) -> io::Result<Self> {
+        let mut reader = reader;
+        let start = match format {
+            Format::Raw => 0,
+            Format::Gzip => GzipHeader::read_from(&mut reader)?.to_bytes().len() as u64,
+        };
+        let mut checkpoints = vec![Checkpoint {
+            output: 0,
+            input_bits: start * 8,
+            window: vec![],
+        }];
+        let mut inflater = Inflater::new(reader);
+        let mut hasher = Hasher::new();
+        let mut buf = vec![0; 1 << 15];
+        while !inflater.is_done() {
+            let n = inflater.inflate_block(&mut buf)?;
+            hasher.update(&buf[..n]);
+            let last = checkpoints.last().unwrap().output;
+            if inflater.is_at_block_boundary() && inflater.total_out() - last >= span.max(1) {
+                checkpoints.push(Checkpoint {
+                    output: inflater.total_out(),
+                    input_bits: start * 8 + inflater.bits_read(),
+                    window: inflater.window(),
+                });
+            }
+        }
+        let total_out = inflater.total_out();
+        if format == Format::Gzip {
+            let (reader, unread) = inflater.into_inner();
+            let mut trailer = [0; 8];
+            unread.as_slice().chain(reader).read_exact(&mut trailer)?;
+            if trailer[..4] != hasher.finalize().to_le_bytes()
+                || trailer[4..] != (total_out as u32).to_le_bytes()
+            {
+                return Err(invalid("gzip trailer mismatch"));
+            }
+        }
+        Ok(Self {
+            format,
+            total_out,
+            checkpoints,
+        })
+    }
+
+    pub fn checkpoint_for(&self, offset: u64) -> &Checkpoint {
+        let index = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.output <= offset);
+        &self.checkpoints[index.saturating_sub(1)]
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.format.to_u8());
+        bytes.extend(self.total_out.to_le_bytes());
+        bytes.extend((self.checkpoints.len() as u64).to_le_bytes());
+        for checkpoint in &self.checkpoints {
+            bytes.extend(checkpoint.output.to_le_bytes());
+            bytes.extend(checkpoint.input_bits.to_le_bytes());
+            bytes.extend((checkpoint.window.len() as u32).to_le_bytes());
+            bytes.extend(&checkpoint.window);
+        }
+        bytes
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("invalid index signature"));
+        }
+        let mut format = [0];
+        reader.read_exact(&mut format)?;
+        let format = Format::from_u8(format[0]).ok_or_else(|| invalid("unknown index format"))?;
+        let total_out = read_u64(reader)?;
+        let count = read_u64(reader)?;
+        let mut checkpoints = vec![];
+        for _ in 0..count {
+            let output = read_u64(reader)?;
+            let input_bits = read_u64(reader)?;
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            let mut window = vec![0; u32::from_le_bytes(len) as usize];
+            reader.read_exact(&mut window)?;
+            checkpoints.push(Checkpoint {
+                output,
+                input_bits,
+                window,
+            });
+        }
+        if checkpoints.is_empty() {
+            return Err(invalid("index has no checkpoints"));
+        }
+        Ok(Self {
+            format,
+            total_out,
+            checkpoints,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct IndexedReader<R: Read + Seek> {
+    index: Index,
+    inflater: Option<Inflater<R>>,
+    pos: u64,
+}
+
+impl<R: Read + Seek> IndexedReader<R> {
+    pub fn new(inner: R, index: Index) -> io::Result<Self> {
+        let mut reader = Self {
+            index,
+            inflater: None,
+            pos: 0,
+        };
+        reader.resume(inner, 0)?;
+        Ok(reader)
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    pub fn into_inner(self) -> Option<R> {
+        self.inflater.map(|inflater| inflater.into_inner().0)
+    }
+
+    fn resume(&mut self, mut inner: R, offset: u64) -> io::Result<()> {
+        let checkpoint = self.index.checkpoint_for(offset);
+        inner.seek(SeekFrom::Start(checkpoint.input_bits / 8))?;
+        let inflater = Inflater::resume(
+            inner,
+            (checkpoint.input_bits % 8) as _,
+            &checkpoint.window,
+            checkpoint.output,
+        )?;
+        self.pos = checkpoint.output;
+        self.inflater = Some(inflater);
+        self.skip(offset - self.pos)
+    }
+
+    fn skip(&mut self, mut n: u64) -> io::Result<()> {
+        let mut buf = vec![0; 1 << 15];
+        while n > 0 {
+            let len = n.min(buf.len() as u64) as usize;
+            let read = self.read(&mut buf[..len])?;
+            if read == 0 {
+                break;
+            }
+            n -= read as u64;
+        }
+        Ok(())
+    }
+
+    fn inflater(&mut self) -> io::Result<&mut Inflater<R>> {
+        self.inflater
+            .as_mut()
+            .ok_or_else(|| io::Error::other("indexed reader was left unusable by a failed seek"))
+    }
+}
+
+impl<R: Read + Seek> Read for IndexedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inflater()?.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for IndexedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.index.total_out.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek position"))?
+        .min(self.index.total_out);
+        let checkpoint = self.index.checkpoint_for(target).output;
+        if checkpoint <= self.pos && self.pos <= target {
+            self.skip(target - self.pos)?;
+        } else {
+            let inflater = self.inflater.take();
+            let (inner, _) = inflater
+                .ok_or_else(|| {
+                    io::Error::other("indexed reader was left unusable by a failed seek")
+                })?
+                .into_inner();
+            self.resume(inner, target)?;
+        }
+        Ok(self.pos)
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{encoder::Encoder, gzip::trailer, parallel::ParallelEncoder, test_util::lines};
+
+    #[test]
+    fn trivial_test() {
+        let data = Encoder::new().deflate(b"");
+        let index = Index::build(data.as_slice(), Format::Raw, DEFAULT_SPAN).unwrap();
+        assert_eq!(0, index.total_out);
+        assert_eq!(1, index.checkpoints.len());
+    }
+
+    #[test]
+    fn test_seek_raw() {
+        let input = lines(300_000);
+        let data = Encoder::new().deflate(&input);
+        let index = Index::build(data.as_slice(), Format::Raw, 100_000).unwrap();
+        assert!(index.checkpoints.len() > 5);
+        assert!(index
+            .checkpoints
+            .iter()
+            .skip(1)
+            .all(|checkpoint| checkpoint.window.len() == 1 << 15));
+        let mut reader = IndexedReader::new(Cursor::new(data), index).unwrap();
+        for offset in [1_000_000, 5, 2_500_000, 2_500_100, input.len() as u64 - 10] {
+            assert_eq!(offset, reader.seek(SeekFrom::Start(offset)).unwrap());
+            let mut buf = [0; 10];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(input[offset as usize..offset as usize + 10], buf);
+        }
+        reader.seek(SeekFrom::End(-3)).unwrap();
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(input[input.len() - 3..], rest);
+    }
+
+    #[test]
+    fn test_seek_gzip() {
+        let input = lines(300_000);
+        let data = ParallelEncoder::new().gzip(
+            &input,
+            &GzipHeader {
+                name: Some(b"sample.txt".to_vec()),
+                ..Default::default()
+            },
+        );
+        let index = Index::build(data.as_slice(), Format::Gzip, 300_000).unwrap();
+        assert_eq!(input.len() as u64, index.total_out);
+        let mut reader = IndexedReader::new(Cursor::new(data), index).unwrap();
+        reader.seek(SeekFrom::Start(2_000_000)).unwrap();
+        let mut buf = [0; 100];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(input[2_000_000..2_000_100], buf);
+    }
+
+    #[test]
+    fn test_gzip_trailer_is_verified() {
+        let mut data = GzipHeader::default().to_bytes();
+        data.extend(Encoder::new().deflate(b"Fa-la-la-la"));
+        data.extend(trailer(0, 11));
+        assert!(Index::build(data.as_slice(), Format::Gzip, DEFAULT_SPAN).is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let data = Encoder::new().deflate(&lines(300_000));
+        let index = Index::build(data.as_slice(), Format::Raw, 500_000).unwrap();
+        let bytes = index.to_bytes();
+        assert_eq!(index, Index::read_from(&mut bytes.as_slice()).unwrap());
+        assert!(Index::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
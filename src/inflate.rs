@@ -0,0 +1,580 @@
+use alloc::vec::Vec;
+use core::{error, fmt};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use lazy_static::lazy_static;
+
+#[cfg(feature = "std")]
+use crate::{
+    bitstream::bitreader::BitReader,
+    deflate::{BTYPE_DYNAMIC, BTYPE_FIXED, BTYPE_STORED, END_OF_BLOCK},
+    huffman::dynamic::CL_ORDER,
+    limits::DecodeLimits,
+};
+use crate::{
+    deflate::{Variant, CONVERT_DISTANCE, CONVERT_LENGTH, MAX_DISTANCE, MIN_SEQUENCE},
+    huffman::decode::HuffmanDecoder,
+    limits::Limit,
+};
+
+pub const WINDOW_SIZE: usize = MAX_DISTANCE;
+
+lazy_static! {
+    static ref LENGTH_BASE: Vec<Option<(usize, usize)>> = bases(&CONVERT_LENGTH, MIN_SEQUENCE);
+    static ref DISTANCE_BASE: Vec<Option<(usize, usize)>> = bases(&CONVERT_DISTANCE, 1);
+    pub(crate) static ref FIXED_DECODERS: (HuffmanDecoder, HuffmanDecoder) = {
+        let mut ll_lengths = [8; 288];
+        ll_lengths[144..256].fill(9);
+        ll_lengths[256..280].fill(7);
+        (
+            HuffmanDecoder::new(&ll_lengths).unwrap(),
+            HuffmanDecoder::new(&[5; 32]).unwrap(),
+        )
+    };
+}
+
+pub(crate) fn length_base(variant: Variant, symbol: usize) -> Result<(usize, usize), InflateError> {
+    match symbol {
+        285 if variant == Variant::Deflate64 => Ok((MIN_SEQUENCE, 16)),
+        _ => LENGTH_BASE
+            .get(symbol)
+            .copied()
+            .flatten()
+            .ok_or(InflateError::InvalidSymbol),
+    }
+}
+
+pub(crate) fn distance_base(
+    variant: Variant,
+    symbol: usize,
+) -> Result<(usize, usize), InflateError> {
+    if symbol >= variant.distance_codes() {
+        return Err(InflateError::InvalidSymbol);
+    }
+    DISTANCE_BASE
+        .get(symbol)
+        .copied()
+        .flatten()
+        .ok_or(InflateError::InvalidSymbol)
+}
+
+fn bases(table: &[(usize, usize, usize)], first: usize) -> Vec<Option<(usize, usize)>> {
+    let mut bases = Vec::new();
+    for (value, (token, extra, _)) in table.iter().enumerate().skip(first) {
+        if bases.len() <= *token {
+            bases.resize(token + 1, None);
+        }
+        bases[*token].get_or_insert((value, *extra));
+    }
+    bases
+}
+
+#[derive(Debug)]
+pub enum InflateError {
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    UnexpectedEnd,
+    InvalidBlockType,
+    InvalidStoredLength,
+    InvalidCodeLengths,
+    InvalidSymbol,
+    DistanceTooFarBack,
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            InflateError::Io(e) => write!(f, "I/O error: {e}"),
+            InflateError::UnexpectedEnd => write!(f, "unexpected end of deflate stream"),
+            InflateError::InvalidBlockType => write!(f, "invalid block type"),
+            InflateError::InvalidStoredLength => write!(f, "stored block length mismatch"),
+            InflateError::InvalidCodeLengths => write!(f, "invalid Huffman code lengths"),
+            InflateError::InvalidSymbol => write!(f, "invalid Huffman symbol"),
+            InflateError::DistanceTooFarBack => write!(f, "distance too far back"),
+            InflateError::LimitExceeded(limit) => write!(f, "decode limit exceeded: {limit}"),
+        }
+    }
+}
+
+impl error::Error for InflateError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for InflateError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            InflateError::UnexpectedEnd
+        } else {
+            InflateError::Io(e)
+        }
+    }
+}
+
+impl From<Limit> for InflateError {
+    fn from(limit: Limit) -> Self {
+        InflateError::LimitExceeded(limit)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<InflateError> for io::Error {
+    fn from(e: InflateError) -> Self {
+        match e {
+            InflateError::Io(e) => e,
+            InflateError::UnexpectedEnd => io::Error::new(io::ErrorKind::UnexpectedEof, e),
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn inflate(input: &[u8]) -> Result<Vec<u8>, InflateError> {
+    inflate_with(input, Variant::Deflate)
+}
+
+#[cfg(feature = "std")]
+pub fn inflate_with(input: &[u8], variant: Variant) -> Result<Vec<u8>, InflateError> {
+    inflate_all(Inflater::new(input).with_variant(variant))
+}
+
+#[cfg(feature = "std")]
+pub fn inflate_limited(input: &[u8], limits: DecodeLimits) -> Result<Vec<u8>, InflateError> {
+    inflate_all(Inflater::new(input).with_limits(limits))
+}
+
+#[cfg(feature = "std")]
+fn inflate_all(mut inflater: Inflater<&[u8]>) -> Result<Vec<u8>, InflateError> {
+    let mut output = vec![];
+    let mut buf = [0; 4096];
+    loop {
+        let n = inflater.inflate(&mut buf)?;
+        if n == 0 {
+            return Ok(output);
+        }
+        output.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Stored(usize),
+    Codes,
+    Copy { length: usize, distance: usize },
+    Done,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Inflater<R: Read> {
+    reader: BitReader<R>,
+    state: State,
+    variant: Variant,
+    limits: DecodeLimits,
+    blocks: u64,
+    last: bool,
+    decoders: Option<Box<(HuffmanDecoder, HuffmanDecoder)>>,
+    window: Vec<u8>,
+    window_pos: usize,
+    total_out: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Inflater<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            reader: BitReader::new(inner),
+            state: State::Header,
+            variant: Variant::Deflate,
+            limits: DecodeLimits::default(),
+            blocks: 0,
+            last: false,
+            decoders: None,
+            window: vec![0; WINDOW_SIZE],
+            window_pos: 0,
+            total_out: 0,
+        }
+    }
+
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self.window = vec![0; variant.max_distance()];
+        self
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn resume(
+        inner: R,
+        skip_bits: usize,
+        window: &[u8],
+        total_out: u64,
+    ) -> Result<Self, InflateError> {
+        let mut inflater = Self::new(inner);
+        inflater.reader.read_bits(skip_bits)?;
+        let window = &window[window.len().saturating_sub(WINDOW_SIZE)..];
+        inflater.window[..window.len()].copy_from_slice(window);
+        inflater.window_pos = window.len() % WINDOW_SIZE;
+        inflater.total_out = total_out;
+        Ok(inflater)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    pub fn is_at_block_boundary(&self) -> bool {
+        self.state == State::Header && !self.last
+    }
+
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    pub fn bits_read(&self) -> u64 {
+        self.reader.bits_read()
+    }
+
+    pub fn window(&self) -> Vec<u8> {
+        let len = self.total_out.min(self.window.len() as u64) as usize;
+        let start = self.window_pos + self.window.len() - len;
+        (start..start + len)
+            .map(|i| self.window[i % self.window.len()])
+            .collect()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub fn into_inner(self) -> (R, Vec<u8>) {
+        self.reader.into_inner()
+    }
+
+    pub fn inflate(&mut self, buf: &mut [u8]) -> Result<usize, InflateError> {
+        self.inflate_until(buf, false)
+    }
+
+    pub fn inflate_block(&mut self, buf: &mut [u8]) -> Result<usize, InflateError> {
+        self.inflate_until(buf, true)
+    }
+
+    fn inflate_until(
+        &mut self,
+        buf: &mut [u8],
+        stop_at_block: bool,
+    ) -> Result<usize, InflateError> {
+        let room = self
+            .limits
+            .output_room(self.total_out)
+            .min(buf.len() as u64);
+        let buf = &mut buf[..room as usize];
+        let mut produced = 0;
+        let mut in_block = self.state != State::Header;
+        while produced < buf.len() {
+            match self.state {
+                State::Header if self.last => self.state = State::Done,
+                State::Header if stop_at_block && in_block => break,
+                State::Header => {
+                    self.read_header()?;
+                    in_block = true;
+                }
+                State::Stored(0) => self.state = State::Header,
+                State::Stored(remain) => {
+                    let byte = self.reader.read_byte()?;
+                    buf[produced] = self.emit(byte);
+                    produced += 1;
+                    self.state = State::Stored(remain - 1);
+                }
+                State::Codes => self.read_symbol(&mut buf[produced], &mut produced)?,
+                State::Copy { length: 0, .. } => self.state = State::Codes,
+                State::Copy { length, distance } => {
+                    let window_size = self.window.len();
+                    let byte =
+                        self.window[(self.window_pos + window_size - distance) % window_size];
+                    buf[produced] = self.emit(byte);
+                    produced += 1;
+                    self.state = State::Copy {
+                        length: length - 1,
+                        distance,
+                    };
+                }
+                State::Done => break,
+            }
+        }
+        self.limits.check_output(self.total_out)?;
+        self.limits
+            .check_ratio(self.reader.bits_read().div_ceil(8), self.total_out)?;
+        Ok(produced)
+    }
+
+    fn emit(&mut self, byte: u8) -> u8 {
+        self.window[self.window_pos] = byte;
+        self.window_pos = (self.window_pos + 1) % self.window.len();
+        self.total_out += 1;
+        byte
+    }
+
+    fn read_header(&mut self) -> Result<(), InflateError> {
+        self.blocks += 1;
+        self.limits.check_blocks(self.blocks)?;
+        self.last = self.reader.read_bit()? == 1;
+        match self.reader.read_bits(2)? as u16 {
+            BTYPE_STORED => {
+                self.reader.align();
+                let len = self.reader.read_bits(16)?;
+                let nlen = self.reader.read_bits(16)?;
+                if len != !nlen & 0xffff {
+                    return Err(InflateError::InvalidStoredLength);
+                }
+                self.state = State::Stored(len as _);
+            }
+            BTYPE_FIXED => {
+                self.decoders = None;
+                self.state = State::Codes;
+            }
+            BTYPE_DYNAMIC => {
+                self.decoders = Some(Box::new(self.read_tables()?));
+                self.state = State::Codes;
+            }
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+        Ok(())
+    }
+
+    fn read_tables(&mut self) -> Result<(HuffmanDecoder, HuffmanDecoder), InflateError> {
+        let hlit = self.reader.read_bits(5)? as usize + 257;
+        let hdist = self.reader.read_bits(5)? as usize + 1;
+        let hclen = self.reader.read_bits(4)? as usize + 4;
+        let mut cl_lengths = [0; CL_ORDER.len()];
+        for symbol in &CL_ORDER[..hclen] {
+            cl_lengths[*symbol] = self.reader.read_bits(3)? as u8;
+        }
+        let cl_decoder =
+            HuffmanDecoder::new(&cl_lengths).ok_or(InflateError::InvalidCodeLengths)?;
+        let mut lengths = Vec::with_capacity(hlit + hdist);
+        while lengths.len() < hlit + hdist {
+            let symbol = cl_decoder
+                .decode(&mut self.reader)?
+                .ok_or(InflateError::InvalidCodeLengths)?;
+            let (len, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *lengths.last().ok_or(InflateError::InvalidCodeLengths)?;
+                    (previous, 3 + self.reader.read_bits(2)?)
+                }
+                17 => (0, 3 + self.reader.read_bits(3)?),
+                _ => (0, 11 + self.reader.read_bits(7)?),
+            };
+            if lengths.len() + repeat as usize > hlit + hdist {
+                return Err(InflateError::InvalidCodeLengths);
+            }
+            lengths.extend((0..repeat).map(|_| len));
+        }
+        if lengths[END_OF_BLOCK] == 0 {
+            return Err(InflateError::InvalidCodeLengths);
+        }
+        let ll_decoder = HuffmanDecoder::new(&lengths[..hlit]);
+        let d_decoder = HuffmanDecoder::new(&lengths[hlit..]);
+        ll_decoder
+            .zip(d_decoder)
+            .ok_or(InflateError::InvalidCodeLengths)
+    }
+
+    fn read_symbol(&mut self, out: &mut u8, produced: &mut usize) -> Result<(), InflateError> {
+        let (ll_decoder, d_decoder) = match self.decoders {
+            Some(ref decoders) => (&decoders.0, &decoders.1),
+            None => (&FIXED_DECODERS.0, &FIXED_DECODERS.1),
+        };
+        let symbol = ll_decoder
+            .decode(&mut self.reader)?
+            .ok_or(InflateError::InvalidSymbol)? as usize;
+        if symbol < END_OF_BLOCK {
+            *out = self.emit(symbol as u8);
+            *produced += 1;
+            return Ok(());
+        }
+        if symbol == END_OF_BLOCK {
+            self.state = State::Header;
+            return Ok(());
+        }
+        let (base, extra) = length_base(self.variant, symbol)?;
+        let length = base + self.reader.read_bits(extra)? as usize;
+        let symbol = d_decoder
+            .decode(&mut self.reader)?
+            .ok_or(InflateError::InvalidSymbol)? as usize;
+        let (base, extra) = distance_base(self.variant, symbol)?;
+        let distance = base + self.reader.read_bits(extra)? as usize;
+        if distance as u64 > self.total_out {
+            return Err(InflateError::DistanceTooFarBack);
+        }
+        self.state = State::Copy { length, distance };
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Inflater<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.inflate(buf)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bitstream::ostream::OutputStream,
+        deflate::{DeflateToken, Strategy},
+        encoder::{Encoder, BLOCK_SIZE},
+        optimal::deflate_optimal,
+        parallel::ParallelEncoder,
+    };
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(Vec::<u8>::new(), inflate(&[3, 0]).unwrap());
+        assert!(matches!(inflate(&[]), Err(InflateError::UnexpectedEnd)));
+        assert!(matches!(inflate(&[7]), Err(InflateError::InvalidBlockType)));
+    }
+
+    #[test]
+    fn test_zlib_output() {
+        let fixed = [
+            115, 73, 77, 203, 73, 44, 73, 213, 81, 240, 72, 205, 201, 201, 87, 228, 2, 0,
+        ];
+        assert_eq!(b"Deflate, Hello!\n".to_vec(), inflate(&fixed).unwrap());
+        let stored = [1, 6, 0, 249, 255, 115, 116, 111, 114, 101, 100];
+        assert_eq!(b"stored".to_vec(), inflate(&stored).unwrap());
+        let dynamic = [
+            13, 200, 71, 1, 0, 48, 16, 2, 48, 173, 172, 3, 255, 10, 218, 60, 99, 221, 136, 197,
+            222, 42, 171, 160, 16, 100, 109, 167, 35, 52, 167, 105, 175, 62, 228, 143, 19, 114,
+            227, 241, 1,
+        ];
+        assert_eq!(
+            b"dcfhbaheddhhgcdcgabcaeaehggghcfbachdegeggfgdfaecfbdeebbhhbfb".to_vec(),
+            inflate(&dynamic).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = (0..100_000_u64)
+            .flat_map(|i| (i * i % 977).to_string().into_bytes())
+            .collect::<Vec<_>>();
+        for strategy in [Strategy::Default, Strategy::Fixed, Strategy::Rle] {
+            let output = Encoder::new().with_strategy(strategy).deflate(&input);
+            assert_eq!(input, inflate(&output).unwrap());
+        }
+        let output = ParallelEncoder::new()
+            .with_chunk_size(50_000)
+            .deflate(&input);
+        assert_eq!(input, inflate(&output).unwrap());
+        let mut os = OutputStream::default();
+        os.extend(deflate_optimal(&input[..20_000]).into_iter());
+        assert_eq!(input[..20_000], inflate(&os.finalize()).unwrap());
+    }
+
+    #[test]
+    fn test_distance_too_far_back() {
+        let mut os = OutputStream::default();
+        os.extend(
+            [
+                DeflateToken::Bhead(1),
+                DeflateToken::Btype(BTYPE_FIXED),
+                DeflateToken::Literal(b'a' as _),
+                DeflateToken::Backref {
+                    length: 3,
+                    distance: 2,
+                },
+                DeflateToken::EndOfBlock,
+            ]
+            .into_iter(),
+        );
+        assert!(matches!(
+            inflate(&os.finalize()),
+            Err(InflateError::DistanceTooFarBack)
+        ));
+    }
+
+    #[test]
+    fn test_deflate64_round_trip() {
+        let mut input = (0..40_000_u64)
+            .flat_map(|i| (i * i % 977).to_string().into_bytes())
+            .collect::<Vec<_>>();
+        input.extend(vec![b'z'; 70_000]);
+        input.extend_from_within(..50_000);
+        let encoder = Encoder::new().with_variant(Variant::Deflate64);
+        let output = encoder.deflate(&input);
+        assert!(output.len() < Encoder::new().deflate(&input).len());
+        assert_eq!(input, inflate_with(&output, Variant::Deflate64).unwrap());
+        assert!(inflate(&output).is_err());
+    }
+
+    #[test]
+    fn test_deflate64_codes_are_rejected_in_deflate() {
+        let mut os = OutputStream::default().with_variant(Variant::Deflate64);
+        os.extend(
+            [
+                DeflateToken::Bhead(1),
+                DeflateToken::Btype(BTYPE_FIXED),
+                DeflateToken::Literal(b'a' as _),
+                DeflateToken::Backref {
+                    length: 3,
+                    distance: 40_000,
+                },
+                DeflateToken::EndOfBlock,
+            ]
+            .into_iter(),
+        );
+        assert!(matches!(
+            inflate(&os.finalize()),
+            Err(InflateError::InvalidSymbol)
+        ));
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let input = vec![b'a'; 3 * BLOCK_SIZE];
+        let data = Encoder::new().deflate(&input);
+        let limits = DecodeLimits::new().with_max_output(input.len() as u64);
+        assert_eq!(input, inflate_limited(&data, limits).unwrap());
+        let limits = DecodeLimits::new().with_max_output(input.len() as u64 - 1);
+        assert!(matches!(
+            inflate_limited(&data, limits),
+            Err(InflateError::LimitExceeded(Limit::Output))
+        ));
+        let limits = DecodeLimits::new().with_max_output(1000);
+        let mut inflater = Inflater::new(data.as_slice()).with_limits(limits);
+        assert!(inflater.inflate(&mut vec![0; input.len()]).is_err());
+        assert_eq!(1001, inflater.total_out());
+        let limits = DecodeLimits::new().with_max_blocks(1);
+        assert!(matches!(
+            inflate_limited(&data, limits),
+            Err(InflateError::LimitExceeded(Limit::Blocks))
+        ));
+        let limits = DecodeLimits::new().with_max_ratio(10);
+        assert!(matches!(
+            inflate_limited(&data, limits),
+            Err(InflateError::LimitExceeded(Limit::Ratio))
+        ));
+    }
+
+    #[test]
+    fn test_unread_input_is_returned() {
+        let mut data = Encoder::new().deflate(b"Fa-la-la-la");
+        data.extend([1, 2, 3]);
+        let mut inflater = Inflater::new(data.as_slice());
+        let mut output = vec![];
+        inflater.read_to_end(&mut output).unwrap();
+        assert!(inflater.is_done());
+        assert_eq!(b"Fa-la-la-la".to_vec(), output);
+        assert_eq!(vec![1, 2, 3], inflater.into_inner().1);
+    }
+}
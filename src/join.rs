@@ -0,0 +1,256 @@
+use std::io::{self, Write};
+
+use crate::{
+    framing::{Framing, Gzip, Raw, Zlib},
+    inflate::Inflater,
+};
+
+pub const CHUNK_SIZE: usize = 1 << 15;
+
+const EMPTY_STREAM: [u8; 2] = [3, 0];
+const STORED_LENGTHS: [u8; 4] = [0, 0, 0xff, 0xff];
+
+pub type DeflateJoin<W> = Join<W, Raw>;
+pub type ZlibJoin<W> = Join<W, Zlib>;
+pub type GzJoin<W> = Join<W, Gzip>;
+
+#[derive(Debug)]
+struct Stream {
+    bytes: Vec<u8>,
+    last_header: u64,
+    end: u64,
+}
+
+#[derive(Debug)]
+pub struct Join<W: Write, F: Framing> {
+    inner: W,
+    framing: Option<F>,
+    input: F,
+    pending: Option<Stream>,
+    started: bool,
+    total: u64,
+}
+
+impl<W: Write, F: Framing + Clone + Default> Join<W, F> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            framing: None,
+            input: F::default(),
+            pending: None,
+            started: false,
+            total: 0,
+        }
+    }
+}
+
+impl<W: Write, F: Framing + Clone> Join<W, F> {
+    pub fn with_framing(mut self, framing: F) -> Self {
+        self.framing = Some(framing);
+        self
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut pos = 0;
+        loop {
+            pos += self.append_stream(&data[pos..])?;
+            if pos == data.len() {
+                return Ok(());
+            }
+            if !self.input.multi_member() {
+                return Err(invalid("trailing data after the deflate stream"));
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.start()?;
+        let trailer = self.framing.as_ref().unwrap().trailer(self.total);
+        match self.pending.take() {
+            Some(stream) => self.inner.write_all(&stream.bytes)?,
+            None => self.inner.write_all(&EMPTY_STREAM)?,
+        }
+        self.inner.write_all(&trailer)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn append_stream(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.input.reset();
+        let header = self
+            .input
+            .parse_header(data)?
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        if self.framing.is_none() {
+            let mut framing = self.input.clone();
+            framing.reset();
+            self.framing = Some(framing);
+        }
+        let body = &data[header..];
+        let mut inflater = Inflater::new(body);
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut last_header = 0;
+        while !inflater.is_done() {
+            if inflater.is_at_block_boundary() {
+                last_header = inflater.bits_read();
+            }
+            let n = inflater.inflate_block(&mut buf)?;
+            self.input.update(&buf[..n]);
+            if let Some(framing) = self.framing.as_mut() {
+                framing.update(&buf[..n]);
+            }
+        }
+        let end = inflater.bits_read();
+        let len = end.div_ceil(8) as usize;
+        let trailer_end = len + self.input.trailer_len();
+        let trailer = body
+            .get(len..trailer_end)
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        self.input.check_trailer(trailer, inflater.total_out())?;
+        self.total += inflater.total_out();
+        let stream = Stream {
+            bytes: body[..len].to_vec(),
+            last_header,
+            end,
+        };
+        if let Some(mut previous) = self.pending.replace(stream) {
+            self.start()?;
+            unset_final(&mut previous.bytes, previous.last_header, previous.end);
+            self.inner.write_all(&previous.bytes)?;
+        }
+        Ok(header + trailer_end)
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            let framing = self.framing.get_or_insert_with(|| self.input.clone());
+            self.inner.write_all(&framing.header())?;
+            self.started = true;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn unset_final(bytes: &mut Vec<u8>, last_header: u64, end: u64) {
+    bytes[(last_header / 8) as usize] &= !(1 << (last_header % 8));
+    bytes.truncate(end.div_ceil(8) as usize);
+    let bits = end % 8;
+    if bits > 0 {
+        *bytes.last_mut().unwrap() &= (1 << bits) - 1;
+    }
+    if bits == 0 || bits > 5 {
+        bytes.push(0);
+    }
+    bytes.extend(STORED_LENGTHS);
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::{
+        deflate::Strategy,
+        encoder::Encoder,
+        flate2::{read::GzDecoder, write::GzEncoder, Compression, GzBuilder},
+        gzip::GzipHeader,
+        inflate::inflate,
+        zlib,
+    };
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn trivial_test() {
+        let output = DeflateJoin::new(vec![]).finish().unwrap();
+        assert_eq!(EMPTY_STREAM.to_vec(), output);
+        let mut join = DeflateJoin::new(vec![]);
+        join.append(&EMPTY_STREAM).unwrap();
+        join.append(&EMPTY_STREAM).unwrap();
+        assert!(inflate(&join.finish().unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_join_raw_streams() {
+        let mut join = DeflateJoin::new(vec![]);
+        let mut expected = vec![];
+        for i in 0..40 {
+            let data = b"Fa-la-la-la, la-la-la-la! ".repeat(i)[..i * 7].to_vec();
+            let strategy = [Strategy::Default, Strategy::Fixed, Strategy::HuffmanOnly][i % 3];
+            join.append(&Encoder::new().with_strategy(strategy).deflate(&data))
+                .unwrap();
+            expected.extend(data);
+        }
+        assert_eq!(expected.len() as u64, join.total_out());
+        assert_eq!(expected, inflate(&join.finish().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_join_gzip_members() {
+        let header = GzipHeader {
+            name: Some(b"daily.log".to_vec()),
+            ..Default::default()
+        };
+        let mut first = GzBuilder::new()
+            .filename("00.log")
+            .write(vec![], Compression::default());
+        first.write_all(b"hour 0\n").unwrap();
+        let mut hourly = first.finish().unwrap();
+        hourly.extend(gzip(b"hour 1\n"));
+        let mut join = GzJoin::new(vec![]);
+        join.append(&hourly).unwrap();
+        join.append(&gzip(&b"hour 2\n".repeat(1000))).unwrap();
+        let joined = join.finish().unwrap();
+        let mut decoder = GzDecoder::new(joined.as_slice());
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        let expected = [b"hour 0\nhour 1\n".to_vec(), b"hour 2\n".repeat(1000)].concat();
+        assert_eq!(expected, output);
+        assert_eq!(Some(&b"00.log"[..]), decoder.header().unwrap().filename());
+        assert_eq!(joined.len() as u64, decoder.total_in());
+        let mut join = GzJoin::new(vec![]).with_framing(Gzip::new(header.clone()));
+        join.append(&gzip(b"hour 3\n")).unwrap();
+        let joined = join.finish().unwrap();
+        assert!(joined.starts_with(&header.to_bytes()));
+    }
+
+    #[test]
+    fn test_join_zlib_and_errors() {
+        let mut join = ZlibJoin::new(vec![]);
+        for data in [&b"Deck the halls"[..], b" with boughs of holly"] {
+            let mut stream = zlib::HEADER.to_vec();
+            stream.extend(Encoder::new().deflate(data));
+            stream.extend(zlib::trailer(zlib::adler32(data)));
+            join.append(&stream).unwrap();
+        }
+        let joined = join.finish().unwrap();
+        let expected = b"Deck the halls with boughs of holly";
+        assert_eq!(
+            zlib::trailer(zlib::adler32(expected)),
+            joined[joined.len() - 4..]
+        );
+        assert_eq!(expected.to_vec(), inflate(&joined[2..]).unwrap());
+        let mut member = gzip(b"hour 0\n");
+        let last = member.len() - 1;
+        member[last] ^= 1;
+        assert!(GzJoin::new(vec![]).append(&member).is_err());
+        assert!(DeflateJoin::new(vec![]).append(&[3, 0, 0]).is_err());
+        assert!(GzJoin::new(vec![]).append(&member[..10]).is_err());
+    }
+}
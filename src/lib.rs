@@ -1,7 +1,67 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod append;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(feature = "std")]
+pub mod bgzf;
 pub mod bitstream;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod compressor;
+pub mod decompressor;
 pub mod deflate;
+#[cfg(feature = "std")]
+pub mod detect;
+pub mod encoder;
+#[cfg(feature = "std")]
+pub mod flate2;
+#[cfg(feature = "std")]
+pub mod framing;
+#[cfg(feature = "std")]
+pub mod gzip;
 pub mod huffman;
+#[cfg(feature = "std")]
+pub mod index;
+pub mod inflate;
+#[cfg(feature = "std")]
+pub mod join;
+pub mod limits;
+pub mod matcher;
+pub mod optimal;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod reader;
+#[cfg(feature = "std")]
+pub mod recompress;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "std")]
+pub mod writer;
+#[cfg(feature = "std")]
+pub mod zip;
+#[cfg(feature = "std")]
+pub mod zlib;
 
-pub use deflate::deflate;
+pub use compressor::{compress_bound, Compressor};
+pub use decompressor::Decompressor;
+pub use deflate::{deflate, Strategy, Variant};
+pub use encoder::Encoder;
 pub use huffman::generate::generate_code;
 pub use huffman::HuffmanToken;
+pub use inflate::InflateError;
+#[cfg(feature = "std")]
+pub use inflate::{inflate, inflate_limited, inflate_with, Inflater};
+pub use limits::{DecodeLimits, Limit};
+pub use matcher::MatchFinder;
+pub use optimal::deflate_optimal;
+#[cfg(feature = "std")]
+pub use parallel::ParallelEncoder;
+#[cfg(feature = "std")]
+pub use reader::DeflateEncoderReader;
+#[cfg(feature = "std")]
+pub use writer::{DeflateDecoderWriter, DeflateWriter};
@@ -0,0 +1,157 @@
+use core::fmt;
+
+pub const RATIO_MIN_OUTPUT: u64 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Output,
+    Ratio,
+    Blocks,
+    Members,
+    HeaderSize,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Output => write!(f, "output size"),
+            Limit::Ratio => write!(f, "compression ratio"),
+            Limit::Blocks => write!(f, "number of blocks"),
+            Limit::Members => write!(f, "number of members"),
+            Limit::HeaderSize => write!(f, "header size"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_output: Option<u64>,
+    max_ratio: Option<u64>,
+    max_blocks: Option<u64>,
+    max_members: Option<u64>,
+    max_header_size: Option<usize>,
+}
+
+impl DecodeLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_output(mut self, max_output: u64) -> Self {
+        self.max_output = Some(max_output);
+        self
+    }
+
+    pub fn with_max_ratio(mut self, max_ratio: u64) -> Self {
+        self.max_ratio = Some(max_ratio);
+        self
+    }
+
+    pub fn with_max_blocks(mut self, max_blocks: u64) -> Self {
+        self.max_blocks = Some(max_blocks);
+        self
+    }
+
+    pub fn with_max_members(mut self, max_members: u64) -> Self {
+        self.max_members = Some(max_members);
+        self
+    }
+
+    pub fn with_max_header_size(mut self, max_header_size: usize) -> Self {
+        self.max_header_size = Some(max_header_size);
+        self
+    }
+
+    pub fn output_room(&self, total_out: u64) -> u64 {
+        self.max_output.map_or(u64::MAX, |max| {
+            max.saturating_add(1).saturating_sub(total_out)
+        })
+    }
+
+    pub fn check_output(&self, total_out: u64) -> Result<(), Limit> {
+        check(self.max_output, total_out, Limit::Output)
+    }
+
+    pub fn check_ratio(&self, total_in: u64, total_out: u64) -> Result<(), Limit> {
+        match self.max_ratio {
+            Some(max)
+                if total_out >= RATIO_MIN_OUTPUT && total_out > max.saturating_mul(total_in) =>
+            {
+                Err(Limit::Ratio)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_blocks(&self, blocks: u64) -> Result<(), Limit> {
+        check(self.max_blocks, blocks, Limit::Blocks)
+    }
+
+    pub fn check_members(&self, members: u64) -> Result<(), Limit> {
+        check(self.max_members, members, Limit::Members)
+    }
+
+    pub fn check_header_size(&self, size: usize) -> Result<(), Limit> {
+        check(
+            self.max_header_size.map(|max| max as u64),
+            size as u64,
+            Limit::HeaderSize,
+        )
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn remaining(&self, total_out: u64, blocks: u64) -> Self {
+        Self {
+            max_output: self.max_output.map(|max| max.saturating_sub(total_out)),
+            max_blocks: self.max_blocks.map(|max| max.saturating_sub(blocks)),
+            ..*self
+        }
+    }
+}
+
+fn check(max: Option<u64>, value: u64, limit: Limit) -> Result<(), Limit> {
+    match max {
+        Some(max) if value > max => Err(limit),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_test() {
+        let limits = DecodeLimits::new();
+        assert_eq!(u64::MAX, limits.output_room(1 << 40));
+        assert_eq!(Ok(()), limits.check_ratio(1, u64::MAX));
+    }
+
+    #[test]
+    fn test_checks() {
+        let limits = DecodeLimits::new()
+            .with_max_output(100)
+            .with_max_ratio(10)
+            .with_max_blocks(2);
+        assert_eq!(101, limits.output_room(0));
+        assert_eq!(Ok(()), limits.check_output(100));
+        assert_eq!(Err(Limit::Output), limits.check_output(101));
+        assert_eq!(Ok(()), limits.check_ratio(1, 1000));
+        assert_eq!(
+            Err(Limit::Ratio),
+            limits.check_ratio(1000, RATIO_MIN_OUTPUT * 10)
+        );
+        assert_eq!(Err(Limit::Blocks), limits.check_blocks(3));
+        let remaining = limits.remaining(60, 2);
+        assert_eq!(Err(Limit::Output), remaining.check_output(41));
+        assert_eq!(Err(Limit::Blocks), remaining.check_blocks(1));
+    }
+
+    #[test]
+    fn test_unbounded_max_output() {
+        let limits = DecodeLimits::new().with_max_output(u64::MAX);
+        assert_eq!(u64::MAX, limits.output_room(0));
+        assert_eq!(u64::MAX - 10, limits.output_room(10));
+        assert_eq!(Ok(()), limits.check_output(u64::MAX));
+    }
+}
@@ -0,0 +1,276 @@
+use alloc::{vec, vec::Vec};
+
+use super::{match_length, MatchFinder};
+use crate::deflate::{MAX_DISTANCE, MAX_SEQUENCE, MIN_SEQUENCE};
+
+const HASH_BITS: usize = 16;
+const HASH_SIZE: usize = 1 << HASH_BITS;
+const NIL: usize = usize::MAX;
+const DEFAULT_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct BinaryTree {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+    son: Vec<usize>,
+    cyclic: usize,
+    depth: usize,
+    next: usize,
+}
+
+impl Default for BinaryTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinaryTree {
+    pub fn new() -> Self {
+        Self::with_window(MAX_DISTANCE)
+    }
+
+    pub fn with_window(window: usize) -> Self {
+        let cyclic = window + 1;
+        Self {
+            head: vec![NIL; HASH_SIZE],
+            prev: vec![NIL; cyclic],
+            son: vec![NIL; 2 * cyclic],
+            cyclic,
+            depth: DEFAULT_DEPTH,
+            next: 0,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    fn catch_up(&mut self, input: &[u8], pos: usize) {
+        while self.next < pos {
+            let current = self.next;
+            self.update(input, current, MAX_SEQUENCE, self.cyclic - 1, None);
+        }
+    }
+
+    fn update(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+        mut matches: Option<&mut Vec<(usize, usize)>>,
+    ) {
+        self.next = pos + 1;
+        if input.len() - pos < MIN_SEQUENCE {
+            return;
+        }
+        let hash = hash(input, pos);
+        let mut cur_match = self.head[hash];
+        self.head[hash] = pos;
+        self.prev[pos % self.cyclic] = cur_match;
+        let limit = max_length.min(input.len() - pos);
+        let mut ptr0 = 2 * (pos % self.cyclic) + 1;
+        let mut ptr1 = 2 * (pos % self.cyclic);
+        let (mut len0, mut len1) = (0, 0);
+        let mut best = MIN_SEQUENCE - 1;
+        let mut depth = self.depth;
+        loop {
+            if cur_match == NIL || pos - cur_match >= self.cyclic || depth == 0 {
+                self.son[ptr0] = NIL;
+                self.son[ptr1] = NIL;
+                return;
+            }
+            depth -= 1;
+            let pair = 2 * (cur_match % self.cyclic);
+            let start = len0.min(len1);
+            let len = start + match_length(input, cur_match + start, pos + start, limit - start);
+            if len > best && pos - cur_match <= max_distance {
+                best = len;
+                if let Some(ref mut matches) = matches {
+                    matches.push((len, pos - cur_match));
+                }
+            }
+            if len == limit {
+                self.son[ptr1] = self.son[pair];
+                self.son[ptr0] = self.son[pair + 1];
+                return;
+            }
+            if input[cur_match + len] < input[pos + len] {
+                self.son[ptr1] = cur_match;
+                ptr1 = pair + 1;
+                cur_match = self.son[ptr1];
+                len1 = len;
+            } else {
+                self.son[ptr0] = cur_match;
+                ptr0 = pair;
+                cur_match = self.son[ptr0];
+                len0 = len;
+            }
+        }
+    }
+
+    fn requery(
+        &self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        if input.len() - pos < MIN_SEQUENCE {
+            return matches;
+        }
+        let oldest = self.next.saturating_sub(self.cyclic);
+        let mut cur_match = self.head[hash(input, pos)];
+        let mut best = MIN_SEQUENCE - 1;
+        let mut depth = self.depth;
+        while cur_match != NIL && cur_match >= oldest && depth > 0 {
+            if cur_match < pos {
+                if pos - cur_match > max_distance {
+                    break;
+                }
+                depth -= 1;
+                let len = match_length(input, cur_match, pos, max_length);
+                if len > best {
+                    best = len;
+                    matches.push((len, pos - cur_match));
+                }
+            }
+            cur_match = self.prev[cur_match % self.cyclic];
+        }
+        matches
+    }
+}
+
+impl MatchFinder for BinaryTree {
+    fn insert(&mut self, input: &[u8], pos: usize) {
+        self.catch_up(input, pos + 1);
+    }
+
+    fn find(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Option<(usize, usize)> {
+        self.find_all(input, pos, max_length, max_distance).pop()
+    }
+
+    fn find_all(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Vec<(usize, usize)> {
+        // Inserting reshapes the tree around newer positions, so an already inserted
+        // position is answered from the hash chain instead.
+        if pos < self.next {
+            return self.requery(input, pos, max_length, max_distance);
+        }
+        let mut matches = vec![];
+        self.catch_up(input, pos);
+        self.update(input, pos, max_length, max_distance, Some(&mut matches));
+        matches
+    }
+}
+
+fn hash(input: &[u8], pos: usize) -> usize {
+    let value =
+        (input[pos] as usize) << 16 | (input[pos + 1] as usize) << 8 | input[pos + 2] as usize;
+    (value.wrapping_mul(2654435761) >> 8) & (HASH_SIZE - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{matcher::BruteForce, test_util::sample};
+
+    #[test]
+    fn trivial_test() {
+        let mut finder = BinaryTree::new();
+        assert_eq!(None, finder.find(b"", 0, 258, 32768));
+        assert_eq!(None, finder.find(b"abc", 0, 258, 32768));
+        assert_eq!(Some((7, 3)), finder.find(b"Fa-la-la-la", 4, 258, 32768));
+    }
+
+    #[test]
+    fn test_longest_match_as_brute_force() {
+        let input = sample(5000);
+        let mut tree = BinaryTree::new().with_depth(usize::MAX);
+        let mut brute = BruteForce;
+        for pos in 0..input.len() {
+            let expected = brute.find(&input, pos, 258, 1000).map(|(len, _)| len);
+            let found = tree.find(&input, pos, 258, 1000);
+            match found {
+                Some((len, distance)) => {
+                    assert_eq!(expected, Some(len), "position {pos}");
+                    assert!(distance <= 1000);
+                    assert_eq!(len, match_length(&input, pos - distance, pos, 258));
+                }
+                None => assert!(expected.unwrap_or(0) < MIN_SEQUENCE, "position {pos}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_all() {
+        let input = b"abcXabcdYabcdeZabcde";
+        let mut tree = BinaryTree::new();
+        for pos in 0..15 {
+            tree.insert(input, pos);
+        }
+        assert_eq!(vec![(5, 6)], tree.find_all(input, 15, 258, 32768));
+        let input = b"abcdeXabcYabcde";
+        let mut tree = BinaryTree::new();
+        assert_eq!(vec![(3, 4), (5, 10)], tree.find_all(input, 10, 258, 32768));
+        assert_eq!(vec![(3, 4), (5, 10)], tree.find_all(input, 10, 258, 32768));
+    }
+
+    #[test]
+    fn test_find_all_as_reference() {
+        let input = sample(5000);
+        let mut tree = BinaryTree::new().with_depth(usize::MAX);
+        for pos in 0..input.len() {
+            let max_distance = if pos % 2 == 0 { 1000 } else { 32768 };
+            assert_eq!(
+                all_lengths(&input, pos, max_distance),
+                tree.find_all(&input, pos, 258, max_distance),
+                "position {pos}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_requery_after_insert() {
+        let input = sample(5000);
+        let mut tree = BinaryTree::new().with_depth(usize::MAX);
+        tree.insert(&input, input.len() - 1);
+        for pos in 0..input.len() {
+            assert_eq!(
+                all_lengths(&input, pos, 1000),
+                tree.find_all(&input, pos, 258, 1000),
+                "position {pos}"
+            );
+        }
+        let input = b"abcXabcdYabcdeZabcde";
+        let mut tree = BinaryTree::new();
+        assert_eq!(vec![(5, 6)], tree.find_all(input, 15, 258, 32768));
+        assert_eq!(vec![(5, 6)], tree.find_all(input, 15, 258, 32768));
+    }
+
+    fn all_lengths(input: &[u8], pos: usize, max_distance: usize) -> Vec<(usize, usize)> {
+        let mut matches: Vec<(usize, usize)> = vec![];
+        for distance in 1..=max_distance.min(pos) {
+            let len = (0..258.min(input.len() - pos))
+                .take_while(|i| input[pos - distance + i] == input[pos + i])
+                .count();
+            if len >= MIN_SEQUENCE && matches.last().is_none_or(|(longest, _)| len > *longest) {
+                matches.push((len, distance));
+            }
+        }
+        matches
+    }
+}
@@ -0,0 +1,81 @@
+use alloc::{vec, vec::Vec};
+
+use super::{match_length, MatchFinder};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BruteForce;
+
+impl MatchFinder for BruteForce {
+    fn insert(&mut self, _input: &[u8], _pos: usize) {}
+
+    fn find(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Option<(usize, usize)> {
+        self.find_all(input, pos, max_length, max_distance).pop()
+    }
+
+    fn find_all(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        let mut len = 0;
+        let start = pos.saturating_sub(max_distance);
+        for current in (start..pos).rev() {
+            let longest = match_length(input, current, pos, max_length);
+            if longest > len {
+                len = longest;
+                matches.push((len, pos - current));
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_test() {
+        let mut finder = BruteForce;
+        assert_eq!(None, finder.find(b"", 0, 258, 32768));
+        assert_eq!(None, finder.find(b"abc", 1, 258, 32768));
+    }
+
+    #[test]
+    fn test_nearest_longest_match() {
+        let mut finder = BruteForce;
+        let input = b"Fa-la-la-la";
+        assert_eq!(Some((7, 3)), finder.find(input, 4, 258, 32768));
+        assert_eq!(Some((6, 3)), finder.find(input, 5, 258, 32768));
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut finder = BruteForce;
+        let input = [b'a'; 300];
+        assert_eq!(Some((258, 1)), finder.find(&input, 1, 258, 32768));
+        assert_eq!(Some((5, 1)), finder.find(&input, 10, 5, 32768));
+        let input = b"abcdxyzabcd";
+        assert_eq!(None, finder.find(input, 7, 258, 3));
+        assert_eq!(Some((4, 7)), finder.find(input, 7, 258, 7));
+    }
+
+    #[test]
+    fn test_find_all() {
+        let mut finder = BruteForce;
+        let input = b"abcdeXabcYabcde";
+        assert_eq!(
+            vec![(3, 4), (5, 10)],
+            finder.find_all(input, 10, 258, 32768)
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use alloc::vec::Vec;
+
+pub mod bintree;
+pub mod brute;
+
+pub use bintree::BinaryTree;
+pub use brute::BruteForce;
+
+pub trait MatchFinder {
+    fn insert(&mut self, input: &[u8], pos: usize);
+
+    fn find(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Option<(usize, usize)>;
+
+    fn find_all(
+        &mut self,
+        input: &[u8],
+        pos: usize,
+        max_length: usize,
+        max_distance: usize,
+    ) -> Vec<(usize, usize)> {
+        self.find(input, pos, max_length, max_distance)
+            .into_iter()
+            .collect()
+    }
+}
+
+pub fn match_length(input: &[u8], x: usize, y: usize, max_length: usize) -> usize {
+    assert!(x < y);
+    let limit = max_length.min(input.len() - y);
+    let mut len = 0;
+    while len < limit && input[x + len] == input[y + len] {
+        len += 1;
+    }
+    len
+}
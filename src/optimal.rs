@@ -0,0 +1,291 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::{
+    bitstream::ostream::OutputStream,
+    deflate::{
+        DeflateToken, Variant, BFINAL_NO, BFINAL_YES, BTYPE_DYNAMIC, BTYPE_FIXED, CONVERT_DISTANCE,
+        CONVERT_LENGTH, END_OF_BLOCK, MAX_DISTANCE, MAX_SEQUENCE, MIN_SEQUENCE,
+    },
+    huffman::{
+        dynamic::{DynamicTables, D_CODES, LL_CODES},
+        generate::MAX_BITS,
+    },
+    matcher::{BinaryTree, MatchFinder},
+};
+
+pub const BLOCK_SIZE: usize = 1 << 16;
+pub const DEFAULT_ITERATIONS: usize = 15;
+
+pub fn deflate_optimal(input: &[u8]) -> Vec<DeflateToken> {
+    optimal_parse(input, BinaryTree::new(), DEFAULT_ITERATIONS)
+}
+
+pub fn optimal_parse<M: MatchFinder>(
+    input: &[u8],
+    mut finder: M,
+    iterations: usize,
+) -> Vec<DeflateToken> {
+    use DeflateToken::*;
+    if input.is_empty() {
+        return vec![Bhead(BFINAL_YES), Btype(BTYPE_FIXED), EndOfBlock];
+    }
+    let mut output = vec![];
+    for start in (0..input.len()).step_by(BLOCK_SIZE) {
+        let end = (start + BLOCK_SIZE).min(input.len());
+        let matches = (start..end)
+            .map(|pos| {
+                let found = finder.find_all(input, pos, MAX_SEQUENCE, MAX_DISTANCE);
+                finder.insert(input, pos);
+                found
+            })
+            .collect::<Vec<_>>();
+        let (tables, tokens) = parse_block(input, &matches, start, end, iterations);
+        let bfinal = if end == input.len() {
+            BFINAL_YES
+        } else {
+            BFINAL_NO
+        };
+        output.push(Bhead(bfinal));
+        if let Some(tables) = tables {
+            output.push(Btype(BTYPE_DYNAMIC));
+            output.push(Tables(Box::new(tables)));
+        } else {
+            output.push(Btype(BTYPE_FIXED));
+        }
+        output.extend(tokens);
+        output.push(EndOfBlock);
+    }
+    output
+}
+
+fn parse_block(
+    input: &[u8],
+    matches: &[Vec<(usize, usize)>],
+    start: usize,
+    end: usize,
+    iterations: usize,
+) -> (Option<DynamicTables>, Vec<DeflateToken>) {
+    let fixed_model = CostModel::fixed();
+    let fixed_tokens = shortest_path(input, matches, start, end, &fixed_model);
+    let fixed_cost = fixed_model.cost(&fixed_tokens);
+    let mut tokens = fixed_tokens.clone();
+    let mut best: Option<(usize, DynamicTables, Vec<DeflateToken>)> = None;
+    for _ in 0..iterations.max(1) {
+        let tables = DynamicTables::from_tokens(
+            tokens.iter().chain([&DeflateToken::EndOfBlock]),
+            Variant::Deflate,
+        );
+        let cost = block_cost(&tables, &tokens);
+        if best
+            .as_ref()
+            .is_some_and(|(best_cost, _, _)| *best_cost <= cost)
+        {
+            break;
+        }
+        let model = CostModel::from_tables(&tables);
+        best = Some((cost, tables, tokens));
+        tokens = shortest_path(input, matches, start, end, &model);
+    }
+    match best {
+        Some((cost, tables, tokens)) if cost < fixed_cost => (Some(tables), tokens),
+        _ => (None, fixed_tokens),
+    }
+}
+
+fn shortest_path(
+    input: &[u8],
+    matches: &[Vec<(usize, usize)>],
+    start: usize,
+    end: usize,
+    model: &CostModel,
+) -> Vec<DeflateToken> {
+    let n = end - start;
+    let mut cost = vec![usize::MAX; n + 1];
+    let mut step = vec![(0, 0); n + 1];
+    cost[0] = 0;
+    for i in 0..n {
+        let literal = cost[i] + model.ll[input[start + i] as usize];
+        if literal < cost[i + 1] {
+            cost[i + 1] = literal;
+            step[i + 1] = (1, 0);
+        }
+        let mut shortest = MIN_SEQUENCE;
+        for (len, distance) in &matches[i] {
+            let len = (*len).min(n - i);
+            let distance_cost = model.distance(*distance);
+            for l in shortest..=len {
+                let backref = cost[i] + model.length[l] + distance_cost;
+                if backref < cost[i + l] {
+                    cost[i + l] = backref;
+                    step[i + l] = (l, *distance);
+                }
+            }
+            shortest = shortest.max(len + 1);
+        }
+    }
+    let mut tokens = vec![];
+    let mut pos = n;
+    while pos > 0 {
+        let (len, distance) = step[pos];
+        pos -= len;
+        if distance == 0 {
+            tokens.push(DeflateToken::Literal(input[start + pos] as _));
+        } else {
+            tokens.push(DeflateToken::Backref {
+                length: len as _,
+                distance: distance as _,
+            });
+        }
+    }
+    tokens.reverse();
+    tokens
+}
+
+fn block_cost(tables: &DynamicTables, tokens: &[DeflateToken]) -> usize {
+    let mut os = OutputStream::default();
+    tables.write_header(&mut os);
+    os.bits_written() + CostModel::from_tables(tables).cost(tokens)
+}
+
+#[derive(Debug)]
+struct CostModel {
+    ll: Vec<usize>,
+    d: Vec<usize>,
+    length: Vec<usize>,
+}
+
+impl CostModel {
+    fn fixed() -> Self {
+        let ll = (0..LL_CODES)
+            .map(|symbol| match symbol {
+                0..=143 => 8,
+                144..=255 => 9,
+                256..=279 => 7,
+                _ => 8,
+            })
+            .collect::<Vec<_>>();
+        Self::new(ll, vec![5; D_CODES])
+    }
+
+    fn from_tables(tables: &DynamicTables) -> Self {
+        let cost = |len: &u8| if *len > 0 { *len as usize } else { MAX_BITS };
+        Self::new(
+            tables.ll_lengths.iter().map(cost).collect(),
+            tables.d_lengths.iter().map(cost).collect(),
+        )
+    }
+
+    fn new(ll: Vec<usize>, d: Vec<usize>) -> Self {
+        let mut length = vec![0; MAX_SEQUENCE + 1];
+        for (len, cost) in length.iter_mut().enumerate().skip(MIN_SEQUENCE) {
+            let (token, extra, _) = CONVERT_LENGTH[len];
+            *cost = ll[token] + extra;
+        }
+        Self { ll, d, length }
+    }
+
+    fn cost(&self, tokens: &[DeflateToken]) -> usize {
+        let data = tokens
+            .iter()
+            .map(|token| match token {
+                DeflateToken::Literal(literal) => self.ll[*literal as usize],
+                DeflateToken::Backref { length, distance } => {
+                    self.length[*length as usize] + self.distance(*distance as usize)
+                }
+                _ => 0,
+            })
+            .sum::<usize>();
+        data + self.ll[END_OF_BLOCK]
+    }
+
+    fn distance(&self, distance: usize) -> usize {
+        let (token, extra, _) = CONVERT_DISTANCE[distance];
+        self.d[token] + extra
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deflate::Deflator, test_util::sample};
+
+    fn replay(tokens: &[DeflateToken]) -> Vec<u8> {
+        let mut output: Vec<u8> = vec![];
+        for token in tokens {
+            match token {
+                DeflateToken::Literal(literal) => output.push(*literal as _),
+                DeflateToken::Backref { length, distance } => {
+                    for _ in 0..*length {
+                        output.push(output[output.len() - *distance as usize]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(
+            vec![
+                DeflateToken::Bhead(BFINAL_YES),
+                DeflateToken::Btype(BTYPE_FIXED),
+                DeflateToken::EndOfBlock
+            ],
+            deflate_optimal(b"")
+        );
+    }
+
+    #[test]
+    fn test_short_input_uses_fixed_block() {
+        let tokens = deflate_optimal(b"Fa-la-la-la");
+        assert_eq!(DeflateToken::Btype(BTYPE_FIXED), tokens[1]);
+        assert_eq!(b"Fa-la-la-la".to_vec(), replay(&tokens));
+    }
+
+    #[test]
+    fn test_tokens_reproduce_input() {
+        let input = sample(1_300);
+        let tokens = deflate_optimal(&input);
+        assert_eq!(input, replay(&tokens));
+        assert_eq!(DeflateToken::Btype(BTYPE_DYNAMIC), tokens[1]);
+        assert_eq!(Some(&DeflateToken::EndOfBlock), tokens.last());
+    }
+
+    #[test]
+    fn test_not_worse_than_greedy() {
+        let input = sample(1_300);
+        let optimal = deflate_optimal(&input);
+        let mut os = OutputStream::default();
+        os.extend(optimal.into_iter());
+        let optimal_size = os.finalize().len();
+        let greedy = Deflator::with_finder(&input, BinaryTree::new()).collect::<Vec<_>>();
+        let tables = DynamicTables::from_tokens(
+            greedy.iter().chain([&DeflateToken::EndOfBlock]),
+            Variant::Deflate,
+        );
+        assert!(optimal_size * 8 <= block_cost(&tables, &greedy) + 3);
+    }
+
+    #[test]
+    fn test_blocks_share_the_window() {
+        let input = sample(5_000).repeat(6);
+        let tokens = optimal_parse(&input, BinaryTree::new(), 1);
+        assert_eq!(input, replay(&tokens));
+        let heads = tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| matches!(token, DeflateToken::Bhead(_)))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        assert_eq!(input.len().div_ceil(BLOCK_SIZE), heads.len());
+        let first = tokens[heads[1]..].iter().find(|token| {
+            matches!(
+                token,
+                DeflateToken::Literal(_) | DeflateToken::Backref { .. }
+            )
+        });
+        assert!(matches!(first, Some(DeflateToken::Backref { .. })));
+    }
+}
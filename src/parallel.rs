@@ -0,0 +1,174 @@
+use std::thread;
+
+use crc32fast::Hasher;
+
+use crate::{
+    bitstream::ostream::OutputStream,
+    deflate::Strategy,
+    encoder::Encoder,
+    gzip::{self, GzipHeader},
+};
+
+pub const CHUNK_SIZE: usize = 128 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ParallelEncoder {
+    chunk_size: usize,
+    threads: usize,
+    encoder: Encoder,
+}
+
+impl Default for ParallelEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParallelEncoder {
+    pub fn new() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            chunk_size: CHUNK_SIZE,
+            threads,
+            encoder: Encoder::new(),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.encoder = self.encoder.with_strategy(strategy);
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn deflate(&self, input: &[u8]) -> Vec<u8> {
+        self.compress(input).0
+    }
+
+    pub fn gzip(&self, input: &[u8], header: &GzipHeader) -> Vec<u8> {
+        let (deflated, crc) = self.compress(input);
+        let mut output = header.to_bytes();
+        output.extend(deflated);
+        output.extend(gzip::trailer(crc, input.len() as u32));
+        output
+    }
+
+    fn compress(&self, input: &[u8]) -> (Vec<u8>, u32) {
+        let starts = (0..input.len().max(1))
+            .step_by(self.chunk_size)
+            .collect::<Vec<_>>();
+        let mut output = vec![];
+        let mut crc = Hasher::new();
+        for batch in starts.chunks(self.threads) {
+            let compressed = thread::scope(|scope| {
+                let workers = batch
+                    .iter()
+                    .map(|start| {
+                        let end = (start + self.chunk_size).min(input.len());
+                        let encoder = self.encoder;
+                        scope.spawn(move || compress_chunk(&encoder, input, *start, end))
+                    })
+                    .collect::<Vec<_>>();
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join().unwrap())
+                    .collect::<Vec<_>>()
+            });
+            for (chunk, hasher) in compressed {
+                output.extend(chunk);
+                crc.combine(&hasher);
+            }
+        }
+        (output, crc.finalize())
+    }
+}
+
+fn compress_chunk(encoder: &Encoder, input: &[u8], start: usize, end: usize) -> (Vec<u8>, Hasher) {
+    let mut hasher = Hasher::new();
+    hasher.update(&input[start..end]);
+    let dictionary_start = start.saturating_sub(encoder.variant().max_distance());
+    let window = &input[dictionary_start..end];
+    let last = end == input.len();
+    let mut os = OutputStream::default().with_variant(encoder.variant());
+    encoder.deflate_into(&mut os, window, start - dictionary_start, last);
+    if !last {
+        os.sync_flush();
+    }
+    (os.finalize(), hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::{gzip::GzipDecoder, inflate::inflate, test_util::sample};
+
+    fn count_sync_markers(data: &[u8]) -> usize {
+        data.windows(4).filter(|w| *w == [0, 0, 0xff, 0xff]).count()
+    }
+
+    #[test]
+    fn trivial_test() {
+        let output = ParallelEncoder::new().deflate(b"");
+        assert_eq!(vec![0b0000_0011, 0], output);
+    }
+
+    #[test]
+    fn test_chunks_end_with_sync_flush() {
+        let input = sample(50_000);
+        let encoder = ParallelEncoder::new()
+            .with_chunk_size(32 * 1024)
+            .with_threads(3);
+        let output = encoder.deflate(&input);
+        let chunks = input.len().div_ceil(32 * 1024);
+        assert!(count_sync_markers(&output) >= chunks - 1);
+        assert_eq!(output, encoder.with_threads(1).deflate(&input));
+    }
+
+    #[test]
+    fn test_gzip_trailer_combines_crc() {
+        let input = sample(50_000);
+        let output = ParallelEncoder::new()
+            .with_chunk_size(10_000)
+            .gzip(&input, &GzipHeader::default());
+        assert_eq!(GzipHeader::default().to_bytes(), output[..10]);
+        let trailer = gzip::trailer(crc32fast::hash(&input), input.len() as u32);
+        assert_eq!(trailer, output[output.len() - 8..]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sample = sample(40_000);
+        for chunk_size in [1_000, 4_096, 32 * 1024 + 1] {
+            for threads in [1, 2, 3, 8] {
+                let encoder = ParallelEncoder::new()
+                    .with_chunk_size(chunk_size)
+                    .with_threads(threads);
+                for len in [0, chunk_size / 2, chunk_size * 3, chunk_size * 3 + 1] {
+                    let input = &sample[..len];
+                    let context = format!("chunk {chunk_size}, threads {threads}, len {len}");
+                    assert_eq!(
+                        input,
+                        inflate(&encoder.deflate(input)).unwrap(),
+                        "{context}"
+                    );
+                    let gzip = encoder.gzip(input, &GzipHeader::default());
+                    let mut output = vec![];
+                    GzipDecoder::new(gzip.as_slice())
+                        .read_to_end(&mut output)
+                        .unwrap();
+                    assert_eq!(input, output, "{context}");
+                }
+            }
+        }
+    }
+}
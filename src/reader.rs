@@ -0,0 +1,190 @@
+use std::io::{self, Read};
+
+use crate::{
+    bitstream::ostream::OutputStream,
+    encoder::{Encoder, BLOCK_SIZE},
+};
+
+#[derive(Debug)]
+pub struct DeflateEncoderReader<R: Read> {
+    inner: R,
+    encoder: Encoder,
+    os: OutputStream,
+    buffer: Vec<u8>,
+    dictionary_len: usize,
+    pending: Vec<u8>,
+    pos: usize,
+    total_in: u64,
+    total_out: u64,
+    finished: bool,
+}
+
+impl<R: Read> DeflateEncoderReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_encoder(inner, Encoder::new())
+    }
+
+    pub fn with_encoder(inner: R, encoder: Encoder) -> Self {
+        Self {
+            inner,
+            encoder,
+            os: OutputStream::default().with_variant(encoder.variant()),
+            buffer: vec![],
+            dictionary_len: 0,
+            pending: vec![],
+            pos: 0,
+            total_in: 0,
+            total_out: 0,
+            finished: false,
+        }
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let target = self.dictionary_len + BLOCK_SIZE;
+        while self.buffer.len() < target {
+            let len = self.buffer.len();
+            self.buffer.resize(target, 0);
+            let result = self.inner.read(&mut self.buffer[len..]);
+            let n = *result.as_ref().unwrap_or(&0);
+            self.buffer.truncate(len + n);
+            self.total_in += n as u64;
+            match result {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let last = self.buffer.len() < target;
+        self.encoder
+            .deflate_into(&mut self.os, &self.buffer, self.dictionary_len, last);
+        if last {
+            self.os.align();
+            self.finished = true;
+        }
+        let window = self.encoder.variant().max_distance();
+        let keep = self.buffer.len().saturating_sub(window);
+        self.buffer.drain(..keep);
+        self.dictionary_len = self.buffer.len();
+        self.pending = self.os.take_bytes();
+        self.pos = 0;
+        self.total_out += self.pending.len() as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DeflateEncoderReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.pending.len() && !self.finished {
+            self.fill()?;
+        }
+        let n = (self.pending.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deflate::Variant,
+        inflate::{inflate, inflate_with},
+        test_util::sample,
+    };
+
+    struct Trickle<'a> {
+        data: &'a [u8],
+        calls: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(3) {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let n = self.data.len().min(buf.len()).min(4099);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn trivial_test() {
+        let mut output = vec![];
+        DeflateEncoderReader::new(&b""[..])
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(vec![0b0000_0011, 0], output);
+    }
+
+    #[test]
+    fn test_small_reads() {
+        let input = sample(200_000);
+        let mut reader = DeflateEncoderReader::new(Trickle {
+            data: &input,
+            calls: 0,
+        });
+        let mut output = vec![];
+        let mut buf = [0; 777];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(input.len() as u64, reader.total_in());
+        assert_eq!(output.len() as u64, reader.total_out());
+        assert_eq!(input, inflate(&output).unwrap());
+    }
+
+    #[test]
+    fn test_deflate64() {
+        let input = sample(200_000);
+        let encoder = Encoder::new().with_variant(Variant::Deflate64);
+        let mut output = vec![];
+        DeflateEncoderReader::with_encoder(input.as_slice(), encoder)
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(input, inflate_with(&output, Variant::Deflate64).unwrap());
+    }
+
+    #[test]
+    fn test_read_error() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken pipe"))
+            }
+        }
+        let mut output = vec![];
+        let err = DeflateEncoderReader::new(Broken)
+            .read_to_end(&mut output)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::Other, err.kind());
+    }
+}
@@ -0,0 +1,413 @@
+use std::io::{self, Cursor};
+
+use crate::{
+    bitstream::ostream::OutputStream,
+    compressor::State,
+    decompressor::Decompressor,
+    detect::{self, Format},
+    gzip::{self, GzipHeader},
+    optimal::deflate_optimal,
+    zip::{
+        read::find_end_of_central_directory, CompressionMethod, ZipArchive, ZipWriter,
+        LOCAL_FILE_HEADER_SIGNATURE,
+    },
+    zlib,
+};
+
+pub const CHUNK_SIZE: usize = 1 << 15;
+
+const FLAG_ENCRYPTED: u16 = 1;
+const ZLIB_TRAILER_SIZE: usize = 4;
+const GZIP_TRAILER_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Container {
+    Raw,
+    Zlib,
+    Gzip,
+    Zip,
+}
+
+impl From<Format> for Container {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Raw => Container::Raw,
+            Format::Zlib => Container::Zlib,
+            Format::Gzip => Container::Gzip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recompressed {
+    pub container: Container,
+    pub data: Vec<u8>,
+    pub streams: usize,
+    pub replaced: usize,
+}
+
+pub fn detect_container(input: &[u8]) -> Option<Container> {
+    if input.starts_with(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes())
+        || find_end_of_central_directory(&mut Cursor::new(input)).is_ok()
+    {
+        return Some(Container::Zip);
+    }
+    detect::detect(input).map(Container::from)
+}
+
+pub fn recompress(input: &[u8]) -> io::Result<Recompressed> {
+    let container = detect_container(input).ok_or(io::ErrorKind::UnexpectedEof)?;
+    recompress_as(input, container)
+}
+
+pub fn recompress_as(input: &[u8], container: Container) -> io::Result<Recompressed> {
+    let mut result = Recompressed {
+        container,
+        data: vec![],
+        streams: 0,
+        replaced: 0,
+    };
+    match container {
+        Container::Raw => result.raw(input)?,
+        Container::Zlib => result.zlib(input)?,
+        Container::Gzip => result.gzip(input)?,
+        Container::Zip => result.zip(input)?,
+    }
+    Ok(result)
+}
+
+impl Recompressed {
+    fn raw(&mut self, input: &[u8]) -> io::Result<()> {
+        let (_, consumed) = self.stream(input)?;
+        self.data.extend_from_slice(&input[consumed..]);
+        Ok(())
+    }
+
+    fn zlib(&mut self, input: &[u8]) -> io::Result<()> {
+        let header = self.copy(input, 0, 2)?;
+        zlib::check_header([header[0], header[1]])?;
+        let (decoded, consumed) = self.stream(&input[2..])?;
+        let end = 2 + consumed;
+        if self.copy(input, end, ZLIB_TRAILER_SIZE)? != zlib::trailer(zlib::adler32(&decoded)) {
+            return Err(invalid("zlib checksum mismatch"));
+        }
+        self.data
+            .extend_from_slice(&input[end + ZLIB_TRAILER_SIZE..]);
+        Ok(())
+    }
+
+    fn gzip(&mut self, input: &[u8]) -> io::Result<()> {
+        let mut pos = 0;
+        while pos < input.len() {
+            let mut reader = &input[pos..];
+            GzipHeader::read_from(&mut reader)?;
+            let body = input.len() - reader.len();
+            self.data.extend_from_slice(&input[pos..body]);
+            let (decoded, consumed) = self.stream(&input[body..])?;
+            let trailer = gzip::trailer(crc32fast::hash(&decoded), decoded.len() as u32);
+            if self.copy(input, body + consumed, GZIP_TRAILER_SIZE)? != trailer {
+                return Err(invalid("gzip trailer mismatch"));
+            }
+            pos = body + consumed + GZIP_TRAILER_SIZE;
+        }
+        Ok(())
+    }
+
+    fn zip(&mut self, input: &[u8]) -> io::Result<()> {
+        let mut archive = ZipArchive::new(Cursor::new(input))?;
+        if !archive.is_empty() && !input.starts_with(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes()) {
+            return Err(invalid("zip archive has leading data"));
+        }
+        let mut entries = vec![];
+        for index in 0..archive.len() {
+            let entry = archive.entries()[index].clone();
+            let local_extra = archive.local_extra(index)?;
+            let raw = archive.raw_data(index)?;
+            if entry.method != CompressionMethod::Deflated || entry.flags & FLAG_ENCRYPTED != 0 {
+                entries.push((entry, local_extra, raw));
+                continue;
+            }
+            let (decoded, _) = inflate_prefix(&raw)?;
+            if decoded.len() as u64 != entry.uncompressed_size
+                || crc32fast::hash(&decoded) != entry.crc
+            {
+                return Err(invalid("zip entry does not match its checksum"));
+            }
+            let payload = self.reencode(&raw, &decoded);
+            entries.push((entry, local_extra, payload));
+        }
+        if self.replaced == 0 {
+            self.data = input.to_vec();
+            return Ok(());
+        }
+        let mut writer = ZipWriter::new(vec![]);
+        writer.set_raw_comment(archive.comment());
+        for (entry, local_extra, payload) in &entries {
+            writer.add_raw_with_local_extra(entry, local_extra, payload)?;
+        }
+        self.data = writer.finish()?;
+        Ok(())
+    }
+
+    fn stream(&mut self, input: &[u8]) -> io::Result<(Vec<u8>, usize)> {
+        let (decoded, consumed) = inflate_prefix(input)?;
+        let payload = self.reencode(&input[..consumed], &decoded);
+        self.data.extend(payload);
+        Ok((decoded, consumed))
+    }
+
+    fn reencode(&mut self, original: &[u8], decoded: &[u8]) -> Vec<u8> {
+        self.streams += 1;
+        let mut os = OutputStream::default();
+        os.extend(deflate_optimal(decoded).into_iter());
+        let candidate = os.finalize();
+        if candidate.len() < original.len() {
+            self.replaced += 1;
+            candidate
+        } else {
+            original.to_vec()
+        }
+    }
+
+    fn copy<'a>(&mut self, input: &'a [u8], start: usize, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = input
+            .get(start..start + len)
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        self.data.extend_from_slice(bytes);
+        Ok(bytes)
+    }
+}
+
+fn inflate_prefix(input: &[u8]) -> io::Result<(Vec<u8>, usize)> {
+    let mut decompressor = Decompressor::new();
+    let mut output = vec![];
+    let mut consumed = 0;
+    loop {
+        let len = output.len();
+        output.resize(len + CHUNK_SIZE, 0);
+        let status = decompressor.decompress(&input[consumed..], &mut output[len..])?;
+        consumed += status.consumed;
+        output.truncate(len + status.produced);
+        match status.state {
+            State::Done => return Ok((output, consumed)),
+            State::NeedsInput => return Err(io::ErrorKind::UnexpectedEof.into()),
+            State::NeedsOutput => {}
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::{
+        deflate::Strategy,
+        encoder::Encoder,
+        inflate::inflate,
+        test_util::sample,
+        zip::{DateTime, FileOptions, ZipEntry},
+    };
+
+    fn weak(data: &[u8]) -> Vec<u8> {
+        Encoder::new()
+            .with_strategy(Strategy::HuffmanOnly)
+            .deflate(data)
+    }
+
+    fn gzip_member(name: &[u8], data: &[u8]) -> Vec<u8> {
+        let header = GzipHeader {
+            name: Some(name.to_vec()),
+            mtime: 1_234_567_890,
+            ..Default::default()
+        };
+        let mut member = header.to_bytes();
+        member.extend(weak(data));
+        member.extend(gzip::trailer(crc32fast::hash(data), data.len() as u32));
+        member
+    }
+
+    #[test]
+    fn trivial_test() {
+        let result = recompress(&[3, 0]).unwrap();
+        assert_eq!(Container::Raw, result.container);
+        assert_eq!(vec![3, 0], result.data);
+        assert_eq!((1, 0), (result.streams, result.replaced));
+        assert!(recompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_raw_and_zlib() {
+        let input = sample(5_000);
+        let raw = weak(&input);
+        let result = recompress(&raw).unwrap();
+        assert_eq!(1, result.replaced);
+        assert!(result.data.len() < raw.len());
+        assert_eq!(input, inflate(&result.data).unwrap());
+        let mut zlib = zlib::HEADER.to_vec();
+        zlib.extend(&raw);
+        zlib.extend(zlib::trailer(zlib::adler32(&input)));
+        let result = recompress(&zlib).unwrap();
+        assert_eq!(Container::Zlib, result.container);
+        assert_eq!(zlib[..2], result.data[..2]);
+        assert_eq!(zlib[zlib.len() - 4..], result.data[result.data.len() - 4..]);
+        assert_eq!(input, inflate(&result.data[2..]).unwrap());
+        let again = recompress(&result.data).unwrap();
+        assert_eq!(0, again.replaced);
+        assert_eq!(result.data, again.data);
+        let last = zlib.len() - 1;
+        zlib[last] ^= 1;
+        assert!(recompress(&zlib).is_err());
+    }
+
+    #[test]
+    fn test_gzip_members_keep_headers() {
+        let input = sample(5_000);
+        let mut gzip = gzip_member(b"first.txt", &input);
+        gzip.extend(gzip_member(b"second.txt", b"Fa-la-la-la"));
+        let result = recompress(&gzip).unwrap();
+        assert_eq!(Container::Gzip, result.container);
+        assert_eq!((2, 2), (result.streams, result.replaced));
+        assert!(result.data.len() < gzip.len());
+        let mut reader = result.data.as_slice();
+        let header = GzipHeader::read_from(&mut reader).unwrap();
+        assert_eq!(Some(&b"first.txt"[..]), header.filename());
+        assert_eq!(1_234_567_890, header.mtime);
+        let mut decoder = crate::flate2::read::MultiGzDecoder::new(result.data.as_slice());
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).unwrap();
+        assert_eq!([input, b"Fa-la-la-la".to_vec()].concat(), output);
+    }
+
+    #[test]
+    fn test_zip_keeps_attributes() {
+        let input = sample(5_000);
+        let modified = DateTime {
+            year: 2020,
+            month: 2,
+            day: 29,
+            hour: 12,
+            minute: 30,
+            second: 14,
+        };
+        let mut zip = ZipWriter::new(vec![]);
+        let options = FileOptions::new()
+            .encoder(Encoder::new().with_strategy(Strategy::HuffmanOnly))
+            .last_modified(modified)
+            .unix_permissions(0o750);
+        zip.add_file("numbers.txt", &input, options).unwrap();
+        zip.add_file(
+            "stored.bin",
+            &[1, 2, 3],
+            FileOptions::new().compression_method(CompressionMethod::Stored),
+        )
+        .unwrap();
+        zip.set_comment("archive comment");
+        let archive = zip.finish().unwrap();
+        let result = recompress(&archive).unwrap();
+        assert_eq!(Container::Zip, result.container);
+        assert_eq!((1, 1), (result.streams, result.replaced));
+        assert!(result.data.len() < archive.len());
+        let before = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut after = ZipArchive::new(Cursor::new(result.data)).unwrap();
+        assert_eq!(b"archive comment", after.comment());
+        for (old, new) in before.entries().iter().zip(after.entries()) {
+            assert_eq!(old.name, new.name);
+            assert_eq!(old.modified, new.modified);
+            assert_eq!(old.crc, new.crc);
+            assert_eq!(old.external_attributes, new.external_attributes);
+        }
+        let mut content = vec![];
+        after
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(input, content);
+    }
+
+    #[test]
+    fn test_zip_keeps_entry_metadata() {
+        let input = sample(5_000);
+        let mut extra = vec![0x55, 0x54, 5, 0, 1];
+        extra.extend(1_600_000_000_u32.to_le_bytes());
+        extra.extend([0x75, 0x78, 11, 0, 1, 4]);
+        extra.extend(1000_u32.to_le_bytes());
+        extra.push(4);
+        extra.extend(100_u32.to_le_bytes());
+        let text = ZipEntry {
+            name: String::from_utf8_lossy(b"caf\x82.txt").into_owned(),
+            raw_name: b"caf\x82.txt".to_vec(),
+            method: CompressionMethod::Deflated,
+            modified: DateTime::default(),
+            crc: crc32fast::hash(&input),
+            compressed_size: 0,
+            uncompressed_size: input.len() as u64,
+            version_made_by: 0,
+            flags: 0,
+            internal_attributes: 1,
+            external_attributes: 0x20,
+            header_offset: 0,
+            extra,
+            comment: b"file comment".to_vec(),
+        };
+        let bzip2 = ZipEntry {
+            name: "data.bz2".to_string(),
+            raw_name: b"data.bz2".to_vec(),
+            method: CompressionMethod::Other(12),
+            extra: vec![],
+            comment: vec![],
+            ..text.clone()
+        };
+        let local_extra = text.extra[..9].to_vec();
+        let mut zip = ZipWriter::new(vec![]);
+        zip.add_raw_with_local_extra(&text, &local_extra, &weak(&input))
+            .unwrap();
+        zip.add_raw(&bzip2, b"BZh91AY&SY").unwrap();
+        let archive = zip.finish().unwrap();
+        let result = recompress(&archive).unwrap();
+        assert_eq!((1, 1), (result.streams, result.replaced));
+        let mut after = ZipArchive::new(Cursor::new(result.data)).unwrap();
+        assert_eq!(local_extra, after.local_extra(0).unwrap());
+        for (expected, entry) in [text, bzip2].iter().zip(after.entries()) {
+            assert_eq!(
+                ZipEntry {
+                    compressed_size: entry.compressed_size,
+                    header_offset: entry.header_offset,
+                    ..expected.clone()
+                },
+                *entry
+            );
+        }
+        assert_eq!(b"BZh91AY&SY".to_vec(), after.raw_data(1).unwrap());
+        let error = after.by_index(1).unwrap_err();
+        assert_eq!(io::ErrorKind::Unsupported, error.kind());
+        let mut content = vec![];
+        after
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(input, content);
+    }
+
+    #[test]
+    fn test_zip_without_gains_is_unchanged() {
+        let mut zip = ZipWriter::new(vec![]);
+        zip.add_file("numbers.txt", &sample(5_000), FileOptions::new())
+            .unwrap();
+        let archive = zip.finish().unwrap();
+        let once = recompress(&archive).unwrap().data;
+        let twice = recompress(&once).unwrap();
+        assert_eq!((1, 0), (twice.streams, twice.replaced));
+        assert_eq!(once, twice.data);
+        let stub = [b"MZ self-extractor".to_vec(), archive].concat();
+        assert_eq!(Some(Container::Zip), detect_container(&stub));
+        let error = recompress(&stub).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+}
@@ -0,0 +1,13 @@
+use alloc::{format, string::ToString, vec::Vec};
+
+pub fn lines(count: u64) -> Vec<u8> {
+    (0..count)
+        .flat_map(|i| format!("{} {}\n", i, i * i % 1013).into_bytes())
+        .collect()
+}
+
+pub fn sample(count: u64) -> Vec<u8> {
+    (0..count)
+        .flat_map(|i| (i * i % 1013).to_string().into_bytes())
+        .collect()
+}
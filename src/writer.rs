@@ -0,0 +1,298 @@
+use std::io::{self, Write};
+
+use crate::{
+    bitstream::ostream::OutputStream,
+    compressor::State,
+    decompressor::Decompressor,
+    deflate::Variant,
+    encoder::{Encoder, BLOCK_SIZE},
+};
+
+pub const CHUNK_SIZE: usize = 1 << 15;
+
+#[derive(Debug)]
+pub struct DeflateWriter<W: Write> {
+    inner: W,
+    encoder: Encoder,
+    os: OutputStream,
+    buffer: Vec<u8>,
+    dictionary_len: usize,
+    total_in: u64,
+    total_out: u64,
+    finished: bool,
+}
+
+impl<W: Write> DeflateWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_encoder(inner, Encoder::new())
+    }
+
+    pub fn with_encoder(inner: W, encoder: Encoder) -> Self {
+        Self {
+            inner,
+            encoder,
+            os: OutputStream::default().with_variant(encoder.variant()),
+            buffer: vec![],
+            dictionary_len: 0,
+            total_in: 0,
+            total_out: 0,
+            finished: false,
+        }
+    }
+
+    pub fn with_dictionary(mut self, dictionary: &[u8]) -> Self {
+        let window = self.encoder.variant().max_distance();
+        self.buffer = dictionary[dictionary.len().saturating_sub(window)..].to_vec();
+        self.dictionary_len = self.buffer.len();
+        self
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.compress(true)?;
+            self.os.align();
+            self.write_out()?;
+            self.finished = true;
+        }
+        self.inner.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner)
+    }
+
+    fn compress(&mut self, last: bool) -> io::Result<()> {
+        if self.buffer.len() > self.dictionary_len || last {
+            self.encoder
+                .deflate_into(&mut self.os, &self.buffer, self.dictionary_len, last);
+            let window = self.encoder.variant().max_distance();
+            let keep = self.buffer.len().saturating_sub(window);
+            self.buffer.drain(..keep);
+            self.dictionary_len = self.buffer.len();
+        }
+        self.write_out()
+    }
+
+    fn write_out(&mut self) -> io::Result<()> {
+        let bytes = self.os.take_bytes();
+        self.total_out += bytes.len() as u64;
+        self.inner.write_all(&bytes)
+    }
+}
+
+impl<W: Write> Write for DeflateWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::other("deflate stream is already finished"));
+        }
+        self.buffer.extend_from_slice(buf);
+        self.total_in += buf.len() as u64;
+        if self.buffer.len() - self.dictionary_len >= BLOCK_SIZE {
+            self.compress(false)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.compress(false)?;
+            self.os.sync_flush();
+            self.write_out()?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinishStatus {
+    pub final_block: bool,
+    pub trailing: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct DeflateDecoderWriter<W: Write> {
+    inner: W,
+    decompressor: Decompressor,
+    buffer: Vec<u8>,
+    trailing: Vec<u8>,
+}
+
+impl<W: Write> DeflateDecoderWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_variant(inner, Variant::Deflate)
+    }
+
+    pub fn with_variant(inner: W, variant: Variant) -> Self {
+        Self {
+            inner,
+            decompressor: Decompressor::with_variant(variant),
+            buffer: vec![0; CHUNK_SIZE],
+            trailing: vec![],
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.decompressor.is_done()
+    }
+
+    pub fn total_in(&self) -> u64 {
+        self.decompressor.total_in()
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.decompressor.total_out()
+    }
+
+    pub fn trailing(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn try_finish(&mut self) -> io::Result<FinishStatus> {
+        self.inner.flush()?;
+        Ok(FinishStatus {
+            final_block: self.is_done(),
+            trailing: self.trailing.clone(),
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<(W, FinishStatus)> {
+        let status = self.try_finish()?;
+        Ok((self.inner, status))
+    }
+}
+
+impl<W: Write> Write for DeflateDecoderWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+        while !self.is_done() {
+            let status = self
+                .decompressor
+                .decompress(&buf[consumed..], &mut self.buffer)?;
+            consumed += status.consumed;
+            self.inner.write_all(&self.buffer[..status.produced])?;
+            if status.state == State::NeedsInput {
+                return Ok(consumed);
+            }
+        }
+        self.trailing.extend_from_slice(&buf[consumed..]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inflate::inflate, test_util::sample};
+
+    #[test]
+    fn trivial_test() {
+        let output = DeflateWriter::new(vec![]).finish().unwrap();
+        assert_eq!(vec![0b0000_0011, 0], output);
+    }
+
+    #[test]
+    fn test_small_writes() {
+        let input = sample(200_000);
+        let mut writer = DeflateWriter::new(vec![]);
+        for chunk in input.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(input.len() as u64, writer.total_in());
+        let output = writer.finish().unwrap();
+        assert_eq!(input, inflate(&output).unwrap());
+    }
+
+    #[test]
+    fn test_flush_makes_output_decodable() {
+        let mut writer = DeflateWriter::new(vec![]);
+        writer.write_all(b"Fa-la-la-la").unwrap();
+        writer.flush().unwrap();
+        let flushed = writer.get_ref().clone();
+        assert_eq!([0, 0, 0xff, 0xff], flushed[flushed.len() - 4..]);
+        writer.write_all(b", la-la-la-la!").unwrap();
+        let output = writer.finish().unwrap();
+        assert_eq!(flushed, output[..flushed.len()]);
+        assert_eq!(
+            b"Fa-la-la-la, la-la-la-la!".to_vec(),
+            inflate(&output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let dictionary = b"Fa-la-la-la, la-la-la-la! ".repeat(10);
+        let mut writer = DeflateWriter::new(vec![]).with_dictionary(&dictionary);
+        writer.write_all(b"Fa-la-la-la, la-la-la-la!").unwrap();
+        let primed = writer.finish().unwrap();
+        let mut writer = DeflateWriter::new(vec![]);
+        writer.write_all(b"Fa-la-la-la, la-la-la-la!").unwrap();
+        assert!(primed.len() < writer.finish().unwrap().len());
+        let empty = DeflateWriter::new(vec![]).with_dictionary(&dictionary);
+        assert_eq!(vec![3, 0], empty.finish().unwrap());
+    }
+
+    #[test]
+    fn test_decoder_writer() {
+        let input = sample(200_000);
+        let mut compressed = DeflateWriter::new(vec![]);
+        compressed.write_all(&input).unwrap();
+        let mut compressed = compressed.finish().unwrap();
+        let mut writer = DeflateDecoderWriter::new(vec![]);
+        for chunk in compressed.chunks(5) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert!(writer.is_done());
+        assert_eq!(compressed.len() as u64, writer.total_in());
+        compressed.extend_from_slice(b"tail");
+        writer.write_all(b"ta").unwrap();
+        writer.write_all(b"il").unwrap();
+        let (output, status) = writer.finish().unwrap();
+        assert_eq!(input, output);
+        assert!(status.final_block);
+        assert_eq!(b"tail".to_vec(), status.trailing);
+        let mut writer = DeflateDecoderWriter::new(vec![]);
+        writer.write_all(&compressed).unwrap();
+        assert_eq!(b"tail", writer.trailing());
+    }
+
+    #[test]
+    fn test_decoder_writer_incomplete() {
+        let mut writer = DeflateDecoderWriter::new(vec![]);
+        writer.write_all(&[0x73, 0x4b, 0xd4, 0xcd, 0x81]).unwrap();
+        let status = writer.try_finish().unwrap();
+        assert!(!status.final_block);
+        assert!(status.trailing.is_empty());
+        assert_eq!(b"Fa-l", writer.get_ref().as_slice());
+        let mut writer = DeflateDecoderWriter::new(vec![]);
+        assert!(writer.write_all(&[0xff, 0xff]).is_err());
+    }
+}
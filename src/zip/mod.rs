@@ -0,0 +1,158 @@
+pub mod read;
+pub mod write;
+
+use crate::deflate::Variant;
+
+pub use read::{ZipArchive, ZipEntry, ZipFile};
+pub use write::{FileOptions, ZipWriter};
+
+pub const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+pub const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+pub const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+pub const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+pub const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+pub const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+pub const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 56;
+pub const ZIP64_LOCATOR_SIZE: usize = 20;
+pub const ZIP64_LIMIT_16: u64 = u16::MAX as u64;
+pub const ZIP64_LIMIT_32: u64 = u32::MAX as u64;
+
+pub const VERSION_NEEDED: u16 = 20;
+pub const VERSION_DEFLATE64: u16 = 21;
+pub const VERSION_ZIP64: u16 = 45;
+pub const VERSION_MADE_BY_UNIX: u16 = 3 << 8 | VERSION_ZIP64;
+pub const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+pub const FLAG_UTF8: u16 = 1 << 11;
+
+pub const DEFAULT_FILE_MODE: u32 = 0o644;
+pub const DEFAULT_DIR_MODE: u32 = 0o755;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const MSDOS_DIRECTORY: u32 = 0x10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Stored,
+    #[default]
+    Deflated,
+    Deflate64,
+    Other(u16),
+}
+
+impl CompressionMethod {
+    pub fn to_u16(self) -> u16 {
+        match self {
+            CompressionMethod::Stored => 0,
+            CompressionMethod::Deflated => 8,
+            CompressionMethod::Deflate64 => 9,
+            CompressionMethod::Other(method) => method,
+        }
+    }
+
+    pub fn variant(self) -> Option<Variant> {
+        match self {
+            CompressionMethod::Deflated => Some(Variant::Deflate),
+            CompressionMethod::Deflate64 => Some(Variant::Deflate64),
+            _ => None,
+        }
+    }
+
+    pub fn from_u16(method: u16) -> Self {
+        match method {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflated,
+            9 => CompressionMethod::Deflate64,
+            method => CompressionMethod::Other(method),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Default for DateTime {
+    fn default() -> Self {
+        Self {
+            year: 1980,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        }
+    }
+}
+
+impl DateTime {
+    pub fn to_dos(&self) -> (u16, u16) {
+        let year = self.year.clamp(1980, 2107) - 1980;
+        let date = year << 9 | (self.month as u16) << 5 | self.day as u16;
+        let time = (self.hour as u16) << 11 | (self.minute as u16) << 5 | (self.second as u16 / 2);
+        (date, time)
+    }
+
+    pub fn from_dos(date: u16, time: u16) -> Self {
+        Self {
+            year: (date >> 9) + 1980,
+            month: (date >> 5 & 0xf) as u8,
+            day: (date & 0x1f) as u8,
+            hour: (time >> 11) as u8,
+            minute: (time >> 5 & 0x3f) as u8,
+            second: ((time & 0x1f) * 2) as u8,
+        }
+    }
+}
+
+pub(crate) fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+pub(crate) fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+pub(crate) fn u64_at(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dos_date_time() {
+        let date_time = DateTime {
+            year: 2023,
+            month: 5,
+            day: 17,
+            hour: 13,
+            minute: 45,
+            second: 31,
+        };
+        let (date, time) = date_time.to_dos();
+        assert_eq!(
+            (43 << 9 | 5 << 5 | 17, 13 << 11 | 45 << 5 | 15),
+            (date, time)
+        );
+        assert_eq!(
+            DateTime {
+                second: 30,
+                ..date_time
+            },
+            DateTime::from_dos(date, time)
+        );
+        assert_eq!((1 << 5 | 1, 0), DateTime::default().to_dos());
+    }
+}
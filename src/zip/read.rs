@@ -0,0 +1,625 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom, Take},
+    path::{Component, Path, PathBuf},
+};
+
+use crc32fast::Hasher;
+
+use super::{
+    u16_at, u32_at, u64_at, CompressionMethod, DateTime, CENTRAL_DIRECTORY_SIGNATURE,
+    END_OF_CENTRAL_DIRECTORY_SIGNATURE, END_OF_CENTRAL_DIRECTORY_SIZE, FLAG_UTF8,
+    LOCAL_FILE_HEADER_SIGNATURE, MSDOS_DIRECTORY, S_IFDIR,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE, ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE, ZIP64_EXTRA_ID,
+    ZIP64_LIMIT_16, ZIP64_LIMIT_32, ZIP64_LOCATOR_SIGNATURE, ZIP64_LOCATOR_SIZE,
+};
+use crate::inflate::Inflater;
+
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+const MAX_COMMENT_LEN: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    pub name: String,
+    pub raw_name: Vec<u8>,
+    pub method: CompressionMethod,
+    pub modified: DateTime,
+    pub crc: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub version_made_by: u16,
+    pub flags: u16,
+    pub internal_attributes: u16,
+    pub external_attributes: u32,
+    pub header_offset: u64,
+    pub extra: Vec<u8>,
+    pub comment: Vec<u8>,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/') || self.external_attributes & MSDOS_DIRECTORY > 0
+    }
+
+    pub fn unix_mode(&self) -> Option<u32> {
+        match self.version_made_by >> 8 {
+            3 => Some(self.external_attributes >> 16).filter(|mode| *mode > 0),
+            _ if self.is_dir() => Some(S_IFDIR | 0o755),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZipArchive<R: Read + Seek> {
+    reader: R,
+    entries: Vec<ZipEntry>,
+    comment: Vec<u8>,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let (record_pos, record, comment) = find_end_of_central_directory(&mut reader)?;
+        let (count, size, offset) = match read_zip64_record(&mut reader, record_pos)? {
+            Some(zip64) => zip64,
+            None => (
+                u16_at(&record, 10) as u64,
+                u32_at(&record, 12) as u64,
+                u32_at(&record, 16) as u64,
+            ),
+        };
+        if offset.checked_add(size).is_none_or(|end| end > record_pos) {
+            return Err(invalid("central directory is outside the archive"));
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut directory = vec![];
+        (&mut reader).take(size).read_to_end(&mut directory)?;
+        if directory.len() as u64 != size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut entries = Vec::with_capacity(count.min(ZIP64_LIMIT_16) as usize);
+        let mut pos = 0;
+        for _ in 0..count {
+            let (entry, len) = parse_central_directory_header(&directory[pos..])?;
+            entries.push(entry);
+            pos += len;
+        }
+        Ok(Self {
+            reader,
+            entries,
+            comment,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    pub fn by_name(&mut self, name: &str) -> io::Result<ZipFile<'_, R>> {
+        let index = self
+            .index_of(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, name.to_string()))?;
+        self.by_index(index)
+    }
+
+    pub fn by_index(&mut self, index: usize) -> io::Result<ZipFile<'_, R>> {
+        let entry = self.entry_at(index)?;
+        self.seek_data(&entry)?;
+        let data = (&mut self.reader).take(entry.compressed_size);
+        let reader = match (entry.method, entry.method.variant()) {
+            (_, Some(variant)) => {
+                EntryReader::Deflated(Box::new(Inflater::new(data).with_variant(variant)))
+            }
+            (CompressionMethod::Stored, None) => EntryReader::Stored(data),
+            _ => return Err(unsupported_method()),
+        };
+        Ok(ZipFile {
+            entry,
+            reader,
+            hasher: Hasher::new(),
+            read: 0,
+        })
+    }
+
+    pub fn raw_data(&mut self, index: usize) -> io::Result<Vec<u8>> {
+        let entry = self.entry_at(index)?;
+        self.seek_data(&entry)?;
+        let mut data = vec![];
+        (&mut self.reader)
+            .take(entry.compressed_size)
+            .read_to_end(&mut data)?;
+        if data.len() as u64 != entry.compressed_size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(data)
+    }
+
+    pub fn extract<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        for index in 0..self.len() {
+            let mut file = self.by_index(index)?;
+            let path = dir.as_ref().join(file.enclosed_name()?);
+            if file.entry().is_dir() {
+                fs::create_dir_all(&path)?;
+            } else {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut output = fs::File::create(&path)?;
+                io::copy(&mut file, &mut output)?;
+            }
+            #[cfg(unix)]
+            if let Some(mode) = file.entry().unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o7777))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_at(&self, index: usize) -> io::Result<ZipEntry> {
+        self.entries.get(index).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no entry at index {index}"),
+            )
+        })
+    }
+
+    pub fn local_extra(&mut self, index: usize) -> io::Result<Vec<u8>> {
+        let entry = self.entry_at(index)?;
+        let header = self.local_header(&entry)?;
+        self.reader
+            .seek(SeekFrom::Current(u16_at(&header, 26) as i64))?;
+        let mut extra = vec![0; u16_at(&header, 28) as usize];
+        self.reader.read_exact(&mut extra)?;
+        Ok(without_zip64(&extra))
+    }
+
+    fn seek_data(&mut self, entry: &ZipEntry) -> io::Result<()> {
+        let header = self.local_header(entry)?;
+        let skip = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+        self.reader.seek(SeekFrom::Current(skip))?;
+        Ok(())
+    }
+
+    fn local_header(&mut self, entry: &ZipEntry) -> io::Result<[u8; LOCAL_FILE_HEADER_SIZE]> {
+        self.reader.seek(SeekFrom::Start(entry.header_offset))?;
+        let mut header = [0; LOCAL_FILE_HEADER_SIZE];
+        self.reader.read_exact(&mut header)?;
+        if u32_at(&header, 0) != LOCAL_FILE_HEADER_SIGNATURE {
+            return Err(invalid("invalid local file header signature"));
+        }
+        Ok(header)
+    }
+}
+
+#[derive(Debug)]
+enum EntryReader<'a, R: Read> {
+    Stored(Take<&'a mut R>),
+    Deflated(Box<Inflater<Take<&'a mut R>>>),
+}
+
+#[derive(Debug)]
+pub struct ZipFile<'a, R: Read> {
+    entry: ZipEntry,
+    reader: EntryReader<'a, R>,
+    hasher: Hasher,
+    read: u64,
+}
+
+impl<'a, R: Read> ZipFile<'a, R> {
+    pub fn entry(&self) -> &ZipEntry {
+        &self.entry
+    }
+
+    pub fn enclosed_name(&self) -> io::Result<PathBuf> {
+        let mut path = PathBuf::new();
+        for component in Path::new(&self.entry.name).components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::CurDir => {}
+                _ => return Err(invalid("entry name escapes the target directory")),
+            }
+        }
+        Ok(path)
+    }
+}
+
+impl<'a, R: Read> Read for ZipFile<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = match self.reader {
+            EntryReader::Stored(ref mut reader) => reader.read(buf)?,
+            EntryReader::Deflated(ref mut reader) => reader.read(buf)?,
+        };
+        self.hasher.update(&buf[..n]);
+        self.read += n as u64;
+        if self.read > self.entry.uncompressed_size {
+            return Err(invalid("entry is longer than its declared size"));
+        }
+        if n == 0 && !buf.is_empty() {
+            if self.read != self.entry.uncompressed_size {
+                return Err(invalid("entry is shorter than its declared size"));
+            }
+            if self.hasher.clone().finalize() != self.entry.crc {
+                return Err(invalid("CRC-32 mismatch"));
+            }
+        }
+        Ok(n)
+    }
+}
+
+pub(crate) fn find_end_of_central_directory<R: Read + Seek>(
+    reader: &mut R,
+) -> io::Result<(u64, Vec<u8>, Vec<u8>)> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = file_len.min((END_OF_CENTRAL_DIRECTORY_SIZE + MAX_COMMENT_LEN) as u64);
+    reader.seek(SeekFrom::Start(file_len - tail_len))?;
+    let mut tail = vec![0; tail_len as usize];
+    reader.read_exact(&mut tail)?;
+    let last = tail
+        .len()
+        .checked_sub(END_OF_CENTRAL_DIRECTORY_SIZE)
+        .ok_or_else(|| invalid("file is too short to be a zip archive"))?;
+    for pos in (0..=last).rev() {
+        if u32_at(&tail, pos) != END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+            continue;
+        }
+        let comment_len = u16_at(&tail, pos + 20) as usize;
+        if pos + END_OF_CENTRAL_DIRECTORY_SIZE + comment_len == tail.len() {
+            let comment_start = pos + END_OF_CENTRAL_DIRECTORY_SIZE;
+            return Ok((
+                file_len - tail_len + pos as u64,
+                tail[pos..comment_start].to_vec(),
+                tail[comment_start..].to_vec(),
+            ));
+        }
+    }
+    Err(invalid("end of central directory record not found"))
+}
+
+fn read_zip64_record<R: Read + Seek>(
+    reader: &mut R,
+    record_pos: u64,
+) -> io::Result<Option<(u64, u64, u64)>> {
+    let Some(locator_pos) = record_pos.checked_sub(ZIP64_LOCATOR_SIZE as u64) else {
+        return Ok(None);
+    };
+    reader.seek(SeekFrom::Start(locator_pos))?;
+    let mut locator = [0; ZIP64_LOCATOR_SIZE];
+    reader.read_exact(&mut locator)?;
+    if u32_at(&locator, 0) != ZIP64_LOCATOR_SIGNATURE {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::Start(u64_at(&locator, 8)))?;
+    let mut record = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE];
+    reader.read_exact(&mut record)?;
+    if u32_at(&record, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+        return Err(invalid("invalid zip64 end of central directory record"));
+    }
+    Ok(Some((
+        u64_at(&record, 32),
+        u64_at(&record, 40),
+        u64_at(&record, 48),
+    )))
+}
+
+fn parse_central_directory_header(data: &[u8]) -> io::Result<(ZipEntry, usize)> {
+    if data.len() < CENTRAL_DIRECTORY_HEADER_SIZE || u32_at(data, 0) != CENTRAL_DIRECTORY_SIGNATURE
+    {
+        return Err(invalid("invalid central directory header"));
+    }
+    let name_len = u16_at(data, 28) as usize;
+    let extra_len = u16_at(data, 30) as usize;
+    let comment_len = u16_at(data, 32) as usize;
+    let len = CENTRAL_DIRECTORY_HEADER_SIZE + name_len + extra_len + comment_len;
+    if data.len() < len {
+        return Err(invalid("truncated central directory header"));
+    }
+    let flags = u16_at(data, 8);
+    let raw_name = &data[CENTRAL_DIRECTORY_HEADER_SIZE..CENTRAL_DIRECTORY_HEADER_SIZE + name_len];
+    let name = if flags & FLAG_UTF8 > 0 {
+        String::from_utf8(raw_name.to_vec()).map_err(|_| invalid("invalid UTF-8 file name"))?
+    } else {
+        String::from_utf8_lossy(raw_name).into_owned()
+    };
+    let extra_start = CENTRAL_DIRECTORY_HEADER_SIZE + name_len;
+    let extra = &data[extra_start..extra_start + extra_len];
+    let comment = &data[extra_start + extra_len..len];
+    let mut zip64 = zip64_values(extra).into_iter();
+    let mut widen = |value: u32| -> io::Result<u64> {
+        if value as u64 == ZIP64_LIMIT_32 {
+            zip64
+                .next()
+                .ok_or_else(|| invalid("missing zip64 extra field"))
+        } else {
+            Ok(value as u64)
+        }
+    };
+    let uncompressed_size = widen(u32_at(data, 24))?;
+    let compressed_size = widen(u32_at(data, 20))?;
+    let header_offset = widen(u32_at(data, 42))?;
+    let entry = ZipEntry {
+        name,
+        raw_name: raw_name.to_vec(),
+        method: CompressionMethod::from_u16(u16_at(data, 10)),
+        modified: DateTime::from_dos(u16_at(data, 14), u16_at(data, 12)),
+        crc: u32_at(data, 16),
+        compressed_size,
+        uncompressed_size,
+        version_made_by: u16_at(data, 4),
+        flags,
+        internal_attributes: u16_at(data, 36),
+        external_attributes: u32_at(data, 38),
+        header_offset,
+        extra: without_zip64(extra),
+        comment: comment.to_vec(),
+    };
+    Ok((entry, len))
+}
+
+fn zip64_values(mut extra: &[u8]) -> Vec<u64> {
+    while extra.len() >= 4 {
+        let id = u16_at(extra, 0);
+        let len = (u16_at(extra, 2) as usize).min(extra.len() - 4);
+        if id == ZIP64_EXTRA_ID {
+            return extra[4..4 + len]
+                .chunks_exact(8)
+                .map(|value| u64_at(value, 0))
+                .collect();
+        }
+        extra = &extra[4 + len..];
+    }
+    vec![]
+}
+
+fn without_zip64(mut extra: &[u8]) -> Vec<u8> {
+    let mut fields = vec![];
+    while extra.len() >= 4 {
+        let len = u16_at(extra, 2) as usize;
+        if len > extra.len() - 4 {
+            break;
+        }
+        if u16_at(extra, 0) != ZIP64_EXTRA_ID {
+            fields.extend_from_slice(&extra[..4 + len]);
+        }
+        extra = &extra[4 + len..];
+    }
+    fields
+}
+
+fn unsupported_method() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "unsupported compression method")
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+    use crate::zip::{FileOptions, ZipWriter};
+
+    fn sample_archive() -> Vec<u8> {
+        let mut zip = ZipWriter::new(vec![]);
+        zip.add_directory("docs", FileOptions::new()).unwrap();
+        zip.add_file(
+            "docs/carol.txt",
+            &b"Fa-la-la-la, la-la-la-la! ".repeat(100),
+            FileOptions::new().unix_permissions(0o600),
+        )
+        .unwrap();
+        zip.add_file(
+            "stored.bin",
+            &[1, 2, 3],
+            FileOptions::new().compression_method(CompressionMethod::Stored),
+        )
+        .unwrap();
+        zip.set_comment("with PK\x05\x06 inside");
+        zip.finish().unwrap()
+    }
+
+    #[test]
+    fn trivial_test() {
+        let archive = ZipWriter::new(vec![]).finish().unwrap();
+        let zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        assert!(zip.is_empty());
+        assert!(ZipArchive::new(Cursor::new(vec![0; 10])).is_err());
+    }
+
+    #[test]
+    fn test_entries() {
+        let zip = ZipArchive::new(Cursor::new(sample_archive())).unwrap();
+        assert_eq!(3, zip.len());
+        assert_eq!(b"with PK\x05\x06 inside", zip.comment());
+        let names = zip
+            .entries()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["docs/", "docs/carol.txt", "stored.bin"], names);
+        assert!(zip.entries()[0].is_dir());
+        assert_eq!(Some(0o100600), zip.entries()[1].unix_mode());
+        assert_eq!(CompressionMethod::Stored, zip.entries()[2].method);
+    }
+
+    #[test]
+    fn test_read_entries() {
+        let mut zip = ZipArchive::new(Cursor::new(sample_archive())).unwrap();
+        let mut content = vec![];
+        zip.by_name("docs/carol.txt")
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(b"Fa-la-la-la, la-la-la-la! ".repeat(100), content);
+        content.clear();
+        zip.by_index(2).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(vec![1, 2, 3], content);
+        assert!(zip.by_name("missing").is_err());
+        let len = zip.len();
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            zip.by_index(len).unwrap_err().kind()
+        );
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            zip.raw_data(len).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_raw_copy() {
+        let archive = sample_archive();
+        let mut zip = ZipArchive::new(Cursor::new(archive.clone())).unwrap();
+        let mut copy = ZipWriter::new(vec![]);
+        copy.set_raw_comment(zip.comment());
+        for index in 0..zip.len() {
+            let entry = zip.entries()[index].clone();
+            copy.add_raw(&entry, &zip.raw_data(index).unwrap()).unwrap();
+        }
+        assert_eq!(vec![1, 2, 3], zip.raw_data(2).unwrap());
+        let copy = copy.finish().unwrap();
+        assert_eq!(archive, copy);
+        let mut zip = ZipArchive::new(Cursor::new(copy)).unwrap();
+        let mut content = vec![];
+        zip.by_index(1).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(b"Fa-la-la-la, la-la-la-la! ".repeat(100), content);
+    }
+
+    #[test]
+    fn test_deflate64_archive() {
+        let data = b"Deflate64, Hello! ".repeat(5000);
+        let options = FileOptions::new().compression_method(CompressionMethod::Deflate64);
+        let mut zip = ZipWriter::new(vec![]);
+        zip.add_file("hello.txt", &data, options).unwrap();
+        zip.start_file("stream.txt", options).unwrap();
+        zip.write_all(&data).unwrap();
+        let mut zip = ZipArchive::new(Cursor::new(zip.finish().unwrap())).unwrap();
+        for index in 0..2 {
+            let mut content = vec![];
+            let mut file = zip.by_index(index).unwrap();
+            assert_eq!(CompressionMethod::Deflate64, file.entry().method);
+            file.read_to_end(&mut content).unwrap();
+            assert_eq!(data.to_vec(), content);
+        }
+    }
+
+    #[test]
+    fn test_streamed_archive() {
+        let mut zip = ZipWriter::new(vec![]);
+        zip.start_file("carol.txt", FileOptions::new().large_file(true))
+            .unwrap();
+        for _ in 0..100 {
+            zip.write_all(b"Fa-la-la-la, la-la-la-la! ").unwrap();
+        }
+        let stored = FileOptions::new().compression_method(CompressionMethod::Stored);
+        zip.start_file("stored.txt", stored).unwrap();
+        zip.write_all(b"Hello!").unwrap();
+        let mut zip = ZipArchive::new(Cursor::new(zip.finish().unwrap())).unwrap();
+        let mut content = vec![];
+        zip.by_index(0).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(b"Fa-la-la-la, la-la-la-la! ".repeat(100), content);
+        content.clear();
+        zip.by_index(1).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(b"Hello!".to_vec(), content);
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let mut archive = sample_archive();
+        let stored = archive.windows(3).position(|w| w == [1, 2, 3]).unwrap();
+        archive[stored] = 0;
+        let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut content = vec![];
+        let error = zip
+            .by_name("stored.bin")
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+
+    #[test]
+    fn test_zip64_archive() {
+        let mut zip = ZipWriter::new(vec![]);
+        let options = FileOptions::new().compression_method(CompressionMethod::Stored);
+        for i in 0..70_000 {
+            zip.add_file(&format!("{i}"), &[i as u8], options).unwrap();
+        }
+        let mut zip = ZipArchive::new(Cursor::new(zip.finish().unwrap())).unwrap();
+        assert_eq!(70_000, zip.len());
+        let mut content = vec![];
+        zip.by_name("69999")
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(vec![69999_u32 as u8], content);
+    }
+
+    #[test]
+    fn test_oversized_central_directory() {
+        let mut archive = ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE
+            .to_le_bytes()
+            .to_vec();
+        archive.resize(ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE, 0);
+        archive[32..40].copy_from_slice(&1_u64.to_le_bytes());
+        archive[40..48].copy_from_slice(&(1_u64 << 40).to_le_bytes());
+        archive.extend(ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+        archive.resize(ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE + ZIP64_LOCATOR_SIZE, 0);
+        archive.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        archive.extend([0; END_OF_CENTRAL_DIRECTORY_SIZE - 4]);
+        let error = ZipArchive::new(Cursor::new(archive.clone())).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        archive[40..48].copy_from_slice(&u64::MAX.to_le_bytes());
+        archive[48..56].copy_from_slice(&1_u64.to_le_bytes());
+        assert!(ZipArchive::new(Cursor::new(archive)).is_err());
+    }
+
+    #[test]
+    fn test_zip64_extra_field() {
+        let mut extra = vec![0x0a, 0, 4, 0, 1, 2, 3, 4];
+        extra.extend([1, 0, 16, 0]);
+        extra.extend((5_u64 << 32).to_le_bytes());
+        extra.extend(7_u64.to_le_bytes());
+        assert_eq!(vec![5 << 32, 7], zip64_values(&extra));
+        assert!(zip64_values(&[0x0a, 0, 4, 0]).is_empty());
+    }
+
+    #[test]
+    fn test_extract() {
+        let dir = std::env::temp_dir().join(format!("otus_deflate_extract_{}", std::process::id()));
+        let mut zip = ZipArchive::new(Cursor::new(sample_archive())).unwrap();
+        zip.extract(&dir).unwrap();
+        let content = fs::read(dir.join("docs").join("carol.txt")).unwrap();
+        assert_eq!(b"Fa-la-la-la, la-la-la-la! ".repeat(100), content);
+        assert_eq!(vec![1, 2, 3], fs::read(dir.join("stored.bin")).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_enclosed_name() {
+        let mut zip = ZipWriter::new(vec![]);
+        zip.add_file("../evil", b"", FileOptions::new()).unwrap();
+        let mut zip = ZipArchive::new(Cursor::new(zip.finish().unwrap())).unwrap();
+        assert!(zip.by_index(0).unwrap().enclosed_name().is_err());
+    }
+}
@@ -0,0 +1,693 @@
+use std::io::{self, Write};
+
+use crc32fast::Hasher;
+
+use super::{
+    read::ZipEntry, CompressionMethod, DateTime, CENTRAL_DIRECTORY_SIGNATURE,
+    DATA_DESCRIPTOR_SIGNATURE, DEFAULT_DIR_MODE, DEFAULT_FILE_MODE,
+    END_OF_CENTRAL_DIRECTORY_SIGNATURE, FLAG_DATA_DESCRIPTOR, FLAG_UTF8,
+    LOCAL_FILE_HEADER_SIGNATURE, MSDOS_DIRECTORY, S_IFDIR, S_IFREG, VERSION_DEFLATE64,
+    VERSION_MADE_BY_UNIX, VERSION_NEEDED, VERSION_ZIP64, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE, ZIP64_EXTRA_ID, ZIP64_LIMIT_16, ZIP64_LIMIT_32,
+    ZIP64_LOCATOR_SIGNATURE,
+};
+use crate::{encoder::Encoder, writer::DeflateWriter};
+
+const MAX_EXTRA_LEN: usize = u16::MAX as usize - 28;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileOptions {
+    method: CompressionMethod,
+    modified: DateTime,
+    permissions: Option<u32>,
+    encoder: Encoder,
+    large_file: bool,
+    size_and_crc: Option<(u64, u32)>,
+}
+
+impl FileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compression_method(mut self, method: CompressionMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn last_modified(mut self, modified: DateTime) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    pub fn unix_permissions(mut self, mode: u32) -> Self {
+        self.permissions = Some(mode & 0o7777);
+        self
+    }
+
+    pub fn encoder(mut self, encoder: Encoder) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
+    pub fn large_file(mut self, large_file: bool) -> Self {
+        self.large_file = large_file;
+        self
+    }
+
+    pub fn size_and_crc(mut self, size: u64, crc: u32) -> Self {
+        self.size_and_crc = Some((size, crc));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    name: Vec<u8>,
+    method: CompressionMethod,
+    modified: DateTime,
+    flags: u16,
+    zip64: bool,
+    crc: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    version_made_by: u16,
+    internal_attributes: u16,
+    external_attributes: u32,
+    offset: u64,
+    extra: Vec<u8>,
+    local_extra: Vec<u8>,
+    comment: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct StreamingEntry {
+    entry: Entry,
+    hasher: Hasher,
+    compressor: Option<DeflateWriter<Vec<u8>>>,
+    expected: Option<(u64, u32)>,
+}
+
+#[derive(Debug)]
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    entries: Vec<Entry>,
+    current: Option<StreamingEntry>,
+    offset: u64,
+    comment: Vec<u8>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            entries: vec![],
+            current: None,
+            offset: 0,
+            comment: vec![],
+        }
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.set_raw_comment(comment.as_bytes());
+    }
+
+    pub fn set_raw_comment(&mut self, comment: &[u8]) {
+        self.comment = comment.to_vec();
+    }
+
+    pub fn start_file(&mut self, name: &str, options: FileOptions) -> io::Result<()> {
+        self.finish_entry()?;
+        let mode = S_IFREG | options.permissions.unwrap_or(DEFAULT_FILE_MODE);
+        let compressor = options.method.variant().map(|variant| {
+            DeflateWriter::with_encoder(vec![], options.encoder.with_variant(variant))
+        });
+        let flags = match (&compressor, options.size_and_crc) {
+            (None, Some(_)) => 0,
+            (Some(_), Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "size and crc can only be given for stored entries",
+                ))
+            }
+            (_, None) => FLAG_DATA_DESCRIPTOR,
+        };
+        let mut entry = self.new_entry(name, options, mode << 16, flags)?;
+        let header = match options.size_and_crc {
+            Some((size, crc)) => {
+                entry.zip64 |= size >= ZIP64_LIMIT_32;
+                local_file_header(&Entry {
+                    crc,
+                    compressed_size: size,
+                    uncompressed_size: size,
+                    ..entry.clone()
+                })
+            }
+            None => local_file_header(&entry),
+        };
+        self.write_raw(&header)?;
+        self.current = Some(StreamingEntry {
+            entry,
+            hasher: Hasher::new(),
+            compressor,
+            expected: options.size_and_crc,
+        });
+        Ok(())
+    }
+
+    pub fn add_file(&mut self, name: &str, data: &[u8], options: FileOptions) -> io::Result<()> {
+        self.finish_entry()?;
+        let mode = S_IFREG | options.permissions.unwrap_or(DEFAULT_FILE_MODE);
+        let compressed = match options.method.variant() {
+            Some(variant) => options.encoder.with_variant(variant).deflate(data),
+            None => data.to_vec(),
+        };
+        self.add_entry(name, data, &compressed, options, mode << 16)
+    }
+
+    pub fn add_raw(&mut self, entry: &ZipEntry, compressed: &[u8]) -> io::Result<()> {
+        self.add_raw_with_local_extra(entry, &entry.extra, compressed)
+    }
+
+    pub fn add_raw_with_local_extra(
+        &mut self,
+        entry: &ZipEntry,
+        local_extra: &[u8],
+        compressed: &[u8],
+    ) -> io::Result<()> {
+        self.finish_entry()?;
+        let options = FileOptions::new()
+            .compression_method(entry.method)
+            .last_modified(entry.modified);
+        let mut raw = self.new_entry(&entry.name, options, entry.external_attributes, 0)?;
+        if entry.raw_name.len() > u16::MAX as usize
+            || entry.comment.len() > u16::MAX as usize
+            || entry.extra.len() > MAX_EXTRA_LEN
+            || local_extra.len() > MAX_EXTRA_LEN
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "entry field is too long",
+            ));
+        }
+        raw.name = entry.raw_name.clone();
+        raw.extra = entry.extra.clone();
+        raw.local_extra = local_extra.to_vec();
+        raw.comment = entry.comment.clone();
+        raw.internal_attributes = entry.internal_attributes;
+        raw.flags = entry.flags & !FLAG_DATA_DESCRIPTOR;
+        raw.crc = entry.crc;
+        raw.compressed_size = compressed.len() as u64;
+        raw.uncompressed_size = entry.uncompressed_size;
+        raw.version_made_by = entry.version_made_by;
+        let header = local_file_header(&raw);
+        self.write_raw(&header)?;
+        self.write_raw(compressed)?;
+        self.entries.push(raw);
+        Ok(())
+    }
+
+    pub fn add_directory(&mut self, name: &str, options: FileOptions) -> io::Result<()> {
+        self.finish_entry()?;
+        let name = if name.ends_with('/') {
+            name.to_string()
+        } else {
+            format!("{name}/")
+        };
+        let mode = S_IFDIR | options.permissions.unwrap_or(DEFAULT_DIR_MODE);
+        let options = options.compression_method(CompressionMethod::Stored);
+        self.add_entry(&name, &[], &[], options, mode << 16 | MSDOS_DIRECTORY)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_entry()?;
+        let start = self.offset;
+        for entry in &self.entries {
+            let header = central_directory_header(entry);
+            self.writer.write_all(&header)?;
+            self.offset += header.len() as u64;
+        }
+        let size = self.offset - start;
+        let count = self.entries.len() as u64;
+        let mut record = vec![];
+        if count >= ZIP64_LIMIT_16 || size >= ZIP64_LIMIT_32 || start >= ZIP64_LIMIT_32 {
+            let zip64_offset = self.offset;
+            record.extend(ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+            record.extend((ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE as u64 - 12).to_le_bytes());
+            record.extend(VERSION_MADE_BY_UNIX.to_le_bytes());
+            record.extend(VERSION_ZIP64.to_le_bytes());
+            record.extend([0; 8]);
+            record.extend(count.to_le_bytes());
+            record.extend(count.to_le_bytes());
+            record.extend(size.to_le_bytes());
+            record.extend(start.to_le_bytes());
+            record.extend(ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+            record.extend([0; 4]);
+            record.extend(zip64_offset.to_le_bytes());
+            record.extend(1_u32.to_le_bytes());
+        }
+        let comment_len = u16::try_from(self.comment.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "comment is too long"))?;
+        record.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        record.extend([0; 4]);
+        record.extend((count.min(ZIP64_LIMIT_16) as u16).to_le_bytes());
+        record.extend((count.min(ZIP64_LIMIT_16) as u16).to_le_bytes());
+        record.extend((size.min(ZIP64_LIMIT_32) as u32).to_le_bytes());
+        record.extend((start.min(ZIP64_LIMIT_32) as u32).to_le_bytes());
+        record.extend(comment_len.to_le_bytes());
+        record.extend(&self.comment);
+        self.writer.write_all(&record)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn add_entry(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        compressed: &[u8],
+        options: FileOptions,
+        external_attributes: u32,
+    ) -> io::Result<()> {
+        let mut entry = self.new_entry(name, options, external_attributes, 0)?;
+        entry.crc = crc32fast::hash(data);
+        entry.compressed_size = compressed.len() as u64;
+        entry.uncompressed_size = data.len() as u64;
+        let header = local_file_header(&entry);
+        self.write_raw(&header)?;
+        self.write_raw(compressed)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn new_entry(
+        &self,
+        name: &str,
+        options: FileOptions,
+        external_attributes: u32,
+        flags: u16,
+    ) -> io::Result<Entry> {
+        if name.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "file name is too long",
+            ));
+        }
+        Ok(Entry {
+            name: name.as_bytes().to_vec(),
+            method: options.method,
+            modified: options.modified,
+            flags: FLAG_UTF8 | flags,
+            zip64: options.large_file,
+            crc: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            version_made_by: VERSION_MADE_BY_UNIX,
+            internal_attributes: 0,
+            external_attributes,
+            offset: self.offset,
+            extra: vec![],
+            local_extra: vec![],
+            comment: vec![],
+        })
+    }
+
+    fn finish_entry(&mut self) -> io::Result<()> {
+        let Some(current) = self.current.take() else {
+            return Ok(());
+        };
+        let mut entry = current.entry;
+        entry.crc = current.hasher.finalize();
+        if let Some(compressor) = current.compressor {
+            let rest = compressor.finish()?;
+            check_size(&entry, entry.compressed_size + rest.len() as u64, 0)?;
+            entry.compressed_size += rest.len() as u64;
+            self.write_raw(&rest)?;
+        }
+        if let Some(expected) = current.expected {
+            if expected != (entry.uncompressed_size, entry.crc) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "entry does not match its declared size and crc",
+                ));
+            }
+        } else {
+            let mut descriptor = vec![];
+            descriptor.extend(DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+            descriptor.extend(entry.crc.to_le_bytes());
+            if entry.zip64 {
+                descriptor.extend(entry.compressed_size.to_le_bytes());
+                descriptor.extend(entry.uncompressed_size.to_le_bytes());
+            } else {
+                descriptor.extend((entry.compressed_size as u32).to_le_bytes());
+                descriptor.extend((entry.uncompressed_size as u32).to_le_bytes());
+            }
+            self.write_raw(&descriptor)?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let current = self
+            .current
+            .as_mut()
+            .ok_or_else(|| io::Error::other("no file has been started"))?;
+        let entry = &mut current.entry;
+        check_size(entry, 0, entry.uncompressed_size + buf.len() as u64)?;
+        let Some(ref mut compressor) = current.compressor else {
+            let size = entry.uncompressed_size + buf.len() as u64;
+            if current
+                .expected
+                .is_some_and(|(expected, _)| size > expected)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "entry is larger than its declared size",
+                ));
+            }
+            current.hasher.update(buf);
+            entry.compressed_size = size;
+            entry.uncompressed_size = size;
+            self.write_raw(buf)?;
+            return Ok(buf.len());
+        };
+        compressor.write_all(buf)?;
+        current.hasher.update(buf);
+        entry.uncompressed_size += buf.len() as u64;
+        let compressed = std::mem::take(compressor.get_mut());
+        check_size(entry, entry.compressed_size + compressed.len() as u64, 0)?;
+        entry.compressed_size += compressed.len() as u64;
+        self.write_raw(&compressed)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn check_size(entry: &Entry, compressed_size: u64, uncompressed_size: u64) -> io::Result<()> {
+    if !entry.zip64 && (compressed_size >= ZIP64_LIMIT_32 || uncompressed_size >= ZIP64_LIMIT_32) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "entry is too large, use FileOptions::large_file",
+        ));
+    }
+    Ok(())
+}
+
+fn local_file_header(entry: &Entry) -> Vec<u8> {
+    let zip64 = entry.zip64
+        || entry.compressed_size >= ZIP64_LIMIT_32
+        || entry.uncompressed_size >= ZIP64_LIMIT_32;
+    let mut extra = if zip64 {
+        zip64_extra(&[entry.uncompressed_size, entry.compressed_size])
+    } else {
+        vec![]
+    };
+    extra.extend(&entry.local_extra);
+    let mut header = vec![];
+    header.extend(LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+    header.extend(version_needed(entry.method, zip64).to_le_bytes());
+    common_fields(entry, &mut header);
+    header.extend(saturate_32(entry.compressed_size, zip64).to_le_bytes());
+    header.extend(saturate_32(entry.uncompressed_size, zip64).to_le_bytes());
+    header.extend((entry.name.len() as u16).to_le_bytes());
+    header.extend((extra.len() as u16).to_le_bytes());
+    header.extend(&entry.name);
+    header.extend(extra);
+    header
+}
+
+fn central_directory_header(entry: &Entry) -> Vec<u8> {
+    let large = [entry.uncompressed_size, entry.compressed_size, entry.offset]
+        .into_iter()
+        .filter(|value| *value >= ZIP64_LIMIT_32)
+        .collect::<Vec<_>>();
+    let mut extra = if large.is_empty() {
+        vec![]
+    } else {
+        zip64_extra(&large)
+    };
+    extra.extend(&entry.extra);
+    let mut header = vec![];
+    header.extend(CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    header.extend(entry.version_made_by.to_le_bytes());
+    header.extend(version_needed(entry.method, !large.is_empty()).to_le_bytes());
+    common_fields(entry, &mut header);
+    header.extend(saturate_32(entry.compressed_size, false).to_le_bytes());
+    header.extend(saturate_32(entry.uncompressed_size, false).to_le_bytes());
+    header.extend((entry.name.len() as u16).to_le_bytes());
+    header.extend((extra.len() as u16).to_le_bytes());
+    header.extend((entry.comment.len() as u16).to_le_bytes());
+    header.extend(0_u16.to_le_bytes());
+    header.extend(entry.internal_attributes.to_le_bytes());
+    header.extend(entry.external_attributes.to_le_bytes());
+    header.extend(saturate_32(entry.offset, false).to_le_bytes());
+    header.extend(&entry.name);
+    header.extend(extra);
+    header.extend(&entry.comment);
+    header
+}
+
+fn common_fields(entry: &Entry, header: &mut Vec<u8>) {
+    let (date, time) = entry.modified.to_dos();
+    header.extend(entry.flags.to_le_bytes());
+    header.extend(entry.method.to_u16().to_le_bytes());
+    header.extend(time.to_le_bytes());
+    header.extend(date.to_le_bytes());
+    header.extend(entry.crc.to_le_bytes());
+}
+
+fn zip64_extra(values: &[u64]) -> Vec<u8> {
+    let mut extra = vec![];
+    extra.extend(ZIP64_EXTRA_ID.to_le_bytes());
+    extra.extend((8 * values.len() as u16).to_le_bytes());
+    for value in values {
+        extra.extend(value.to_le_bytes());
+    }
+    extra
+}
+
+fn version_needed(method: CompressionMethod, zip64: bool) -> u16 {
+    match method {
+        _ if zip64 => VERSION_ZIP64,
+        CompressionMethod::Deflate64 => VERSION_DEFLATE64,
+        _ => VERSION_NEEDED,
+    }
+}
+
+fn saturate_32(value: u64, force: bool) -> u32 {
+    if force {
+        ZIP64_LIMIT_32 as u32
+    } else {
+        value.min(ZIP64_LIMIT_32) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::{u16_at, u32_at, u64_at};
+
+    #[test]
+    fn trivial_test() {
+        let archive = ZipWriter::new(vec![]).finish().unwrap();
+        assert_eq!(22, archive.len());
+        assert_eq!(END_OF_CENTRAL_DIRECTORY_SIGNATURE, u32_at(&archive, 0));
+    }
+
+    #[test]
+    fn test_stored_entry() {
+        let mut zip = ZipWriter::new(vec![]);
+        let options = FileOptions::new()
+            .compression_method(CompressionMethod::Stored)
+            .unix_permissions(0o755);
+        zip.add_file("hello.txt", b"Hello!", options).unwrap();
+        let archive = zip.finish().unwrap();
+        assert_eq!(LOCAL_FILE_HEADER_SIGNATURE, u32_at(&archive, 0));
+        assert_eq!(FLAG_UTF8, u16_at(&archive, 6));
+        assert_eq!(0, u16_at(&archive, 8));
+        assert_eq!(crc32fast::hash(b"Hello!"), u32_at(&archive, 14));
+        assert_eq!(6, u32_at(&archive, 18));
+        assert_eq!(b"hello.txt", &archive[30..39]);
+        assert_eq!(b"Hello!", &archive[39..45]);
+        assert_eq!(CENTRAL_DIRECTORY_SIGNATURE, u32_at(&archive, 45));
+        assert_eq!(0o100755 << 16, u32_at(&archive, 45 + 38));
+        let eocd = archive.len() - 22;
+        assert_eq!(1, u16_at(&archive, eocd + 10));
+        assert_eq!(45, u32_at(&archive, eocd + 16));
+    }
+
+    #[test]
+    fn test_zip64_headers() {
+        let entry = Entry {
+            name: b"huge.bin".to_vec(),
+            method: CompressionMethod::Deflated,
+            modified: DateTime::default(),
+            flags: FLAG_UTF8,
+            zip64: false,
+            crc: 0,
+            compressed_size: 5 << 30,
+            uncompressed_size: 10 << 30,
+            version_made_by: VERSION_MADE_BY_UNIX,
+            internal_attributes: 0,
+            external_attributes: 0,
+            offset: 100,
+            extra: vec![],
+            local_extra: vec![],
+            comment: vec![],
+        };
+        let local = local_file_header(&entry);
+        assert_eq!(VERSION_ZIP64, u16_at(&local, 4));
+        assert_eq!(u32::MAX, u32_at(&local, 18));
+        assert_eq!(u32::MAX, u32_at(&local, 22));
+        assert_eq!(20, u16_at(&local, 28));
+        assert_eq!(ZIP64_EXTRA_ID, u16_at(&local, 38));
+        assert_eq!(10 << 30, u64_at(&local, 42));
+        assert_eq!(5 << 30, u64_at(&local, 50));
+        let central = central_directory_header(&Entry {
+            compressed_size: 10,
+            offset: 6 << 30,
+            ..entry.clone()
+        });
+        assert_eq!(10, u32_at(&central, 20));
+        assert_eq!(u32::MAX, u32_at(&central, 24));
+        assert_eq!(u32::MAX, u32_at(&central, 42));
+        assert_eq!(20, u16_at(&central, 30));
+        assert_eq!(10 << 30, u64_at(&central, 58));
+        assert_eq!(6 << 30, u64_at(&central, 66));
+    }
+
+    #[test]
+    fn test_zip64_end_of_central_directory() {
+        let mut zip = ZipWriter::new(vec![]);
+        let options = FileOptions::new().compression_method(CompressionMethod::Stored);
+        for i in 0..=u16::MAX as usize {
+            zip.add_file(&format!("{i:x}"), b"", options).unwrap();
+        }
+        let archive = zip.finish().unwrap();
+        let eocd = archive.len() - 22;
+        assert_eq!(u16::MAX, u16_at(&archive, eocd + 10));
+        let locator = eocd - 20;
+        assert_eq!(ZIP64_LOCATOR_SIGNATURE, u32_at(&archive, locator));
+        let record = u64_at(&archive, locator + 8) as usize;
+        assert_eq!(locator - 56, record);
+        assert_eq!(
+            ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+            u32_at(&archive, record)
+        );
+        assert_eq!(1 << 16, u64_at(&archive, record + 32));
+    }
+
+    #[test]
+    fn test_streaming_entries() {
+        let data = b"Fa-la-la-la, la-la-la-la!".repeat(10);
+        let mut zip = ZipWriter::new(vec![]);
+        assert!(zip.write_all(b"nowhere").is_err());
+        zip.start_file("carol.txt", FileOptions::new()).unwrap();
+        for chunk in data.chunks(7) {
+            zip.write_all(chunk).unwrap();
+        }
+        let stored = FileOptions::new()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(true)
+            .size_and_crc(6, crc32fast::hash(b"Hello!"));
+        zip.start_file("stored.txt", stored).unwrap();
+        zip.write_all(b"Hello!").unwrap();
+        let archive = zip.finish().unwrap();
+        assert_eq!(FLAG_UTF8 | FLAG_DATA_DESCRIPTOR, u16_at(&archive, 6));
+        assert_eq!(0, u32_at(&archive, 14));
+        assert_eq!(0, u32_at(&archive, 18));
+        let descriptor = archive
+            .windows(4)
+            .position(|w| w == DATA_DESCRIPTOR_SIGNATURE.to_le_bytes())
+            .unwrap();
+        let compressed_size = u32_at(&archive, descriptor + 8);
+        assert_eq!(descriptor - 39, compressed_size as usize);
+        assert_eq!(crc32fast::hash(&data), u32_at(&archive, descriptor + 4));
+        assert_eq!(data.len() as u32, u32_at(&archive, descriptor + 12));
+        let second = descriptor + 16;
+        assert_eq!(LOCAL_FILE_HEADER_SIGNATURE, u32_at(&archive, second));
+        assert_eq!(VERSION_ZIP64, u16_at(&archive, second + 4));
+        assert_eq!(FLAG_UTF8, u16_at(&archive, second + 6));
+        assert_eq!(crc32fast::hash(b"Hello!"), u32_at(&archive, second + 14));
+        assert_eq!(6, u64_at(&archive, second + 30 + 10 + 4));
+        assert_eq!(6, u64_at(&archive, second + 30 + 10 + 12));
+        let data = second + 30 + 10 + 20;
+        assert_eq!(b"Hello!", &archive[data..data + 6]);
+        assert_eq!(CENTRAL_DIRECTORY_SIGNATURE, u32_at(&archive, data + 6));
+    }
+
+    #[test]
+    fn test_stored_streaming_entry() {
+        let stored = FileOptions::new().compression_method(CompressionMethod::Stored);
+        let mut zip = ZipWriter::new(vec![]);
+        zip.start_file("stored.txt", stored).unwrap();
+        zip.write_all(b"Hello").unwrap();
+        assert_eq!(b"Hello", &zip.writer[zip.writer.len() - 5..]);
+        zip.write_all(b"!").unwrap();
+        let archive = zip.finish().unwrap();
+        assert_eq!(FLAG_UTF8 | FLAG_DATA_DESCRIPTOR, u16_at(&archive, 6));
+        assert_eq!(b"Hello!", &archive[40..46]);
+        assert_eq!(DATA_DESCRIPTOR_SIGNATURE, u32_at(&archive, 46));
+        assert_eq!(crc32fast::hash(b"Hello!"), u32_at(&archive, 50));
+        assert_eq!(6, u32_at(&archive, 54));
+        assert_eq!(6, u32_at(&archive, 58));
+        let mut zip = ZipWriter::new(vec![]);
+        zip.start_file("stored.txt", stored.size_and_crc(6, 0))
+            .unwrap();
+        zip.write_all(b"Hello!").unwrap();
+        assert!(zip.write_all(b"!").is_err());
+        assert!(zip.finish().is_err());
+        let mut zip = ZipWriter::new(vec![]);
+        assert!(zip
+            .start_file("carol.txt", FileOptions::new().size_and_crc(6, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_size_limit_is_checked_before_writing() {
+        let mut zip = ZipWriter::new(vec![]);
+        zip.start_file("huge.bin", FileOptions::new()).unwrap();
+        zip.write_all(b"Fa-la-la").unwrap();
+        let written = zip.writer.len();
+        let current = zip.current.as_mut().unwrap();
+        current.entry.uncompressed_size = ZIP64_LIMIT_32 - 4;
+        let err = zip.write_all(b"la-la-la").unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        assert_eq!(written, zip.writer.len());
+        let mut zip = ZipWriter::new(vec![]);
+        let options = FileOptions::new().large_file(true);
+        zip.start_file("huge.bin", options).unwrap();
+        let current = zip.current.as_mut().unwrap();
+        current.entry.uncompressed_size = ZIP64_LIMIT_32 - 4;
+        zip.write_all(b"la-la-la").unwrap();
+    }
+
+    #[test]
+    fn test_deflated_entry_and_directory() {
+        let data = b"Fa-la-la-la, la-la-la-la!".repeat(10);
+        let mut zip = ZipWriter::new(vec![]);
+        zip.add_directory("songs", FileOptions::new()).unwrap();
+        zip.add_file("songs/carol.txt", &data, FileOptions::new())
+            .unwrap();
+        zip.set_comment("Christmas");
+        let archive = zip.finish().unwrap();
+        assert_eq!(b"songs/", &archive[30..36]);
+        let second = 36;
+        assert_eq!(8, u16_at(&archive, second + 8));
+        assert!((u32_at(&archive, second + 18) as usize) < data.len());
+        assert_eq!(data.len() as u32, u32_at(&archive, second + 22));
+        assert_eq!(b"Christmas", &archive[archive.len() - 9..]);
+    }
+}
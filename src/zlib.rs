@@ -0,0 +1,110 @@
+use std::io;
+
+pub const CM_DEFLATE: u8 = 8;
+pub const HEADER: [u8; 2] = [0x78, 0x9c];
+
+const MOD_ADLER: u32 = 65521;
+const NMAX: usize = 5552;
+const FDICT: u8 = 0x20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Adler32 {
+    pub fn new() -> Self {
+        Self::from_checksum(1)
+    }
+
+    pub fn from_checksum(checksum: u32) -> Self {
+        Self {
+            a: checksum & 0xffff,
+            b: checksum >> 16,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for chunk in data.chunks(NMAX) {
+            for &byte in chunk {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= MOD_ADLER;
+            self.b %= MOD_ADLER;
+        }
+    }
+
+    pub fn finalize(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut adler = Adler32::new();
+    adler.update(data);
+    adler.finalize()
+}
+
+pub fn is_header(header: [u8; 2]) -> bool {
+    let cmf = header[0];
+    cmf & 0x0f == CM_DEFLATE && cmf >> 4 <= 7 && u16::from_be_bytes(header).is_multiple_of(31)
+}
+
+pub fn check_header(header: [u8; 2]) -> io::Result<()> {
+    if !is_header(header) {
+        return Err(invalid("invalid zlib header"));
+    }
+    if header[1] & FDICT != 0 {
+        return Err(invalid("zlib preset dictionaries are not supported"));
+    }
+    Ok(())
+}
+
+pub fn trailer(adler: u32) -> [u8; 4] {
+    adler.to_be_bytes()
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trivial_test() {
+        assert_eq!(1, adler32(b""));
+        assert!(check_header(HEADER).is_ok());
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(0x11e60398, adler32(b"Wikipedia"));
+        let data = vec![0xff; 100_000];
+        let mut adler = Adler32::new();
+        for chunk in data.chunks(777) {
+            adler.update(chunk);
+        }
+        assert_eq!(adler32(&data), adler.finalize());
+        assert_eq!(0x149a_302c, adler32(&data));
+    }
+
+    #[test]
+    fn test_check_header() {
+        for header in [[0x78, 0x01], [0x78, 0x5e], [0x78, 0xda], [0x08, 0x1d]] {
+            assert!(check_header(header).is_ok());
+        }
+        assert!(check_header([0x78, 0x9d]).is_err());
+        assert!(check_header([0x79, 0x9c]).is_err());
+        assert!(check_header([0x78, 0xbb]).is_err());
+    }
+}
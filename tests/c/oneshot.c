@@ -0,0 +1,61 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "otus_deflate.h"
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+static const uint8_t ZLIB_STREAM[] = {
+    0x78, 0xda, 0x73, 0x4b, 0xd4, 0xcd, 0x81, 0x22, 0x1d, 0x85, 0x1c, 0x04,
+    0x47, 0x51, 0xc1, 0x8d, 0xaa, 0x32, 0x00, 0x80, 0x42, 0x1f, 0x75,
+};
+
+int main(void) {
+    const char *text = "Fa-la-la-la, la-la-la-la! Fa-la-la-la, la-la-la-la! "
+                       "Fa-la-la-la, la-la-la-la! Fa-la-la-la, la-la-la-la! ";
+    unsigned long len = strlen(text);
+    uint8_t compressed[256];
+    uint8_t plain[256];
+    unsigned long compressed_len = sizeof(compressed);
+    unsigned long plain_len = sizeof(plain);
+
+    CHECK(strcmp(zlibVersion(), ZLIB_VERSION) == 0);
+    CHECK(compressBound(len) >= len);
+    CHECK(crc32(0, (const uint8_t *)"123456789", 9) == 0xcbf43926);
+    CHECK(crc32(crc32(0, (const uint8_t *)"1234", 4), (const uint8_t *)"56789", 5) == 0xcbf43926);
+    CHECK(adler32(1, (const uint8_t *)"Wikipedia", 9) == 0x11e60398);
+    CHECK(adler32(0, NULL, 0) == 1);
+
+    CHECK(compress2(compressed, &compressed_len, (const uint8_t *)text, len, Z_BEST_COMPRESSION) == Z_OK);
+    CHECK(compressed_len < len);
+    CHECK(uncompress(plain, &plain_len, compressed, compressed_len) == Z_OK);
+    CHECK(plain_len == len && memcmp(plain, text, len) == 0);
+
+    compressed_len = sizeof(compressed);
+    CHECK(compress2(compressed, &compressed_len, (const uint8_t *)text, len, Z_NO_COMPRESSION) == Z_OK);
+    CHECK(compressed_len > len);
+    plain_len = sizeof(plain);
+    CHECK(uncompress(plain, &plain_len, compressed, compressed_len) == Z_OK);
+    CHECK(plain_len == len && memcmp(plain, text, len) == 0);
+
+    plain_len = sizeof(plain);
+    CHECK(uncompress(plain, &plain_len, ZLIB_STREAM, sizeof(ZLIB_STREAM)) == Z_OK);
+    CHECK(plain_len == 104 && memcmp(plain, text, len) == 0);
+
+    plain_len = 10;
+    CHECK(uncompress(plain, &plain_len, ZLIB_STREAM, sizeof(ZLIB_STREAM)) == Z_BUF_ERROR);
+    compressed_len = 4;
+    CHECK(compress2(compressed, &compressed_len, (const uint8_t *)text, len, 6) == Z_BUF_ERROR);
+
+    memcpy(compressed, ZLIB_STREAM, sizeof(ZLIB_STREAM));
+    compressed[sizeof(ZLIB_STREAM) - 1] ^= 1;
+    plain_len = sizeof(plain);
+    CHECK(uncompress(plain, &plain_len, compressed, sizeof(ZLIB_STREAM)) == Z_DATA_ERROR);
+    return 0;
+}
@@ -0,0 +1,13 @@
+#!/bin/sh
+set -eu
+
+cd "$(dirname "$0")/../.."
+cargo rustc --lib --release --features capi --crate-type cdylib
+out=target/c-tests
+mkdir -p "$out"
+for test in tests/c/*.c; do
+    name=$(basename "$test" .c)
+    ${CC:-cc} -std=c99 -Wall -Wextra -Werror -Iinclude "$test" -o "$out/$name" -Ltarget/release -lotus_deflate
+    LD_LIBRARY_PATH=target/release "$out/$name"
+    echo "$name: ok"
+done
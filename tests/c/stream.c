@@ -0,0 +1,115 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "otus_deflate.h"
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+#define CHUNK 7
+
+static const uint8_t GZIP_MEMBER[] = {
+    0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x73, 0x49,
+    0x4d, 0xcb, 0x49, 0x2c, 0x49, 0xd5, 0x51, 0xf0, 0x48, 0xcd, 0xc9, 0xc9,
+    0x57, 0xe4, 0x02, 0x00, 0x59, 0x77, 0x44, 0xfd, 0x10, 0x00, 0x00, 0x00,
+};
+
+static int deflate_chunked(int window_bits, const uint8_t *in, size_t in_len, uint8_t *out, size_t *out_len) {
+    z_stream strm;
+    size_t fed = 0;
+    int ret;
+
+    memset(&strm, 0, sizeof(strm));
+    CHECK(deflateInit2(&strm, 6, Z_DEFLATED, window_bits, 8, Z_DEFAULT_STRATEGY) == Z_OK);
+    strm.next_out = out;
+    do {
+        size_t n = in_len - fed < CHUNK ? in_len - fed : CHUNK;
+        strm.next_in = (uint8_t *)in + fed;
+        strm.avail_in = (unsigned)n;
+        fed += n;
+        do {
+            strm.avail_out = CHUNK;
+            ret = deflate(&strm, fed == in_len ? Z_FINISH : Z_NO_FLUSH);
+            CHECK(ret == Z_OK || ret == Z_STREAM_END || ret == Z_BUF_ERROR);
+        } while (strm.avail_out == 0);
+        CHECK(strm.avail_in == 0);
+    } while (fed < in_len);
+    CHECK(ret == Z_STREAM_END);
+    CHECK(strm.total_in == in_len);
+    *out_len = strm.total_out;
+    CHECK(deflateEnd(&strm) == Z_OK);
+    return 0;
+}
+
+static int inflate_chunked(int window_bits, const uint8_t *in, size_t in_len, uint8_t *out, size_t *out_len) {
+    z_stream strm;
+    int ret = Z_OK;
+
+    memset(&strm, 0, sizeof(strm));
+    CHECK(inflateInit2(&strm, window_bits) == Z_OK);
+    strm.next_in = (uint8_t *)in;
+    strm.next_out = out;
+    while (ret != Z_STREAM_END) {
+        size_t left = in_len - strm.total_in;
+        strm.avail_in = (unsigned)(left < CHUNK ? left : CHUNK);
+        strm.avail_out = CHUNK;
+        ret = inflate(&strm, Z_NO_FLUSH);
+        CHECK(ret == Z_OK || ret == Z_STREAM_END);
+    }
+    CHECK(strm.total_in == in_len);
+    *out_len = strm.total_out;
+    CHECK(inflateEnd(&strm) == Z_OK);
+    return 0;
+}
+
+static int round_trip(int deflate_bits, int inflate_bits, const char *text) {
+    uint8_t compressed[1024];
+    uint8_t plain[1024];
+    size_t compressed_len, plain_len;
+    size_t len = strlen(text);
+
+    CHECK(deflate_chunked(deflate_bits, (const uint8_t *)text, len, compressed, &compressed_len) == 0);
+    CHECK(inflate_chunked(inflate_bits, compressed, compressed_len, plain, &plain_len) == 0);
+    CHECK(plain_len == len && memcmp(plain, text, len) == 0);
+    return 0;
+}
+
+int main(void) {
+    const char *text = "Hickory, dickory, dock. The mouse ran up the clock. "
+                       "The clock struck one, the mouse ran down. "
+                       "Hickory, dickory, dock.";
+    uint8_t plain[64];
+    size_t plain_len;
+    z_stream strm;
+
+    CHECK(round_trip(MAX_WBITS, MAX_WBITS, text) == 0);
+    CHECK(round_trip(-MAX_WBITS, -MAX_WBITS, text) == 0);
+    CHECK(round_trip(MAX_WBITS + 16, MAX_WBITS + 16, text) == 0);
+    CHECK(round_trip(MAX_WBITS + 16, MAX_WBITS + 32, text) == 0);
+    CHECK(round_trip(MAX_WBITS, MAX_WBITS + 32, text) == 0);
+    CHECK(round_trip(MAX_WBITS, 0, "") == 0);
+
+    CHECK(inflate_chunked(MAX_WBITS + 16, GZIP_MEMBER, sizeof(GZIP_MEMBER), plain, &plain_len) == 0);
+    CHECK(plain_len == 16 && memcmp(plain, "Deflate, Hello!\n", 16) == 0);
+
+    memset(&strm, 0, sizeof(strm));
+    CHECK(inflateInit2(&strm, MAX_WBITS) == Z_OK);
+    strm.next_in = (uint8_t *)GZIP_MEMBER;
+    strm.avail_in = sizeof(GZIP_MEMBER);
+    strm.next_out = plain;
+    strm.avail_out = sizeof(plain);
+    CHECK(inflate(&strm, Z_NO_FLUSH) == Z_DATA_ERROR);
+    CHECK(strm.msg != NULL);
+    CHECK(inflateEnd(&strm) == Z_OK);
+
+    memset(&strm, 0, sizeof(strm));
+    CHECK(deflateInit2(&strm, 6, Z_DEFLATED, 7, 8, Z_DEFAULT_STRATEGY) == Z_STREAM_ERROR);
+    CHECK(deflateInit_(&strm, 6, "0.9", (int)sizeof(z_stream)) == Z_VERSION_ERROR);
+    CHECK(inflateInit_(&strm, ZLIB_VERSION, 1) == Z_VERSION_ERROR);
+    return 0;
+}